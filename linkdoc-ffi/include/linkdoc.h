@@ -0,0 +1,72 @@
+/*
+ * A C interface to linkdoc. Build the linkdoc-ffi crate to get
+ * liblinkdoc_ffi.so (or .dylib, .dll, .a) and link against it.
+ *
+ *     LinkdocCrawl *crawl = linkdoc_crawl_start("https://example.com", 8);
+ *     LinkdocResult *result;
+ *     int status;
+ *     while ((status = linkdoc_crawl_poll(crawl, &result)) != LINKDOC_DONE) {
+ *         if (status == LINKDOC_PENDING) {
+ *             // Nothing yet: do something else, then poll again.
+ *             continue;
+ *         }
+ *         if (!result->ok) {
+ *             printf("%s -> %s (%s)\n", result->referrer, result->url, result->reason);
+ *         }
+ *         linkdoc_result_free(result);
+ *     }
+ *     linkdoc_crawl_free(crawl);
+ *
+ * A crawl runs on its own threads, and polling never blocks. Only one
+ * thread should poll a crawl at a time.
+ */
+
+#ifndef LINKDOC_H
+#define LINKDOC_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define LINKDOC_RESULT 1
+#define LINKDOC_PENDING 0
+#define LINKDOC_DONE (-1)
+
+typedef struct LinkdocCrawl LinkdocCrawl;
+
+/* Strings are UTF-8, and owned by the result. */
+typedef struct LinkdocResult {
+    char *url;
+    /* The first page linking to url. */
+    char *referrer;
+    /* 1 if the URL is accessible, 0 otherwise. */
+    int ok;
+    /* The HTTP status, or 0 if there was no response. */
+    int status;
+    /* Why the URL isn't accessible, or "" if it is. */
+    char *reason;
+} LinkdocResult;
+
+/* Start crawling from url, checking up to concurrency URLs at once.
+ * Returns NULL if url isn't a valid absolute URL. */
+LinkdocCrawl *linkdoc_crawl_start(const char *url, size_t concurrency);
+
+/* Returns LINKDOC_RESULT and sets *result, LINKDOC_PENDING if there's
+ * no result yet, or LINKDOC_DONE once the crawl is over. */
+int linkdoc_crawl_poll(LinkdocCrawl *crawl, LinkdocResult **result);
+
+/* Stop the crawl. It still needs freeing. */
+void linkdoc_crawl_cancel(LinkdocCrawl *crawl);
+
+/* Cancel the crawl if it's still going, and free it. */
+void linkdoc_crawl_free(LinkdocCrawl *crawl);
+
+void linkdoc_result_free(LinkdocResult *result);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* LINKDOC_H */
@@ -0,0 +1,178 @@
+//! A C interface to linkdoc, so editors and desktop tools can check
+//! links without linking against Rust. See `include/linkdoc.h`.
+//!
+//! A crawl runs on its own threads. Callers poll for results, which
+//! never blocks, so it's safe to call from a UI event loop.
+
+use crossbeam_channel::{unbounded, Receiver, TryRecvError};
+use linkdoc::{CancelHandle, CheckResult, Crawler};
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+use std::thread;
+use url::Url;
+
+/// A crawl in progress, opaque to C.
+pub struct LinkdocCrawl {
+    results: Receiver<CheckResult>,
+    cancel: CancelHandle,
+}
+
+/// The result of checking a single URL. Strings are UTF-8 and owned by
+/// the result, so copy them before calling `linkdoc_result_free`.
+#[repr(C)]
+pub struct LinkdocResult {
+    pub url: *mut c_char,
+    /// The first page linking to `url`.
+    pub referrer: *mut c_char,
+    /// 1 if the URL is accessible, 0 otherwise.
+    pub ok: c_int,
+    /// The HTTP status, or 0 if we didn't get a response.
+    pub status: c_int,
+    /// Why the URL isn't accessible, or "" if it is.
+    pub reason: *mut c_char,
+}
+
+/// `linkdoc_crawl_poll` wrote a result.
+pub const LINKDOC_RESULT: c_int = 1;
+/// No result yet, poll again later.
+pub const LINKDOC_PENDING: c_int = 0;
+/// The crawl has finished, or was cancelled.
+pub const LINKDOC_DONE: c_int = -1;
+
+/// Convert `s` for C, dropping any interior NULs rather than failing.
+fn c_string(s: &str) -> *mut c_char {
+    let s = CString::new(s.replace('\0', "")).unwrap();
+    s.into_raw()
+}
+
+/// Start crawling from `url`, checking up to `concurrency` URLs at
+/// once. Returns NULL if `url` isn't a valid absolute URL or the crawl
+/// couldn't start.
+///
+/// # Safety
+///
+/// `url` must be a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn linkdoc_crawl_start(
+    url: *const c_char,
+    concurrency: usize,
+) -> *mut LinkdocCrawl {
+    if url.is_null() {
+        return ptr::null_mut();
+    }
+    let url = match CStr::from_ptr(url).to_str().map(Url::parse) {
+        Ok(Ok(url)) => url,
+        _ => return ptr::null_mut(),
+    };
+    let crawler = match Crawler::builder()
+        .seed(&url)
+        .concurrency(concurrency)
+        .build()
+    {
+        Ok(crawler) => crawler,
+        Err(_) => return ptr::null_mut(),
+    };
+    let cancel = crawler.cancel_handle();
+
+    // Iterating blocks, so do it on a thread of its own and hand
+    // results over a channel we can poll.
+    let (results_s, results_r) = unbounded();
+    thread::spawn(move || {
+        for result in crawler {
+            if results_s.send(result).is_err() {
+                break;
+            }
+        }
+    });
+
+    Box::into_raw(Box::new(LinkdocCrawl {
+        results: results_r,
+        cancel,
+    }))
+}
+
+/// Take the next result, if there is one. Returns `LINKDOC_RESULT` and
+/// sets `*result` to a result to free with `linkdoc_result_free`,
+/// `LINKDOC_PENDING` if there's nothing yet, or `LINKDOC_DONE` once the
+/// crawl is over.
+///
+/// # Safety
+///
+/// `crawl` must come from `linkdoc_crawl_start` and not have been
+/// freed, and `result` must be valid to write to.
+#[no_mangle]
+pub unsafe extern "C" fn linkdoc_crawl_poll(
+    crawl: *mut LinkdocCrawl,
+    result: *mut *mut LinkdocResult,
+) -> c_int {
+    if crawl.is_null() || result.is_null() {
+        return LINKDOC_DONE;
+    }
+    let state = match (*crawl).results.try_recv() {
+        Ok(state) => state,
+        Err(TryRecvError::Empty) => return LINKDOC_PENDING,
+        Err(TryRecvError::Disconnected) => return LINKDOC_DONE,
+    };
+    let reason = if state.is_accessible() {
+        String::new()
+    } else {
+        state.reason()
+    };
+    *result = Box::into_raw(Box::new(LinkdocResult {
+        url: c_string(state.url_str()),
+        referrer: c_string(state.referrer()),
+        ok: state.is_accessible() as c_int,
+        status: state
+            .status
+            .map_or(0, |status| c_int::from(status.as_u16())),
+        reason: c_string(&reason),
+    }));
+    LINKDOC_RESULT
+}
+
+/// Stop the crawl. Polling returns `LINKDOC_DONE` soon after. The
+/// crawl still needs freeing.
+///
+/// # Safety
+///
+/// `crawl` must come from `linkdoc_crawl_start` and not have been
+/// freed.
+#[no_mangle]
+pub unsafe extern "C" fn linkdoc_crawl_cancel(crawl: *mut LinkdocCrawl) {
+    if !crawl.is_null() {
+        (*crawl).cancel.cancel();
+    }
+}
+
+/// Cancel the crawl if it's still going, and free it.
+///
+/// # Safety
+///
+/// `crawl` must come from `linkdoc_crawl_start`, or be NULL, and not
+/// be used again.
+#[no_mangle]
+pub unsafe extern "C" fn linkdoc_crawl_free(crawl: *mut LinkdocCrawl) {
+    if crawl.is_null() {
+        return;
+    }
+    let crawl = Box::from_raw(crawl);
+    crawl.cancel.cancel();
+}
+
+/// Free a result from `linkdoc_crawl_poll`.
+///
+/// # Safety
+///
+/// `result` must come from `linkdoc_crawl_poll`, or be NULL, and not
+/// be used again.
+#[no_mangle]
+pub unsafe extern "C" fn linkdoc_result_free(result: *mut LinkdocResult) {
+    if result.is_null() {
+        return;
+    }
+    let result = Box::from_raw(result);
+    drop(CString::from_raw(result.url));
+    drop(CString::from_raw(result.referrer));
+    drop(CString::from_raw(result.reason));
+}
@@ -0,0 +1,141 @@
+use chrono::Utc;
+use reqwest::header::{CONTENT_ENCODING, CONTENT_LENGTH, TRANSFER_ENCODING};
+use reqwest::Response;
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use url::Url;
+use uuid::Uuid;
+
+/// Writes fetched pages to a WARC file, so a crawl doubles as an
+/// archive of the site.
+pub struct WarcWriter {
+    file: Mutex<BufWriter<File>>,
+}
+
+fn warc_date() -> String {
+    Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+impl WarcWriter {
+    pub fn create(path: &Path) -> io::Result<WarcWriter> {
+        let writer = WarcWriter {
+            file: Mutex::new(BufWriter::new(File::create(path)?)),
+        };
+
+        let info = format!(
+            "software: linkdoc/{}\r\nformat: WARC File Format 1.1\r\n",
+            env!("CARGO_PKG_VERSION")
+        );
+        writer.write_record("warcinfo", None, "application/warc-fields", info.as_bytes())?;
+        Ok(writer)
+    }
+
+    fn write_record(
+        &self,
+        warc_type: &str,
+        target_uri: Option<&Url>,
+        content_type: &str,
+        block: &[u8],
+    ) -> io::Result<()> {
+        let mut header = format!(
+            "WARC/1.1\r\nWARC-Type: {}\r\nWARC-Record-ID: <urn:uuid:{}>\r\nWARC-Date: {}\r\n",
+            warc_type,
+            Uuid::new_v4(),
+            warc_date()
+        );
+        if let Some(uri) = target_uri {
+            header.push_str(&format!("WARC-Target-URI: {}\r\n", uri));
+        }
+        header.push_str(&format!(
+            "Content-Type: {}\r\nContent-Length: {}\r\n\r\n",
+            content_type,
+            block.len()
+        ));
+
+        // Write the whole record while holding the lock, so records
+        // from different threads don't interleave.
+        let mut file = self.file.lock().unwrap();
+        file.write_all(header.as_bytes())?;
+        file.write_all(block)?;
+        file.write_all(b"\r\n\r\n")?;
+        file.flush()
+    }
+
+    /// Archive `response` from `url`, whose (already read) body is
+    /// `body`.
+    pub fn write_response(&self, url: &Url, response: &Response, body: &[u8]) -> io::Result<()> {
+        self.write_record(
+            "response",
+            Some(url),
+            "application/http;msgtype=response",
+            &http_message(response, body),
+        )
+    }
+}
+
+/// `response`, whose (already read) body is `body`, as it came over
+/// the wire: the status line, headers and body.
+fn http_message(response: &Response, body: &[u8]) -> Vec<u8> {
+    let mut message = format!("{:?} {}\r\n", response.version(), response.status()).into_bytes();
+    for (name, value) in response.headers() {
+        // reqwest has already decoded the body, so these would no
+        // longer be accurate.
+        if *name == CONTENT_ENCODING || *name == TRANSFER_ENCODING || *name == CONTENT_LENGTH {
+            continue;
+        }
+        message.extend_from_slice(name.as_str().as_bytes());
+        message.extend_from_slice(b": ");
+        message.extend_from_slice(value.as_bytes());
+        message.extend_from_slice(b"\r\n");
+    }
+    message.extend_from_slice(format!("Content-Length: {}\r\n\r\n", body.len()).as_bytes());
+    message.extend_from_slice(body);
+    message
+}
+
+/// Saves the responses of broken URLs to a directory, one file each,
+/// so users can see what the server actually sent.
+pub struct FailureDump {
+    dir: PathBuf,
+}
+
+impl FailureDump {
+    /// Save responses to `dir`, creating it if needed.
+    pub fn create(dir: &Path) -> io::Result<FailureDump> {
+        fs::create_dir_all(dir)?;
+        Ok(FailureDump {
+            dir: dir.to_owned(),
+        })
+    }
+
+    /// The file we save the response from `url` to: the host and path,
+    /// made safe for a file name, and a hash of the whole URL, so URLs
+    /// that only differ in their query or punctuation don't collide.
+    pub fn path(&self, url: &Url) -> PathBuf {
+        let readable: String = format!("{}{}", url.host_str().unwrap_or_default(), url.path())
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .take(100)
+            .collect();
+        let mut hasher = DefaultHasher::new();
+        url.as_str().hash(&mut hasher);
+        self.dir
+            .join(format!("{}-{:08x}.http", readable, hasher.finish() as u32))
+    }
+
+    /// Save `response` from `url`, whose (already read) body is `body`,
+    /// replacing any earlier response from a retry.
+    pub fn write_response(&self, url: &Url, response: &Response, body: &[u8]) -> io::Result<()> {
+        fs::write(self.path(url), http_message(response, body))
+    }
+}
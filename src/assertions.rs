@@ -0,0 +1,203 @@
+use std::collections::BTreeMap;
+use url::Url;
+
+use crate::fetching::Page;
+use crate::ignoring;
+
+/// What a page must be like.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Check {
+    Contains(String),
+    Lacks(String),
+    SizeAbove(u64),
+    SizeBelow(u64),
+    /// The response has the header, by lowercase name, containing the
+    /// text if given, ignoring case.
+    Header(String, Option<String>),
+}
+
+/// A rule that pages matching a pattern must follow, e.g.
+/// `/downloads/* contains "version"`.
+#[derive(Debug, Clone)]
+pub struct Assertion {
+    /// As written, for reporting.
+    rule: String,
+    /// A glob matched against the whole URL or, if it starts with `/`,
+    /// the path.
+    pattern: Vec<char>,
+    check: Check,
+}
+
+/// Strip one pair of matching quotes around `text`.
+fn unquote(text: &str) -> &str {
+    for quote in &['"', '\''] {
+        if let Some(inner) = text
+            .strip_prefix(*quote)
+            .and_then(|text| text.strip_suffix(*quote))
+        {
+            return inner;
+        }
+    }
+    text
+}
+
+impl Assertion {
+    /// Parse a rule: a pattern, then `contains TEXT`, `lacks TEXT`,
+    /// `size > BYTES`, `size < BYTES`, `header NAME` or `header NAME
+    /// contains TEXT`. TEXT may be quoted.
+    pub fn parse(rule: &str) -> Result<Assertion, String> {
+        let rule = rule.trim();
+        let invalid = || {
+            format!(
+                "Invalid assertion {}, expected e.g. \"/downloads/* contains version\", \"/downloads/* size > 1000\" or \"/assets/* header Cache-Control\"",
+                rule
+            )
+        };
+        let (pattern, rest) = rule.split_once(char::is_whitespace).ok_or_else(invalid)?;
+        let (op, arg) = rest
+            .trim_start()
+            .split_once(char::is_whitespace)
+            .ok_or_else(invalid)?;
+        let arg = arg.trim();
+        let size = |arg: &str| -> Result<(char, u64), String> {
+            let (comparison, bytes) =
+                arg.split_at(arg.find(|c: char| c.is_ascii_digit()).ok_or_else(invalid)?);
+            let bytes = bytes.trim().parse().map_err(|_| invalid())?;
+            match comparison.trim() {
+                ">" => Ok(('>', bytes)),
+                "<" => Ok(('<', bytes)),
+                _ => Err(invalid()),
+            }
+        };
+        let header = |arg: &str| -> Result<Check, String> {
+            let (name, rest) = match arg.split_once(char::is_whitespace) {
+                Some((name, rest)) => (name, Some(rest.trim_start())),
+                None => (arg, None),
+            };
+            let contains = match rest.map(|rest| rest.split_once(char::is_whitespace)) {
+                None => None,
+                Some(Some((op, text)))
+                    if op.eq_ignore_ascii_case("contains") && !unquote(text.trim()).is_empty() =>
+                {
+                    Some(unquote(text.trim()).to_ascii_lowercase())
+                }
+                Some(_) => return Err(invalid()),
+            };
+            let name = name.trim_end_matches(':').to_ascii_lowercase();
+            Ok(Check::Header(name, contains))
+        };
+        let check = match op.to_ascii_lowercase().as_str() {
+            "contains" if !unquote(arg).is_empty() => Check::Contains(unquote(arg).to_owned()),
+            "lacks" if !unquote(arg).is_empty() => Check::Lacks(unquote(arg).to_owned()),
+            "size" => match size(arg)? {
+                ('>', bytes) => Check::SizeAbove(bytes),
+                (_, bytes) => Check::SizeBelow(bytes),
+            },
+            "header" => header(arg)?,
+            _ => return Err(invalid()),
+        };
+        Ok(Assertion {
+            rule: rule.to_owned(),
+            pattern: pattern.chars().collect(),
+            check,
+        })
+    }
+
+    /// Does the rule apply to `url`?
+    pub fn applies_to(&self, url: &Url) -> bool {
+        let target: Vec<char> = if self.pattern.first() == Some(&'/') {
+            url.path().chars().collect()
+        } else {
+            url.as_str().chars().collect()
+        };
+        ignoring::matches(&self.pattern, &target)
+    }
+
+    /// Is the rule about the response's headers, rather than the page?
+    pub fn is_header_rule(&self) -> bool {
+        matches!(self.check, Check::Header(..))
+    }
+
+    /// Does `page` follow the rule? Its size is `size`, the
+    /// Content-Length, if the server sent one, or the body's length.
+    /// Header rules always hold here, see `holds_for_headers`.
+    pub fn holds(&self, page: &Page, size: Option<u64>) -> bool {
+        let size = size.unwrap_or(page.body.len() as u64);
+        match self.check {
+            Check::Contains(ref text) => page.body.contains(text.as_str()),
+            Check::Lacks(ref text) => !page.body.contains(text.as_str()),
+            Check::SizeAbove(bytes) => size > bytes,
+            Check::SizeBelow(bytes) => size < bytes,
+            Check::Header(..) => true,
+        }
+    }
+
+    /// Does a response with `headers`, by lowercase name, follow the
+    /// rule? Rules about the page always hold here.
+    pub fn holds_for_headers(&self, headers: &BTreeMap<String, String>) -> bool {
+        match self.check {
+            Check::Header(ref name, ref contains) => match (headers.get(name), contains) {
+                (None, _) => false,
+                (Some(_), None) => true,
+                (Some(value), Some(text)) => value.to_ascii_lowercase().contains(text.as_str()),
+            },
+            _ => true,
+        }
+    }
+}
+
+/// Every rule pages must follow.
+#[derive(Debug, Clone, Default)]
+pub struct Assertions {
+    rules: Vec<Assertion>,
+}
+
+impl Assertions {
+    pub fn parse(rules: &[String]) -> Result<Assertions, String> {
+        Ok(Assertions {
+            rules: rules
+                .iter()
+                .map(|rule| Assertion::parse(rule))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Do any rules about the page apply to `url`, so we need to fetch
+    /// it?
+    pub fn apply_to(&self, url: &Url) -> bool {
+        self.rules
+            .iter()
+            .any(|rule| !rule.is_header_rule() && rule.applies_to(url))
+    }
+
+    /// The rules that apply to `url` that `page` breaks, as written.
+    pub fn broken(&self, url: &Url, page: &Page, size: Option<u64>) -> Vec<&str> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.applies_to(url) && !rule.holds(page, size))
+            .map(|rule| rule.rule.as_str())
+            .collect()
+    }
+
+    /// The lowercase names of the headers the rules are about, so we
+    /// know which to keep from each response.
+    pub fn header_names(&self) -> Vec<String> {
+        self.rules
+            .iter()
+            .filter_map(|rule| match rule.check {
+                Check::Header(ref name, _) => Some(name.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The header rules that apply to `url` that a response with
+    /// `headers` breaks, as written.
+    pub fn broken_headers(&self, url: &Url, headers: &BTreeMap<String, String>) -> Vec<&str> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.applies_to(url) && !rule.holds_for_headers(headers))
+            .map(|rule| rule.rule.as_str())
+            .collect()
+    }
+}
@@ -0,0 +1,51 @@
+use std::fs;
+use std::path::Path;
+
+/// Links that are known to be broken, and shouldn't fail the run.
+///
+/// Each line is a URL, optionally followed by whitespace and the page
+/// linking to it, so the same URL can be accepted on one page but not
+/// another. Blank lines and lines starting with `#` are skipped.
+#[derive(Debug, Clone, Default)]
+pub struct Baseline {
+    /// (URL, referrer) pairs, where `None` matches any referrer.
+    entries: Vec<(String, Option<String>)>,
+}
+
+impl Baseline {
+    pub fn parse(src: &str) -> Baseline {
+        let entries = src
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let mut parts = line.split_whitespace();
+                let url = parts.next().unwrap().to_owned();
+                (url, parts.next().map(|referrer| referrer.to_owned()))
+            })
+            .collect();
+        Baseline { entries }
+    }
+
+    pub fn load(path: &Path) -> Result<Baseline, String> {
+        fs::read_to_string(path)
+            .map(|src| Baseline::parse(&src))
+            .map_err(|e| e.to_string())
+    }
+
+    /// Is `url`, linked from `referrers`, known to be broken?
+    pub fn covers(&self, url: &str, referrers: &[&str]) -> bool {
+        self.entries.iter().any(|(entry_url, entry_referrer)| {
+            entry_url == url
+                && match entry_referrer {
+                    Some(referrer) => referrers.contains(&referrer.as_str()),
+                    None => true,
+                }
+        })
+    }
+
+    /// Does the baseline mention `url` at all?
+    pub fn contains(&self, url: &str) -> bool {
+        self.entries.iter().any(|(entry_url, _)| entry_url == url)
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use url::Url;
+
+/// Domains we shouldn't link to at all, e.g. known malware or spam
+/// hosts. Listing a domain lists its subdomains too.
+#[derive(Debug, Clone, Default)]
+pub struct Blocklist {
+    domains: HashSet<String>,
+}
+
+/// The domain on a line of a blocklist, if there is one. We accept the
+/// common formats: a domain per line, a hosts file (`0.0.0.0
+/// example.com`), and adblock host rules (`||example.com^`). Comments
+/// start with `#` or `!`.
+fn parse_line(line: &str) -> Option<String> {
+    // Comments can also follow a domain, after whitespace.
+    let words: Vec<&str> = line
+        .split_whitespace()
+        .take_while(|word| !word.starts_with('#'))
+        .collect();
+    let line = words.join(" ");
+    // Adblock element rules, like `example.com##.ad`, don't block the
+    // domain.
+    if line.is_empty() || line.starts_with('!') || line.contains('#') {
+        return None;
+    }
+    let domain = if let Some(rule) = line.strip_prefix("||") {
+        // Only plain host rules, not ones with paths or options.
+        let rule = rule.strip_suffix('^').unwrap_or(rule);
+        if rule.contains(|c| matches!(c, '/' | '^' | '$' | '*')) {
+            return None;
+        }
+        rule
+    } else {
+        let mut words = line.split_whitespace();
+        let first = words.next()?;
+        match words.next() {
+            // A hosts file maps an address to the blocked domain.
+            Some(second) if first.parse::<std::net::IpAddr>().is_ok() => second,
+            Some(_) => return None,
+            None => first,
+        }
+    };
+    let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+    // Hosts files also map localhost, which isn't blocked.
+    if domain.is_empty() || domain == "localhost" || domain.starts_with("localhost.") {
+        return None;
+    }
+    Some(domain)
+}
+
+impl Blocklist {
+    pub fn parse(src: &str) -> Blocklist {
+        Blocklist {
+            domains: src.lines().filter_map(parse_line).collect(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Blocklist, String> {
+        fs::read_to_string(path)
+            .map(|src| Blocklist::parse(&src))
+            .map_err(|e| e.to_string())
+    }
+
+    /// Add the domains in `other`.
+    pub fn extend(&mut self, other: Blocklist) {
+        self.domains.extend(other.domains);
+    }
+
+    /// The listed domain that `url` is on, if any.
+    pub fn listed(&self, url: &Url) -> Option<&str> {
+        let host = url.host_str()?.trim_end_matches('.').to_ascii_lowercase();
+        let mut domain = host.as_str();
+        loop {
+            if let Some(listed) = self.domains.get(domain) {
+                return Some(listed.as_str());
+            }
+            match domain.split_once('.') {
+                Some((_, parent)) => domain = parent,
+                None => return None,
+            }
+        }
+    }
+}
@@ -0,0 +1,663 @@
+use clap::{App, AppSettings, Arg, SubCommand};
+use std::path::Path;
+use std::time::Duration;
+use url::Url;
+
+use linkdoc::assertions;
+use linkdoc::comments;
+use linkdoc::fetching;
+use linkdoc::graphing;
+use linkdoc::reporting::{self, Format};
+use linkdoc::schemes;
+use linkdoc::severity;
+use linkdoc::tuning;
+
+/// Clap validator for non-negative numbers.
+fn is_number(s: String) -> Result<(), String> {
+    s.parse::<usize>()
+        .map(|_| ())
+        .map_err(|_| format!("expected a non-negative number, got {}", s))
+}
+
+/// Clap validator for numbers greater than zero.
+fn is_positive_number(s: String) -> Result<(), String> {
+    match s.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(()),
+        _ => Err(format!("expected a number greater than zero, got {}", s)),
+    }
+}
+
+/// Check that `s` is a URL we can start crawling from.
+pub fn is_start_url(s: String) -> Result<(), String> {
+    let url = match Url::parse(&s) {
+        Ok(url) => url,
+        Err(url::ParseError::RelativeUrlWithoutBase) => {
+            return Err(format!(
+                "{} is not a full URL, did you mean http://{} ?",
+                s, s
+            ))
+        }
+        Err(e) => return Err(format!("{} is not a valid URL: {}", s, e)),
+    };
+
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(format!(
+            "{} is not a web page, expected an http:// or https:// URL",
+            s
+        ));
+    }
+    if url.host().is_none() {
+        return Err(format!("{} must have a host", s));
+    }
+    Ok(())
+}
+
+/// Parse an interval such as `90s`, `30m`, `1h` or `1d`.
+pub fn parse_interval(s: &str) -> Result<Duration, String> {
+    let err = || format!("expected an interval such as 30m or 1h, got {}", s);
+    let split = s.len() - s.chars().last().map_or(0, |c| c.len_utf8());
+    let (number, unit) = s.split_at(split);
+    let secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(err()),
+    };
+    match number.parse::<u64>() {
+        Ok(n) if n > 0 => Ok(Duration::from_secs(n * secs)),
+        _ => Err(err()),
+    }
+}
+
+fn severity_arg() -> Arg<'static, 'static> {
+    Arg::with_name("severity")
+        .long("severity")
+        .value_name("CATEGORY=SEVERITY")
+        .help("Set the severity (error, warning or info) of a result category or HTTP status, e.g. timed_out=info or 403=warning")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .validator(|s| severity::Severities::parse_rule(&s).map(|_| ()))
+}
+
+fn fix_arg() -> Arg<'static, 'static> {
+    Arg::with_name("fix")
+        .long("fix")
+        .help("Rewrite links that permanently redirect to where they end up, and http:// links that work over HTTPS to https://, printing each change")
+}
+
+/// Options for reporting results, shared by every subcommand that
+/// checks URLs.
+fn report_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("max-broken")
+            .long("max-broken")
+            .value_name("N")
+            .help("Exit successfully if there are at most N broken links (default: 0)")
+            .takes_value(true)
+            .validator(is_number),
+        Arg::with_name("ignore-warnings")
+            .long("ignore-warnings")
+            .help("Don't count warning-level results (e.g. timeouts) as broken links"),
+        Arg::with_name("baseline")
+            .long("baseline")
+            .value_name("PATH")
+            .help("Don't count links listed in PATH as broken, reporting them separately")
+            .takes_value(true),
+        Arg::with_name("triage")
+            .long("triage")
+            .help("Afterwards, step through each broken link to open it, add it to the baseline or add it to the fix list"),
+        Arg::with_name("fix-list")
+            .long("fix-list")
+            .value_name("PATH")
+            .help("Where --triage records links to fix (default: linkdoc-fixes.txt)")
+            .takes_value(true),
+        severity_arg(),
+        Arg::with_name("wayback")
+            .long("wayback")
+            .help("Look up each broken link to another site in the Wayback Machine, and report its latest archived copy"),
+        Arg::with_name("warn-tracking")
+            .long("warn-tracking")
+            .help("Report links between pages on the site with tracking or session parameters, such as utm_source or PHPSESSID"),
+        Arg::with_name("suggest-https")
+            .long("suggest-https")
+            .help("Try each working http:// link over HTTPS, and report the links that could be upgraded"),
+        Arg::with_name("check-integrity")
+            .long("check-integrity")
+            .help("Download scripts and stylesheets with an integrity attribute, and report those whose hash doesn't match"),
+        Arg::with_name("format")
+            .long("format")
+            .value_name("FORMAT")
+            .help("How to report broken links (default: text)")
+            .possible_values(Format::NAMES)
+            .takes_value(true),
+        Arg::with_name("output")
+            .long("output")
+            .short("o")
+            .value_name("[FORMAT:]PATH")
+            .help("Also write a report to PATH (format taken from the extension if not given)")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .validator(|s| reporting::parse_output(&s).map(|_| ())),
+        Arg::with_name("template")
+            .long("template")
+            .value_name("PATH")
+            .help("Render the results with the Tera template at PATH")
+            .takes_value(true),
+        Arg::with_name("template-output")
+            .long("template-output")
+            .value_name("PATH")
+            .help("Write the rendered template to PATH rather than stdout")
+            .takes_value(true),
+        Arg::with_name("webhook")
+            .long("webhook")
+            .value_name("URL")
+            .help("POST a JSON summary to URL when the crawl finishes")
+            .takes_value(true),
+        Arg::with_name("webhook-threshold")
+            .long("webhook-threshold")
+            .value_name("N")
+            .help("Only call the webhook if there are more than N broken links")
+            .takes_value(true)
+            .validator(is_number),
+        Arg::with_name("webhook-full")
+            .long("webhook-full")
+            .help("Include every broken link in the webhook payload"),
+        Arg::with_name("slack-webhook")
+            .long("slack-webhook")
+            .value_name("URL")
+            .help("Post a summary to this Slack incoming webhook when the crawl finishes")
+            .takes_value(true),
+        Arg::with_name("teams-webhook")
+            .long("teams-webhook")
+            .value_name("URL")
+            .help("Post a summary to this Microsoft Teams incoming webhook when the crawl finishes")
+            .takes_value(true),
+        Arg::with_name("report-link")
+            .long("report-link")
+            .value_name("URL")
+            .help("Link to the published HTML report in chat notifications")
+            .takes_value(true),
+    ]
+}
+
+/// Options for checking URLs, shared by every subcommand that does so.
+fn check_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("concurrency")
+            .long("concurrency")
+            .short("j")
+            .value_name("N")
+            .help("Check N URLs at once (default: 10)")
+            .takes_value(true)
+            .validator(is_positive_number),
+        Arg::with_name("timeout")
+            .long("timeout")
+            .value_name("SECS")
+            .help("Give up on a URL if it doesn't respond within SECS seconds (default: 10)")
+            .takes_value(true)
+            .validator(is_positive_number),
+        Arg::with_name("timeout-for")
+            .long("timeout-for")
+            .value_name("PATTERN=SECS")
+            .help("Wait SECS seconds for URLs matching PATTERN instead, e.g. /export/*=60 (can be repeated)")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .validator(|s| tuning::parse_rule(&s).map(|_| ())),
+        Arg::with_name("user-agent")
+            .long("user-agent")
+            .value_name("STRING")
+            .help("Send STRING as the User-Agent header (default: linkdoc/VERSION)")
+            .takes_value(true),
+        Arg::with_name("retries")
+            .long("retries")
+            .value_name("N")
+            .help("Check a URL up to N more times if it isn't accessible (default: 0)")
+            .takes_value(true)
+            .validator(is_number),
+        Arg::with_name("retries-for")
+            .long("retries-for")
+            .value_name("PATTERN=N")
+            .help("Check URLs matching PATTERN up to N more times instead, e.g. /flaky/*=3 (can be repeated)")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .validator(|s| tuning::parse_rule(&s).map(|_| ())),
+        Arg::with_name("max-redirects")
+            .long("max-redirects")
+            .value_name("N")
+            .help("Fail URLs that redirect more than N times (default: 10)")
+            .takes_value(true)
+            .validator(is_number),
+        Arg::with_name("method")
+            .long("method")
+            .value_name("METHOD")
+            .help("Check URLs with GET, or with HEAD for a fast pass over known URLs that doesn't download or crawl pages (default: get)")
+            .possible_values(fetching::Method::NAMES)
+            .takes_value(true),
+        Arg::with_name("warn-redirects")
+            .long("warn-redirects")
+            .value_name("N")
+            .help("Report working URLs that redirect more than N times, since every visit waits for each hop")
+            .takes_value(true)
+            .validator(is_number),
+        Arg::with_name("database")
+            .long("database")
+            .value_name("PATH")
+            .help("Record every result in the SQLite database at PATH, keeping history across runs")
+            .takes_value(true),
+        Arg::with_name("dashboard")
+            .long("dashboard")
+            .value_name("ADDR")
+            .help("Serve a live dashboard of the crawl on ADDR (e.g. 127.0.0.1:8080)")
+            .takes_value(true),
+        Arg::with_name("metrics-file")
+            .long("metrics-file")
+            .value_name("PATH")
+            .help("Write Prometheus metrics to PATH, for the node_exporter textfile collector")
+            .takes_value(true),
+        Arg::with_name("ignore-file")
+            .long("ignore-file")
+            .value_name("PATH")
+            .help("Skip URLs matching the patterns in PATH (default: .linkdocignore, if it exists)")
+            .takes_value(true),
+        Arg::with_name("blocklist")
+            .long("blocklist")
+            .value_name("PATH")
+            .help("Report links to the domains in PATH, a list of domains, a hosts file or adblock host rules, without requesting them (can be repeated)")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1),
+        Arg::with_name("scheme")
+            .long("scheme")
+            .value_name("SCHEME=RULE")
+            .help("Check, skip or forbid links using SCHEME, e.g. http=forbid or mailto=skip, where * is every other scheme (can be repeated)")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .validator(|s| schemes::Schemes::parse_rule(&s).map(|_| ())),
+        Arg::with_name("tui")
+            .long("tui")
+            .help("Show results in an interactive terminal UI"),
+    ]
+}
+
+/// Options for crawling a site.
+fn crawl_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("START URL")
+            .help("Where to start crawling (or set start-url in the config file)")
+            .validator(is_start_url),
+        Arg::with_name("graph")
+            .long("graph")
+            .value_name("PATH")
+            .help("Write the link graph to PATH (.dot or .graphml), with broken links in red")
+            .takes_value(true)
+            .validator(|s| match graphing::GraphFormat::from_path(Path::new(&s)) {
+                Some(_) => Ok(()),
+                None => Err("the graph file must end with .dot, .gv or .graphml".to_owned()),
+            }),
+        Arg::with_name("emit-sitemap")
+            .long("emit-sitemap")
+            .value_name("PATH")
+            .help("Write a sitemap.xml of all the accessible pages on the domain to PATH")
+            .takes_value(true),
+        Arg::with_name("warc")
+            .long("warc")
+            .value_name("PATH")
+            .help("Archive every page fetched on the domain to a WARC file at PATH")
+            .takes_value(true),
+        Arg::with_name("dump-failures")
+            .long("dump-failures")
+            .value_name("DIR")
+            .help("Save the headers and body of every broken URL's response to a file in DIR")
+            .takes_value(true),
+        Arg::with_name("ignore-www")
+            .long("ignore-www")
+            .help("Treat www.example.com and example.com as the same site when deciding which pages to crawl"),
+        Arg::with_name("keep-fragments")
+            .long("keep-fragments")
+            .help("Check page#a and page#b separately rather than once, for single-page apps where fragments are routes"),
+        Arg::with_name("soft-404")
+            .long("soft-404")
+            .help("Warn about pages on the site that load but look like error pages, e.g. \"Page not found\" with a 200 status"),
+        Arg::with_name("soft-404-phrases")
+            .long("soft-404-phrases")
+            .value_name("PHRASES")
+            .help("Also treat pages whose title or heading contains one of PHRASES as error pages (implies --soft-404)")
+            .takes_value(true)
+            .use_delimiter(true),
+        Arg::with_name("detect-parked")
+            .long("detect-parked")
+            .help("Fetch links to other sites and report those whose domain has lapsed into a parking or for-sale page"),
+        Arg::with_name("dns-prepass")
+            .long("dns-prepass")
+            .help("Look up other sites' hosts before requesting them, and report links to hosts that don't exist straight away"),
+        Arg::with_name("check-favicon")
+            .long("check-favicon")
+            .help("Also check /favicon.ico for pages that don't declare an icon, since browsers request it on every visit"),
+        Arg::with_name("assert")
+            .long("assert")
+            .value_name("RULE")
+            .help("Fail pages matching a pattern unless they follow RULE, e.g. \"/downloads/* contains version\", \"/downloads/* size > 1000\" or \"/assets/* header Cache-Control\" (can be repeated)")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .validator(|s| assertions::Assertion::parse(&s).map(|_| ())),
+        Arg::with_name("capture-header")
+            .long("capture-header")
+            .value_name("NAME")
+            .help("Keep the response header NAME for each URL, in JSON reports (can be repeated)")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1),
+        Arg::with_name("strip-params")
+            .long("strip-params")
+            .value_name("NAMES")
+            .help("Remove the query parameters NAMES (e.g. ref,utm_*) from links before checking them, where * strips them all")
+            .takes_value(true)
+            .use_delimiter(true),
+        Arg::with_name("ignore-params")
+            .long("ignore-params")
+            .value_name("NAMES")
+            .help("Check links that only differ in the query parameters NAMES (e.g. page,sort) once, where * ignores them all")
+            .takes_value(true)
+            .use_delimiter(true),
+        Arg::with_name("max-depth")
+            .long("max-depth")
+            .value_name("N")
+            .help("Only follow links up to N clicks away from the start URL")
+            .takes_value(true)
+            .validator(is_number),
+        Arg::with_name("bloom-filter")
+            .long("bloom-filter")
+            .value_name("N")
+            .help("Remember visited URLs in a fixed-size Bloom filter for about N URLs, rather than keeping every URL in memory. About 0.1% of URLs are wrongly skipped. The graph of every link isn't kept either, unless --graph is given, so reports only name the page each URL was first found on")
+            .takes_value(true)
+            .validator(is_number),
+        Arg::with_name("prioritize")
+            .long("prioritize")
+            .help("Check pages closer to the start URL and pages in the sitemap first, and URLs with many query parameters last, so a crawl that stops early covers the pages that matter most. The queue isn't sharded, so workers share one lock on it"),
+        Arg::with_name("locales")
+            .long("locales")
+            .value_name("PREFIXES")
+            .help("Crawl each locale under PREFIXES (e.g. /en/,/de/,/fr/) and report pages that are broken or missing in some locales but not others")
+            .takes_value(true)
+            .use_delimiter(true),
+        Arg::with_name("watch")
+            .long("watch")
+            .value_name("INTERVAL")
+            .help("Keep crawling every INTERVAL (e.g. 30m or 1h), reporting links that broke or were fixed since the last crawl")
+            .takes_value(true)
+            .validator(|s| parse_interval(&s).map(|_| ())),
+        Arg::with_name("list-only")
+            .long("list-only")
+            .help("Print every URL found on the site and the pages linking to it, without checking links to other domains"),
+    ]
+}
+
+/// The command line interface. `profiles` are offered as values for
+/// `--profile`, for shell completion.
+pub fn build(profiles: &'static [&'static str]) -> App<'static, 'static> {
+    let mut profile = Arg::with_name("profile")
+        .long("profile")
+        .value_name("NAME")
+        .help("Use the settings from [profile.NAME] in the config file")
+        .takes_value(true)
+        .global(true);
+    // An empty list would reject every value.
+    if !profiles.is_empty() {
+        profile = profile.possible_values(profiles);
+    }
+
+    App::new("LinkDoctor")
+        .version("0.2")
+        .about("Finds dead links in web sites and reports.")
+        .author("Wilfred Hughes")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .setting(AppSettings::VersionlessSubcommands)
+        .arg(
+            Arg::with_name("config")
+                .long("config")
+                .value_name("PATH")
+                .help("Read settings from this TOML file (default: linkdoc.toml, if it exists)")
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(profile)
+        .arg(
+            Arg::with_name("color")
+                .long("color")
+                .value_name("WHEN")
+                .help("When to use colored output (default: auto)")
+                .possible_values(&["auto", "always", "never"])
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("log-level")
+                .long("log-level")
+                .value_name("LEVEL")
+                .help("Log crawler activity at this level and above to stderr (default: warn)")
+                .possible_values(&["error", "warn", "info", "debug", "trace"])
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("log-json")
+                .long("log-json")
+                .help("Write logs as JSON lines")
+                .global(true),
+        )
+        .subcommand(
+            SubCommand::with_name("crawl")
+                .about("Walks all the web pages in a domain to find dead links")
+                .args(&crawl_args())
+                .args(&check_args())
+                .args(&report_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("list")
+                .about("Crawl a site and print every URL found, without checking links to other domains")
+                .args(&crawl_args())
+                .args(&check_args())
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .help("How to print the URLs (default: text)")
+                        .possible_values(&["text", "json"])
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("urls")
+                .about("Check a list of URLs, one per line, without crawling")
+                .arg(
+                    Arg::with_name("input")
+                        .long("input")
+                        .short("i")
+                        .value_name("PATH")
+                        .help("Read URLs from PATH rather than stdin")
+                        .takes_value(true),
+                )
+                .args(&check_args())
+                .args(&report_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("openapi")
+                .about("Check the URLs in OpenAPI or Swagger specs: servers, docs, license, contact and remote $refs")
+                .arg(
+                    Arg::with_name("SPECS")
+                        .help("The specs to check, in YAML or JSON")
+                        .required(true)
+                        .multiple(true),
+                )
+                .args(&check_args())
+                .args(&report_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("check")
+                .about("Check the links in a directory of HTML, e.g. a static site before it's deployed")
+                .arg(
+                    Arg::with_name("DIR")
+                        .help("The directory to check (or set dir in the config file)"),
+                )
+                .arg(
+                    Arg::with_name("base-url")
+                        .long("base-url")
+                        .value_name("URL")
+                        .help("Check the directory as if it was served at URL (default: http://localhost/)")
+                        .takes_value(true)
+                        .validator(is_start_url),
+                )
+                .arg(
+                    Arg::with_name("check-external")
+                        .long("check-external")
+                        .help("Also check links to other sites over HTTP"),
+                )
+                .arg(fix_arg())
+                .args(&check_args())
+                .args(&report_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("docs")
+                .about("Check the links between the Markdown files in a repository, and to other sites")
+                .arg(
+                    Arg::with_name("DIR")
+                        .help("The repository to check (default: the current directory, or set dir in the config file)"),
+                )
+                .arg(
+                    Arg::with_name("offline")
+                        .long("offline")
+                        .help("Only check links between files, not links to other sites"),
+                )
+                .arg(fix_arg())
+                .args(&check_args())
+                .args(&report_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("comments")
+                .about("Check the URLs in the comments of a repository's source code")
+                .arg(
+                    Arg::with_name("DIR")
+                        .help("The repository to check (default: the current directory, or set dir in the config file)"),
+                )
+                .arg(
+                    Arg::with_name("comment-syntax")
+                        .long("comment-syntax")
+                        .value_name("EXTENSION=MARKERS")
+                        .help("Set the comment markers for files with EXTENSION, space-separated with block comments written start...end, e.g. 'lua=-- --[[...]]'")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .validator(|s| comments::CommentSyntax::parse_rule(&s).map(|_| ())),
+                )
+                .args(&check_args())
+                .args(&report_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("mdbook")
+                .about("Check an mdBook, from its source or the output of mdbook build, including anchors")
+                .arg(
+                    Arg::with_name("DIR")
+                        .help("The book's directory, with its book.toml, or its output directory (or set dir in the config file)"),
+                )
+                .arg(
+                    Arg::with_name("check-external")
+                        .long("check-external")
+                        .help("Also check links to other sites over HTTP"),
+                )
+                .args(&check_args())
+                .args(&report_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("rustdoc")
+                .about("Check the API docs from cargo doc, including item anchors")
+                .arg(
+                    Arg::with_name("DIR")
+                        .help("The output of cargo doc (default: target/doc, or set dir in the config file)"),
+                )
+                .arg(
+                    Arg::with_name("check-external")
+                        .long("check-external")
+                        .help("Also check links to other sites over HTTP"),
+                )
+                .args(&check_args())
+                .args(&report_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("recheck")
+                .about("Check the broken links in a JSON report again, with retries")
+                .arg(Arg::with_name("REPORT").required(true))
+                .args(&check_args())
+                .args(&report_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("serve")
+                .about("Serve an HTTP API for starting crawls and fetching their results")
+                .arg(
+                    Arg::with_name("listen")
+                        .long("listen")
+                        .value_name("ADDR")
+                        .help("Listen on ADDR (default: 127.0.0.1:8000)")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("dashboard")
+                        .long("dashboard")
+                        .value_name("ADDR")
+                        .help("Serve a live dashboard of every crawl on ADDR (e.g. 127.0.0.1:8080)")
+                        .takes_value(true),
+                )
+                .args(&check_args()[..2])
+                .arg(severity_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("merge")
+                .about("Combine JSON reports from several runs into one report")
+                .arg(Arg::with_name("REPORTS").required(true).multiple(true))
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .possible_values(&["json", "text"])
+                        .default_value("json"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .short("o")
+                        .value_name("PATH")
+                        .help("Write the merged report to PATH rather than stdout")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("diff")
+                .about("Compare two JSON reports, showing newly broken and fixed links")
+                .arg(Arg::with_name("OLD").required(true))
+                .arg(Arg::with_name("NEW").required(true))
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .possible_values(&["json", "text"])
+                        .default_value("text"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("completions")
+                .about("Print a completion script for your shell")
+                .arg(Arg::with_name("SHELL").required(true).possible_values(&[
+                    "bash",
+                    "zsh",
+                    "fish",
+                    "powershell",
+                    "elvish",
+                ])),
+        )
+}
@@ -0,0 +1,151 @@
+use std::collections::BTreeMap;
+
+use crate::parsing;
+
+/// How comments are written in a language.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommentSyntax {
+    /// Markers starting a comment that runs to the end of the line,
+    /// e.g. `//`.
+    pub line: Vec<String>,
+    /// The start and end markers of block comments, e.g. `/*` and
+    /// `*/`. Doc strings, such as Python's `"""`, count too.
+    pub block: Vec<(String, String)>,
+}
+
+impl CommentSyntax {
+    /// Parse space-separated markers, where a block comment's markers
+    /// are joined by `...`, e.g. `// /*...*/`.
+    pub fn parse(src: &str) -> Result<CommentSyntax, String> {
+        let mut syntax = CommentSyntax::default();
+        for marker in src.split_whitespace() {
+            match marker.split_once("...") {
+                Some((start, end)) if !start.is_empty() && !end.is_empty() => {
+                    syntax.block.push((start.to_owned(), end.to_owned()))
+                }
+                Some(_) => return Err(format!("Invalid block comment: {}", marker)),
+                None => syntax.line.push(marker.to_owned()),
+            }
+        }
+        if syntax.line.is_empty() && syntax.block.is_empty() {
+            return Err("No comment markers given".to_owned());
+        }
+        Ok(syntax)
+    }
+
+    /// Parse a rule such as `lua=-- --[[...]]`, giving the comment
+    /// syntax for a file extension.
+    pub fn parse_rule(rule: &str) -> Result<(String, CommentSyntax), String> {
+        match rule.split_once('=') {
+            Some((extension, markers)) if !extension.is_empty() => Ok((
+                extension.trim_start_matches('.').to_owned(),
+                CommentSyntax::parse(markers)?,
+            )),
+            _ => Err(format!(
+                "Expected EXTENSION=MARKERS, e.g. lua=--, got {}",
+                rule
+            )),
+        }
+    }
+}
+
+/// The comment syntax of common languages, by file extension.
+pub fn default_syntaxes() -> BTreeMap<String, CommentSyntax> {
+    let languages: &[(&[&str], &str)] = &[
+        (
+            &[
+                "rs", "c", "h", "cc", "cpp", "hpp", "cs", "java", "js", "jsx", "mjs", "ts", "tsx",
+                "go", "swift", "kt", "kts", "scala", "dart", "groovy", "scss", "less", "proto",
+            ],
+            "// /*...*/",
+        ),
+        (&["css"], "/*...*/"),
+        (&["php"], "// # /*...*/"),
+        (&["py", "pyi"], "# \"\"\"...\"\"\" '''...'''"),
+        (&["rb"], "# =begin...=end"),
+        (
+            &[
+                "sh", "bash", "zsh", "fish", "pl", "pm", "r", "yaml", "yml", "toml", "cmake", "ex",
+                "exs", "nix", "tf", "ps1",
+            ],
+            "#",
+        ),
+        (&["sql"], "-- /*...*/"),
+        (&["lua"], "-- --[[...]]"),
+        (&["hs", "elm"], "-- {-...-}"),
+        (&["clj", "cljs", "el", "lisp", "scm"], ";"),
+        (&["erl", "tex"], "%"),
+        (&["html", "xml", "svg", "vue"], "<!--...-->"),
+    ];
+    let mut syntaxes = BTreeMap::new();
+    for (extensions, markers) in languages {
+        let syntax = CommentSyntax::parse(markers).unwrap();
+        for extension in *extensions {
+            syntaxes.insert((*extension).to_owned(), syntax.clone());
+        }
+    }
+    syntaxes
+}
+
+/// The URLs in the comments of `source`, with the line each is on,
+/// counting from 1. We don't parse string literals, so a comment
+/// marker inside a string is taken as the start of a comment.
+pub fn comment_urls(source: &str, syntax: &CommentSyntax) -> Vec<(usize, String)> {
+    let mut urls = vec![];
+    // The end marker of the block comment we're in, if any.
+    let mut block_end: Option<&str> = None;
+    for (i, line) in source.lines().enumerate() {
+        let mut comments = vec![];
+        let mut rest = line;
+        loop {
+            if let Some(end) = block_end {
+                match rest.find(end) {
+                    Some(pos) => {
+                        comments.push(&rest[..pos]);
+                        rest = &rest[pos + end.len()..];
+                        block_end = None;
+                    }
+                    None => {
+                        comments.push(rest);
+                        break;
+                    }
+                }
+                continue;
+            }
+
+            let line_start = syntax
+                .line
+                .iter()
+                .filter_map(|marker| rest.find(marker.as_str()))
+                .min();
+            let block_start = syntax
+                .block
+                .iter()
+                .filter_map(|(start, end)| rest.find(start.as_str()).map(|pos| (pos, start, end)))
+                .min_by_key(|(pos, _, _)| *pos);
+            match (line_start, block_start) {
+                // A block comment wins a tie, e.g. Lua's -- and --[[.
+                (Some(line_pos), Some((block_pos, _, _))) if line_pos < block_pos => {
+                    comments.push(&rest[line_pos..]);
+                    break;
+                }
+                (_, Some((block_pos, start, end))) => {
+                    rest = &rest[block_pos + start.len()..];
+                    block_end = Some(end.as_str());
+                }
+                (Some(line_pos), None) => {
+                    comments.push(&rest[line_pos..]);
+                    break;
+                }
+                (None, None) => break,
+            }
+        }
+
+        for comment in comments {
+            for url in parsing::find_urls(comment) {
+                urls.push((i + 1, url));
+            }
+        }
+    }
+    urls
+}
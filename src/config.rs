@@ -0,0 +1,514 @@
+use clap::ArgMatches;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::tuning;
+
+/// The config file we look for in the working directory.
+pub const DEFAULT_PATH: &str = "linkdoc.toml";
+
+/// The prefix of environment variables we read settings from, e.g.
+/// `LINKDOC_CONCURRENCY`.
+pub const ENV_PREFIX: &str = "LINKDOC_";
+
+/// Settings from a config file or the command line. Every field is
+/// optional so that sources can be layered, with defaults applied
+/// once everything has been merged.
+#[derive(Debug, Default, Deserialize)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize))]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    pub start_url: Option<String>,
+    /// A file of URLs to check, for `linkdoc urls`.
+    pub input: Option<String>,
+    /// The directory to check, for `linkdoc check`.
+    pub dir: Option<String>,
+    /// Where `linkdoc check` pretends the directory is served.
+    pub base_url: Option<String>,
+    /// Whether `linkdoc check` checks links to other sites.
+    pub check_external: Option<bool>,
+    /// Comment markers for `linkdoc comments`, keyed by file
+    /// extension, see `CommentSyntax::parse`.
+    pub comment_syntax: Option<BTreeMap<String, String>>,
+    /// Whether `linkdoc check` and `linkdoc docs` rewrite links that
+    /// have moved.
+    pub fix: Option<bool>,
+    pub concurrency: Option<usize>,
+    /// In seconds.
+    pub timeout: Option<u64>,
+    /// Timeouts in seconds for URLs matching patterns, e.g.
+    /// `"/export/*" = 60`, see `Tuning`.
+    pub timeout_for: Option<BTreeMap<String, u64>>,
+    /// Retries for URLs matching patterns.
+    pub retries_for: Option<BTreeMap<String, u64>>,
+    pub max_broken: Option<usize>,
+    pub ignore_warnings: Option<bool>,
+    /// Keyed by category or HTTP status, see `Severities::parse_rule`.
+    pub severity: Option<BTreeMap<String, String>>,
+    /// What to do with links using each scheme, or `*`, see
+    /// `Schemes::parse_rule`.
+    pub scheme: Option<BTreeMap<String, String>>,
+    pub format: Option<String>,
+    pub output: Option<Vec<String>>,
+    pub template: Option<String>,
+    pub template_output: Option<String>,
+    pub database: Option<String>,
+    pub dashboard: Option<String>,
+    pub metrics_file: Option<String>,
+    pub webhook: Option<String>,
+    pub webhook_threshold: Option<usize>,
+    pub webhook_full: Option<bool>,
+    /// Whether to look up broken links in the Wayback Machine.
+    pub wayback: Option<bool>,
+    pub warn_tracking: Option<bool>,
+    pub suggest_https: Option<bool>,
+    pub check_integrity: Option<bool>,
+    pub slack_webhook: Option<String>,
+    pub teams_webhook: Option<String>,
+    pub report_link: Option<String>,
+    pub graph: Option<String>,
+    pub emit_sitemap: Option<String>,
+    pub warc: Option<String>,
+    pub dump_failures: Option<String>,
+    pub list_only: Option<bool>,
+    /// An interval such as `1h`, see `cli::parse_interval`.
+    pub watch: Option<String>,
+    /// Where `linkdoc serve` listens.
+    pub listen: Option<String>,
+    pub ignore_file: Option<String>,
+    /// Files listing domains that links shouldn't point to.
+    pub blocklist: Option<Vec<String>>,
+    pub tui: Option<bool>,
+    pub baseline: Option<String>,
+    pub retries: Option<usize>,
+    pub max_redirects: Option<usize>,
+    /// `get` or `head`.
+    pub method: Option<String>,
+    /// Warn about URLs that redirect more than this many times.
+    pub warn_redirects: Option<usize>,
+    pub triage: Option<bool>,
+    pub fix_list: Option<String>,
+    pub max_depth: Option<usize>,
+    /// Remember visited URLs in a Bloom filter sized for this many.
+    pub bloom_filter: Option<usize>,
+    /// Check the pages most likely to matter first, see
+    /// `prioritizing::Heuristics`.
+    pub prioritize: Option<bool>,
+    pub ignore_www: Option<bool>,
+    pub keep_fragments: Option<bool>,
+    pub soft_404: Option<bool>,
+    /// Phrases marking an error page, on top of
+    /// `soft404::DEFAULT_PHRASES`.
+    pub soft_404_phrases: Option<Vec<String>>,
+    pub detect_parked: Option<bool>,
+    pub dns_prepass: Option<bool>,
+    pub check_favicon: Option<bool>,
+    /// Rules pages must follow, see `Assertion::parse`. They can
+    /// contain commas, so can't be set from the environment.
+    pub assert: Option<Vec<String>>,
+    /// Response headers to keep for each URL, by name.
+    pub capture_header: Option<Vec<String>>,
+    /// Query parameters to remove from links, e.g. `ref`.
+    pub strip_params: Option<Vec<String>>,
+    /// Query parameters that don't make a link a different page, e.g.
+    /// `page`.
+    pub ignore_params: Option<Vec<String>>,
+    /// Path prefixes of the site's translations, e.g. `/en/`, to
+    /// compare.
+    pub locales: Option<Vec<String>>,
+    pub user_agent: Option<String>,
+    pub color: Option<String>,
+    pub log_level: Option<String>,
+    pub log_json: Option<bool>,
+    /// Named sets of settings, applied on top of the rest of the file
+    /// when selected with `--profile`.
+    pub profile: Option<BTreeMap<String, Config>>,
+}
+
+fn flag(matches: &ArgMatches, name: &str) -> Option<bool> {
+    if matches.is_present(name) {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+fn string(matches: &ArgMatches, name: &str) -> Option<String> {
+    matches.value_of(name).map(|s| s.to_owned())
+}
+
+/// Parse a number that clap has already validated.
+fn number(matches: &ArgMatches, name: &str) -> Option<usize> {
+    matches.value_of(name).map(|s| s.parse().unwrap())
+}
+
+/// The value of `LINKDOC_<name>`, if set and not empty.
+fn env_string(name: &str) -> Option<String> {
+    env::var(format!("{}{}", ENV_PREFIX, name))
+        .ok()
+        .filter(|value| !value.is_empty())
+}
+
+fn env_number<T: FromStr>(name: &str) -> Result<Option<T>, String> {
+    match env_string(name) {
+        Some(value) => match value.parse() {
+            Ok(n) => Ok(Some(n)),
+            Err(_) => Err(format!(
+                "{}{} must be a number, got {}",
+                ENV_PREFIX, name, value
+            )),
+        },
+        None => Ok(None),
+    }
+}
+
+fn env_flag(name: &str) -> Result<Option<bool>, String> {
+    match env_string(name).as_deref() {
+        Some("1") | Some("true") | Some("yes") => Ok(Some(true)),
+        Some("0") | Some("false") | Some("no") => Ok(Some(false)),
+        Some(value) => Err(format!(
+            "{}{} must be true or false, got {}",
+            ENV_PREFIX, name, value
+        )),
+        None => Ok(None),
+    }
+}
+
+/// `KEY=VALUE` rules, such as severities. Rules without an `=` are
+/// skipped, since clap has already validated them.
+fn rules<'a>(rules: impl Iterator<Item = &'a str>) -> BTreeMap<String, String> {
+    rules
+        .filter_map(|rule| {
+            let mut parts = rule.splitn(2, '=');
+            Some((parts.next()?.to_owned(), parts.next()?.to_owned()))
+        })
+        .collect()
+}
+
+/// `PATTERN=NUMBER` rules, see `tuning::parse_rule`.
+fn number_rules<'a>(rules: impl Iterator<Item = &'a str>) -> Result<BTreeMap<String, u64>, String> {
+    rules.map(tuning::parse_rule).collect()
+}
+
+/// Combine two sets of rules, preferring those in `overrides`.
+fn merge_rules<T>(
+    base: Option<BTreeMap<String, T>>,
+    overrides: Option<BTreeMap<String, T>>,
+) -> Option<BTreeMap<String, T>> {
+    match (base, overrides) {
+        (Some(mut base), Some(overrides)) => {
+            base.extend(overrides);
+            Some(base)
+        }
+        (base, overrides) => overrides.or(base),
+    }
+}
+
+/// A comma-separated list.
+fn env_list(name: &str) -> Option<Vec<String>> {
+    env_string(name).map(|value| value.split(',').map(|s| s.trim().to_owned()).collect())
+}
+
+impl Config {
+    /// Read the TOML config file at `path`.
+    pub fn load(path: &Path) -> Result<Config, String> {
+        let src = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        toml::from_str(&src).map_err(|e| e.to_string())
+    }
+
+    /// Apply the profile called `name` on top of the other settings.
+    pub fn with_profile(mut self, name: &str) -> Result<Config, String> {
+        match self
+            .profile
+            .as_mut()
+            .and_then(|profiles| profiles.remove(name))
+        {
+            Some(profile) => {
+                if profile.profile.is_some() {
+                    return Err(format!("profile {} can't contain other profiles", name));
+                }
+                Ok(self.merge(profile))
+            }
+            None => Err(format!("no profile called {}", name)),
+        }
+    }
+
+    /// The settings given on the command line.
+    pub fn from_matches(matches: &ArgMatches) -> Config {
+        let severity = matches.values_of("severity").map(rules);
+        let scheme = matches.values_of("scheme").map(rules);
+        let comment_syntax = matches.values_of("comment-syntax").map(rules);
+        // Clap has already checked these.
+        let timeout_for = matches
+            .values_of("timeout-for")
+            .and_then(|rules| number_rules(rules).ok());
+        let retries_for = matches
+            .values_of("retries-for")
+            .and_then(|rules| number_rules(rules).ok());
+
+        Config {
+            start_url: string(matches, "START URL"),
+            input: string(matches, "input"),
+            dir: string(matches, "DIR"),
+            base_url: string(matches, "base-url"),
+            check_external: flag(matches, "check-external")
+                .or_else(|| flag(matches, "offline").map(|_| false)),
+            comment_syntax,
+            fix: flag(matches, "fix"),
+            concurrency: number(matches, "concurrency"),
+            timeout: number(matches, "timeout").map(|secs| secs as u64),
+            timeout_for,
+            retries_for,
+            max_broken: number(matches, "max-broken"),
+            ignore_warnings: flag(matches, "ignore-warnings"),
+            severity,
+            scheme,
+            format: string(matches, "format"),
+            output: matches
+                .values_of("output")
+                .map(|specs| specs.map(|s| s.to_owned()).collect()),
+            template: string(matches, "template"),
+            template_output: string(matches, "template-output"),
+            database: string(matches, "database"),
+            dashboard: string(matches, "dashboard"),
+            metrics_file: string(matches, "metrics-file"),
+            webhook: string(matches, "webhook"),
+            webhook_threshold: number(matches, "webhook-threshold"),
+            webhook_full: flag(matches, "webhook-full"),
+            wayback: flag(matches, "wayback"),
+            warn_tracking: flag(matches, "warn-tracking"),
+            suggest_https: flag(matches, "suggest-https"),
+            check_integrity: flag(matches, "check-integrity"),
+            slack_webhook: string(matches, "slack-webhook"),
+            teams_webhook: string(matches, "teams-webhook"),
+            report_link: string(matches, "report-link"),
+            graph: string(matches, "graph"),
+            emit_sitemap: string(matches, "emit-sitemap"),
+            warc: string(matches, "warc"),
+            dump_failures: string(matches, "dump-failures"),
+            list_only: flag(matches, "list-only"),
+            watch: string(matches, "watch"),
+            listen: string(matches, "listen"),
+            ignore_file: string(matches, "ignore-file"),
+            blocklist: matches
+                .values_of("blocklist")
+                .map(|paths| paths.map(|s| s.to_owned()).collect()),
+            tui: flag(matches, "tui"),
+            baseline: string(matches, "baseline"),
+            retries: number(matches, "retries"),
+            max_redirects: number(matches, "max-redirects"),
+            method: string(matches, "method"),
+            warn_redirects: number(matches, "warn-redirects"),
+            triage: flag(matches, "triage"),
+            fix_list: string(matches, "fix-list"),
+            max_depth: number(matches, "max-depth"),
+            bloom_filter: number(matches, "bloom-filter"),
+            prioritize: flag(matches, "prioritize"),
+            ignore_www: flag(matches, "ignore-www"),
+            keep_fragments: flag(matches, "keep-fragments"),
+            soft_404: flag(matches, "soft-404"),
+            soft_404_phrases: matches
+                .values_of("soft-404-phrases")
+                .map(|phrases| phrases.map(|s| s.to_owned()).collect()),
+            detect_parked: flag(matches, "detect-parked"),
+            dns_prepass: flag(matches, "dns-prepass"),
+            check_favicon: flag(matches, "check-favicon"),
+            assert: matches
+                .values_of("assert")
+                .map(|rules| rules.map(|s| s.to_owned()).collect()),
+            capture_header: matches
+                .values_of("capture-header")
+                .map(|names| names.map(|s| s.to_owned()).collect()),
+            strip_params: matches
+                .values_of("strip-params")
+                .map(|names| names.map(|s| s.to_owned()).collect()),
+            ignore_params: matches
+                .values_of("ignore-params")
+                .map(|names| names.map(|s| s.to_owned()).collect()),
+            locales: matches
+                .values_of("locales")
+                .map(|prefixes| prefixes.map(|s| s.to_owned()).collect()),
+            user_agent: string(matches, "user-agent"),
+            color: string(matches, "color"),
+            log_level: string(matches, "log-level"),
+            log_json: flag(matches, "log-json"),
+            profile: None,
+        }
+    }
+
+    /// The settings given in `LINKDOC_*` environment variables, named
+    /// after the config file keys, e.g. `LINKDOC_MAX_BROKEN`. Lists
+    /// such as `LINKDOC_OUTPUT` are comma-separated, and severity
+    /// rules are given as `LINKDOC_SEVERITY=timed_out=info,403=warning`.
+    pub fn from_env() -> Result<Config, String> {
+        let severity = env_list("SEVERITY").map(|list| rules(list.iter().map(|s| s.as_str())));
+        let scheme = env_list("SCHEME").map(|list| rules(list.iter().map(|s| s.as_str())));
+        let comment_syntax =
+            env_list("COMMENT_SYNTAX").map(|list| rules(list.iter().map(|s| s.as_str())));
+        let timeout_for = env_list("TIMEOUT_FOR")
+            .map(|list| number_rules(list.iter().map(|s| s.as_str())))
+            .transpose()?;
+        let retries_for = env_list("RETRIES_FOR")
+            .map(|list| number_rules(list.iter().map(|s| s.as_str())))
+            .transpose()?;
+
+        Ok(Config {
+            start_url: env_string("START_URL"),
+            input: env_string("INPUT"),
+            dir: env_string("DIR"),
+            base_url: env_string("BASE_URL"),
+            check_external: env_flag("CHECK_EXTERNAL")?,
+            comment_syntax,
+            fix: env_flag("FIX")?,
+            concurrency: env_number("CONCURRENCY")?,
+            timeout: env_number("TIMEOUT")?,
+            timeout_for,
+            retries_for,
+            max_broken: env_number("MAX_BROKEN")?,
+            ignore_warnings: env_flag("IGNORE_WARNINGS")?,
+            severity,
+            scheme,
+            format: env_string("FORMAT"),
+            output: env_list("OUTPUT"),
+            template: env_string("TEMPLATE"),
+            template_output: env_string("TEMPLATE_OUTPUT"),
+            database: env_string("DATABASE"),
+            dashboard: env_string("DASHBOARD"),
+            metrics_file: env_string("METRICS_FILE"),
+            webhook: env_string("WEBHOOK"),
+            webhook_threshold: env_number("WEBHOOK_THRESHOLD")?,
+            webhook_full: env_flag("WEBHOOK_FULL")?,
+            wayback: env_flag("WAYBACK")?,
+            warn_tracking: env_flag("WARN_TRACKING")?,
+            suggest_https: env_flag("SUGGEST_HTTPS")?,
+            check_integrity: env_flag("CHECK_INTEGRITY")?,
+            slack_webhook: env_string("SLACK_WEBHOOK"),
+            teams_webhook: env_string("TEAMS_WEBHOOK"),
+            report_link: env_string("REPORT_LINK"),
+            graph: env_string("GRAPH"),
+            emit_sitemap: env_string("EMIT_SITEMAP"),
+            warc: env_string("WARC"),
+            dump_failures: env_string("DUMP_FAILURES"),
+            list_only: env_flag("LIST_ONLY")?,
+            watch: env_string("WATCH"),
+            listen: env_string("LISTEN"),
+            ignore_file: env_string("IGNORE_FILE"),
+            blocklist: env_list("BLOCKLIST"),
+            tui: env_flag("TUI")?,
+            baseline: env_string("BASELINE"),
+            retries: env_number("RETRIES")?,
+            max_redirects: env_number("MAX_REDIRECTS")?,
+            method: env_string("METHOD"),
+            warn_redirects: env_number("WARN_REDIRECTS")?,
+            triage: env_flag("TRIAGE")?,
+            fix_list: env_string("FIX_LIST"),
+            max_depth: env_number("MAX_DEPTH")?,
+            bloom_filter: env_number("BLOOM_FILTER")?,
+            prioritize: env_flag("PRIORITIZE")?,
+            ignore_www: env_flag("IGNORE_WWW")?,
+            keep_fragments: env_flag("KEEP_FRAGMENTS")?,
+            soft_404: env_flag("SOFT_404")?,
+            soft_404_phrases: env_list("SOFT_404_PHRASES"),
+            detect_parked: env_flag("DETECT_PARKED")?,
+            dns_prepass: env_flag("DNS_PREPASS")?,
+            check_favicon: env_flag("CHECK_FAVICON")?,
+            assert: None,
+            capture_header: env_list("CAPTURE_HEADER"),
+            strip_params: env_list("STRIP_PARAMS"),
+            ignore_params: env_list("IGNORE_PARAMS"),
+            locales: env_list("LOCALES"),
+            user_agent: env_string("USER_AGENT"),
+            color: env_string("COLOR"),
+            log_level: env_string("LOG_LEVEL"),
+            log_json: env_flag("LOG_JSON")?,
+            profile: None,
+        })
+    }
+
+    /// Combine two configs, preferring values from `overrides`.
+    /// Severity, scheme, comment syntax, timeout and retry rules are
+    /// merged key by key.
+    pub fn merge(self, overrides: Config) -> Config {
+        let severity = merge_rules(self.severity, overrides.severity);
+        let scheme = merge_rules(self.scheme, overrides.scheme);
+        let comment_syntax = merge_rules(self.comment_syntax, overrides.comment_syntax);
+        let timeout_for = merge_rules(self.timeout_for, overrides.timeout_for);
+        let retries_for = merge_rules(self.retries_for, overrides.retries_for);
+
+        Config {
+            start_url: overrides.start_url.or(self.start_url),
+            input: overrides.input.or(self.input),
+            dir: overrides.dir.or(self.dir),
+            base_url: overrides.base_url.or(self.base_url),
+            check_external: overrides.check_external.or(self.check_external),
+            comment_syntax,
+            fix: overrides.fix.or(self.fix),
+            concurrency: overrides.concurrency.or(self.concurrency),
+            timeout: overrides.timeout.or(self.timeout),
+            timeout_for,
+            retries_for,
+            max_broken: overrides.max_broken.or(self.max_broken),
+            ignore_warnings: overrides.ignore_warnings.or(self.ignore_warnings),
+            severity,
+            scheme,
+            format: overrides.format.or(self.format),
+            output: overrides.output.or(self.output),
+            template: overrides.template.or(self.template),
+            template_output: overrides.template_output.or(self.template_output),
+            database: overrides.database.or(self.database),
+            dashboard: overrides.dashboard.or(self.dashboard),
+            metrics_file: overrides.metrics_file.or(self.metrics_file),
+            webhook: overrides.webhook.or(self.webhook),
+            webhook_threshold: overrides.webhook_threshold.or(self.webhook_threshold),
+            webhook_full: overrides.webhook_full.or(self.webhook_full),
+            wayback: overrides.wayback.or(self.wayback),
+            warn_tracking: overrides.warn_tracking.or(self.warn_tracking),
+            suggest_https: overrides.suggest_https.or(self.suggest_https),
+            check_integrity: overrides.check_integrity.or(self.check_integrity),
+            slack_webhook: overrides.slack_webhook.or(self.slack_webhook),
+            teams_webhook: overrides.teams_webhook.or(self.teams_webhook),
+            report_link: overrides.report_link.or(self.report_link),
+            graph: overrides.graph.or(self.graph),
+            emit_sitemap: overrides.emit_sitemap.or(self.emit_sitemap),
+            warc: overrides.warc.or(self.warc),
+            dump_failures: overrides.dump_failures.or(self.dump_failures),
+            list_only: overrides.list_only.or(self.list_only),
+            watch: overrides.watch.or(self.watch),
+            listen: overrides.listen.or(self.listen),
+            ignore_file: overrides.ignore_file.or(self.ignore_file),
+            blocklist: overrides.blocklist.or(self.blocklist),
+            tui: overrides.tui.or(self.tui),
+            baseline: overrides.baseline.or(self.baseline),
+            retries: overrides.retries.or(self.retries),
+            max_redirects: overrides.max_redirects.or(self.max_redirects),
+            method: overrides.method.or(self.method),
+            warn_redirects: overrides.warn_redirects.or(self.warn_redirects),
+            triage: overrides.triage.or(self.triage),
+            fix_list: overrides.fix_list.or(self.fix_list),
+            max_depth: overrides.max_depth.or(self.max_depth),
+            bloom_filter: overrides.bloom_filter.or(self.bloom_filter),
+            prioritize: overrides.prioritize.or(self.prioritize),
+            ignore_www: overrides.ignore_www.or(self.ignore_www),
+            keep_fragments: overrides.keep_fragments.or(self.keep_fragments),
+            soft_404: overrides.soft_404.or(self.soft_404),
+            soft_404_phrases: overrides.soft_404_phrases.or(self.soft_404_phrases),
+            detect_parked: overrides.detect_parked.or(self.detect_parked),
+            dns_prepass: overrides.dns_prepass.or(self.dns_prepass),
+            check_favicon: overrides.check_favicon.or(self.check_favicon),
+            assert: overrides.assert.or(self.assert),
+            capture_header: overrides.capture_header.or(self.capture_header),
+            strip_params: overrides.strip_params.or(self.strip_params),
+            ignore_params: overrides.ignore_params.or(self.ignore_params),
+            locales: overrides.locales.or(self.locales),
+            user_agent: overrides.user_agent.or(self.user_agent),
+            color: overrides.color.or(self.color),
+            log_level: overrides.log_level.or(self.log_level),
+            log_json: overrides.log_json.or(self.log_json),
+            profile: overrides.profile.or(self.profile),
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use url::Url;
+
+use crate::graphing::LinkGraph;
+use crate::scoping::Scope;
+
+/// Sites often impersonated by phishing domains, which links shouldn't
+/// have look-alikes of.
+pub const POPULAR_DOMAINS: &[&str] = &[
+    "google.com",
+    "youtube.com",
+    "facebook.com",
+    "instagram.com",
+    "twitter.com",
+    "x.com",
+    "linkedin.com",
+    "microsoft.com",
+    "live.com",
+    "office.com",
+    "apple.com",
+    "icloud.com",
+    "amazon.com",
+    "paypal.com",
+    "netflix.com",
+    "github.com",
+    "gitlab.com",
+    "wikipedia.org",
+    "dropbox.com",
+    "yahoo.com",
+    "stackoverflow.com",
+    "crates.io",
+    "docs.rs",
+    "rust-lang.org",
+];
+
+/// Characters that look like an ASCII letter, and the letter. Fullwidth
+/// forms don't need listing, since hosts are normalized before we see
+/// them.
+const CONFUSABLES: &[(char, char)] = &[
+    // Cyrillic
+    ('а', 'a'),
+    ('в', 'b'),
+    ('е', 'e'),
+    ('һ', 'h'),
+    ('і', 'i'),
+    ('ј', 'j'),
+    ('к', 'k'),
+    ('ӏ', 'l'),
+    ('м', 'm'),
+    ('п', 'n'),
+    ('о', 'o'),
+    ('р', 'p'),
+    ('ԛ', 'q'),
+    ('г', 'r'),
+    ('ѕ', 's'),
+    ('т', 't'),
+    ('ц', 'u'),
+    ('ѵ', 'v'),
+    ('ԝ', 'w'),
+    ('х', 'x'),
+    ('у', 'y'),
+    ('ԁ', 'd'),
+    // Greek
+    ('α', 'a'),
+    ('ε', 'e'),
+    ('ι', 'i'),
+    ('κ', 'k'),
+    ('ν', 'v'),
+    ('ο', 'o'),
+    ('ρ', 'p'),
+    ('τ', 't'),
+    ('υ', 'u'),
+    ('χ', 'x'),
+    ('γ', 'y'),
+    // Latin
+    ('ı', 'i'),
+    ('ɑ', 'a'),
+    ('ɡ', 'g'),
+    ('ɩ', 'i'),
+    ('ɪ', 'i'),
+    ('ʟ', 'l'),
+    ('ո', 'n'),
+    ('ս', 'u'),
+    ('à', 'a'),
+    ('á', 'a'),
+    ('â', 'a'),
+    ('ã', 'a'),
+    ('ä', 'a'),
+    ('å', 'a'),
+    ('ç', 'c'),
+    ('è', 'e'),
+    ('é', 'e'),
+    ('ê', 'e'),
+    ('ë', 'e'),
+    ('ì', 'i'),
+    ('í', 'i'),
+    ('î', 'i'),
+    ('ï', 'i'),
+    ('ñ', 'n'),
+    ('ò', 'o'),
+    ('ó', 'o'),
+    ('ô', 'o'),
+    ('õ', 'o'),
+    ('ö', 'o'),
+    ('ù', 'u'),
+    ('ú', 'u'),
+    ('û', 'u'),
+    ('ü', 'u'),
+    ('ý', 'y'),
+    ('ÿ', 'y'),
+];
+
+/// A link from a page on the site to a host that looks like another
+/// domain but isn't, e.g. `gіthub.com` with a Cyrillic `і`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConfusableLink {
+    pub page: String,
+    pub url: String,
+    /// The host of `url` in Unicode, as readers see it.
+    pub host: String,
+    /// The domain it looks like.
+    pub looks_like: String,
+}
+
+/// `host` with every character that looks like an ASCII letter replaced
+/// by the letter, so look-alikes compare equal.
+fn skeleton(host: &str) -> String {
+    host.chars()
+        .map(|c| {
+            CONFUSABLES
+                .iter()
+                .find(|(confusable, _)| *confusable == c)
+                .map_or(c, |(_, ascii)| *ascii)
+        })
+        .collect()
+}
+
+/// Is `host`, or the domain it's a subdomain of, `domain`?
+fn is_on(host: &str, domain: &str) -> bool {
+    host == domain
+        || host
+            .strip_suffix(domain)
+            .map_or(false, |sub| sub.ends_with('.'))
+}
+
+/// The domain of `domains` that `url`'s host looks like without being
+/// on it, if any. Only hosts with non-ASCII characters are checked,
+/// since typos in plain ASCII aren't homographs.
+pub fn looks_like<'a>(url: &Url, domains: &[&'a str]) -> Option<&'a str> {
+    let (host, result) = idna::domain_to_unicode(url.domain()?);
+    if result.is_err() || host.is_ascii() {
+        return None;
+    }
+    let host = host.trim_end_matches('.').to_lowercase();
+    let skeleton = skeleton(&host);
+    domains
+        .iter()
+        .find(|domain| is_on(&skeleton, domain) && !is_on(&host, domain))
+        .copied()
+}
+
+/// Every link in `graph` from a page in `scope` to a host that looks
+/// like the site's own domain or one of `POPULAR_DOMAINS`, in the
+/// order we found them.
+pub fn confusable_links(graph: &LinkGraph, scope: &Scope) -> Vec<ConfusableLink> {
+    let own = scope.host().trim_start_matches("www.").to_ascii_lowercase();
+    let mut domains: Vec<&str> = vec![own.as_str()];
+    domains.extend(POPULAR_DOMAINS);
+
+    let nodes = graph.nodes();
+    let mut links: Vec<ConfusableLink> = vec![];
+    for edge in graph.edges() {
+        let (page, url) = (&nodes[edge.source].url, &nodes[edge.target].url);
+        let parsed = match (Url::parse(page), Url::parse(url)) {
+            (Ok(page), Ok(url)) if scope.contains(&page) && !scope.contains(&url) => url,
+            _ => continue,
+        };
+        let looks_like = match looks_like(&parsed, &domains) {
+            Some(domain) => domain,
+            None => continue,
+        };
+        let seen = links
+            .iter()
+            .any(|link| link.page == *page && link.url == *url);
+        if !seen {
+            let (host, _) = idna::domain_to_unicode(parsed.domain().unwrap_or_default());
+            links.push(ConfusableLink {
+                page: page.clone(),
+                url: url.clone(),
+                host,
+                looks_like: looks_like.to_owned(),
+            });
+        }
+    }
+    links
+}
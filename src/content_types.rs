@@ -0,0 +1,78 @@
+use url::Url;
+
+/// The Content-Types a file with each extension should be served as.
+/// Images are left to `images`, which also looks at the body.
+const EXPECTED: &[(&[&str], &[&str])] = &[
+    (&["css"], &["text/css"]),
+    (
+        &["js", "mjs"],
+        &[
+            "application/javascript",
+            "text/javascript",
+            "application/x-javascript",
+            "application/ecmascript",
+            "text/ecmascript",
+        ],
+    ),
+    (&["json"], &["application/json", "text/json"]),
+    (&["html", "htm"], &["text/html", "application/xhtml+xml"]),
+    (&["xml"], &["application/xml", "text/xml"]),
+    (&["txt"], &["text/plain"]),
+    (&["md", "markdown"], &["text/markdown", "text/plain"]),
+    (&["pdf"], &["application/pdf", "application/octet-stream"]),
+    (
+        &["zip"],
+        &[
+            "application/zip",
+            "application/x-zip-compressed",
+            "application/octet-stream",
+        ],
+    ),
+    (
+        &["woff", "woff2", "ttf", "otf"],
+        &[
+            "font/",
+            "application/font-",
+            "application/x-font-",
+            "application/octet-stream",
+        ],
+    ),
+    (&["mp4"], &["video/mp4"]),
+    (&["webm"], &["video/webm", "audio/webm"]),
+    (&["mp3"], &["audio/mpeg", "audio/mp3"]),
+];
+
+/// Why `url`, served as `content_type`, was served as the wrong type
+/// for its extension, if it was. Types ending in `/` or `-` match any
+/// type they start, and any `+json` or `+xml` type matches JSON or XML.
+pub fn mismatch(url: &Url, content_type: &str) -> Option<String> {
+    let name = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .unwrap_or_default();
+    let extension = name.rsplit_once('.')?.1.to_ascii_lowercase();
+    let (_, expected) = EXPECTED
+        .iter()
+        .find(|(extensions, _)| extensions.contains(&extension.as_str()))?;
+
+    let actual = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    let suffix_matches = (extension == "json" && actual.ends_with("+json"))
+        || (extension == "xml" && actual.ends_with("+xml"));
+    let matches = expected.iter().any(|expected| {
+        if expected.ends_with('/') || expected.ends_with('-') {
+            actual.starts_with(expected)
+        } else {
+            actual == *expected
+        }
+    });
+    if matches || suffix_matches {
+        None
+    } else {
+        Some(format!(".{} file served as {}", extension, actual))
+    }
+}
@@ -1,35 +1,505 @@
 use crossbeam_channel::{unbounded, Receiver, Sender};
 use crossbeam_utils::Backoff;
-use std::collections::HashSet;
-use std::sync::{Arc, Mutex};
+use std::any::Any;
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+#[cfg(feature = "stream")]
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::Waker;
+#[cfg(feature = "stream")]
+use std::task::{Context, Poll};
 use std::thread;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tracing::{debug, error, info_span, trace};
 use url::Url;
 
-use crate::fetching::{fetch_all_urls, url_status, UrlState};
+use crate::archiving::{FailureDump, WarcWriter};
+use crate::assertions::Assertions;
+use crate::blocklist::Blocklist;
+use crate::fetching::{
+    self, build_url, url_status, CheckResult, FetchError, Fetcher, HttpFetcher, Method, Outcome,
+    Page, DEFAULT_TIMEOUT,
+};
+use crate::graphing::LinkGraph;
+use crate::ignoring::IgnoreList;
+use crate::login;
+use crate::metrics::Metrics;
+use crate::middleware::{Middleware, Pipeline};
+use crate::normalizing::{self, QueryRules};
+use crate::parking;
+use crate::parsing::{self, Extractors, LinkExtractor};
+use crate::politeness::{Permit, Politeness};
+use crate::progress::{Event, EventKind, ProgressEvents, Tracker};
+use crate::resolving::HostCache;
+use crate::schemes::{SchemeRule, Schemes};
+use crate::scoping::Scope;
+use crate::soft404::Soft404Detector;
+use crate::stats::{Counters, Stats};
+use crate::store::{CrawlStore, MemoryStore, DEFAULT_SHARDS};
+use crate::tuning::Tuning;
 
+/// Why we couldn't start a crawl.
+#[derive(Debug, Error)]
+pub enum CrawlError {
+    #[error("concurrency must be greater than zero")]
+    ZeroConcurrency,
+    #[error("nothing to check, add a seed URL")]
+    NoSeeds,
+    #[error("Can't crawl {0}, the URL must have a host")]
+    NoDomain(String),
+    #[error(transparent)]
+    Fetch(#[from] FetchError),
+}
+
+/// How many URLs we check at once, unless configured otherwise.
+pub const DEFAULT_CONCURRENCY: usize = 10;
+
+/// Settings and optional extras for a crawl.
+pub struct CrawlOptions {
+    /// The number of worker threads.
+    pub concurrency: usize,
+    /// How long to wait for each URL.
+    pub timeout: Duration,
+    /// Write every page we fetch to this WARC archive.
+    pub archive: Option<Arc<WarcWriter>>,
+    /// Save the response of every URL that isn't accessible here.
+    pub dump_failures: Option<Arc<FailureDump>>,
+    /// Only fetch pages on the site, to find their links, and don't
+    /// check links anywhere else.
+    pub list_only: bool,
+    /// URLs we shouldn't check or crawl.
+    pub ignore: IgnoreList,
+    /// Query parameters to strip from links, or ignore when deciding
+    /// whether we've visited them.
+    pub query_rules: QueryRules,
+    /// How many more times to check a URL if it isn't accessible.
+    pub retries: usize,
+    /// Timeouts and retries for URLs matching patterns, instead of
+    /// `timeout` and `retries`.
+    pub tuning: Tuning,
+    /// How many redirects to follow before giving up on a URL.
+    pub max_redirects: usize,
+    /// How to request URLs when checking them.
+    pub method: Method,
+    /// Only follow links this many clicks away from the seeds.
+    pub max_depth: Option<usize>,
+    /// Send this User-Agent, rather than `fetching::DEFAULT_USER_AGENT`.
+    pub user_agent: Option<String>,
+    /// Treat `www.example.com` and `example.com` as the same site.
+    pub ignore_www: bool,
+    /// Check `page#a` and `page#b` separately, e.g. for single-page
+    /// apps where fragments are routes. By default we only check the
+    /// first, unless the fetcher checks fragments itself.
+    pub keep_fragments: bool,
+    /// Report pages on the site that are served successfully but look
+    /// like error pages.
+    pub soft_404: Option<Soft404Detector>,
+    /// Report links to other sites whose domain has lapsed and now
+    /// shows a parking or for-sale page. This fetches every external
+    /// page that's accessible.
+    pub detect_parked: bool,
+    /// Domains that links shouldn't point to. Links to them are
+    /// reported without being requested.
+    pub blocklist: Blocklist,
+    /// Which URL schemes to check, skip, or report without checking.
+    pub schemes: Schemes,
+    /// Look up the hosts of other sites before requesting them, and
+    /// report links to hosts that don't resolve straight away. The
+    /// seeds' hosts are all looked up before the crawl starts, and
+    /// those that don't resolve are reported first.
+    pub dns_prepass: bool,
+    /// Check `/favicon.ico` for pages that don't declare an icon, since
+    /// browsers request it for them.
+    pub check_favicon: bool,
+    /// Rules that pages must follow, e.g. containing some text. Pages
+    /// with rules are fetched even if they aren't crawled.
+    pub assertions: Assertions,
+    /// Response headers to keep in each result, by name, as well as
+    /// any the assertions are about.
+    pub capture_headers: Vec<String>,
+}
+
+impl Default for CrawlOptions {
+    fn default() -> Self {
+        CrawlOptions {
+            concurrency: DEFAULT_CONCURRENCY,
+            timeout: DEFAULT_TIMEOUT,
+            archive: None,
+            dump_failures: None,
+            list_only: false,
+            ignore: IgnoreList::default(),
+            query_rules: QueryRules::default(),
+            retries: 0,
+            tuning: Tuning::default(),
+            max_redirects: fetching::DEFAULT_MAX_REDIRECTS,
+            method: Method::Get,
+            max_depth: None,
+            user_agent: None,
+            ignore_www: false,
+            keep_fragments: false,
+            soft_404: None,
+            detect_parked: false,
+            blocklist: Blocklist::default(),
+            schemes: Schemes::default(),
+            dns_prepass: false,
+            check_favicon: false,
+            assertions: Assertions::default(),
+            capture_headers: vec![],
+        }
+    }
+}
+
+type PageHook = dyn Fn(&Url, &str) + Send + Sync;
+type LinkHook = dyn Fn(&str, &str) + Send + Sync;
+type StateHook = dyn Fn(&CheckResult) + Send + Sync;
+
+/// Callbacks run by the worker threads as the crawl goes, so embedding
+/// applications can see results without wrapping the iterator. They're
+/// called from several threads at once, so should be quick.
+#[derive(Default)]
+struct Hooks {
+    page_fetched: Vec<Box<PageHook>>,
+    link_discovered: Vec<Box<LinkHook>>,
+    result: Vec<Box<StateHook>>,
+    error: Vec<Box<StateHook>>,
+}
+
+impl Hooks {
+    fn page_fetched(&self, url: &Url, body: &str) {
+        for hook in &self.page_fetched {
+            hook(url, body);
+        }
+    }
+
+    fn link_discovered(&self, page: &str, target: &str) {
+        for hook in &self.link_discovered {
+            hook(page, target);
+        }
+    }
+
+    fn result(&self, state: &CheckResult) {
+        for hook in &self.result {
+            hook(state);
+        }
+        if state.is_accessible() {
+            return;
+        }
+        for hook in &self.error {
+            hook(state);
+        }
+    }
+}
+
+/// A change to the link graph that a worker has made, but that hasn't
+/// been applied yet.
+enum GraphUpdate {
+    /// A page, and the (target, href) of every link on it.
+    Links(String, Vec<(String, String)>),
+    Result(CheckResult),
+}
+
+/// The link graph, with the workers' changes buffered in shards rather
+/// than applied straight away, so workers don't all wait on the one
+/// lock. The buffered changes are applied, in the order they were made,
+/// whenever anyone looks at the graph.
+struct SharedGraph {
+    graph: Mutex<LinkGraph>,
+    /// Each change, numbered so they can be applied in order.
+    pending: Vec<Mutex<Vec<(u64, GraphUpdate)>>>,
+    next: AtomicU64,
+}
+
+impl SharedGraph {
+    fn new(graph: LinkGraph) -> SharedGraph {
+        SharedGraph {
+            graph: Mutex::new(graph),
+            pending: (0..DEFAULT_SHARDS).map(|_| Mutex::default()).collect(),
+            next: AtomicU64::new(0),
+        }
+    }
+
+    fn push(&self, update: GraphUpdate) {
+        let order = self.next.fetch_add(1, Ordering::SeqCst);
+        let shard = &self.pending[(order % self.pending.len() as u64) as usize];
+        lock(shard).push((order, update));
+    }
+
+    /// Record that `page` links to each of `links`, as (target, href).
+    fn add_links(&self, page: &str, links: Vec<(String, String)>) {
+        self.push(GraphUpdate::Links(page.to_owned(), links));
+    }
+
+    fn set_result(&self, result: CheckResult) {
+        self.push(GraphUpdate::Result(result));
+    }
+
+    /// The graph, with every change made so far.
+    fn get(&self) -> MutexGuard<'_, LinkGraph> {
+        let mut graph = lock(&self.graph);
+        let mut updates: Vec<_> = self
+            .pending
+            .iter()
+            .flat_map(|shard| std::mem::take(&mut *lock(shard)))
+            .collect();
+        updates.sort_by_key(|(order, _)| *order);
+        for (_, update) in updates {
+            match update {
+                GraphUpdate::Links(page, links) => {
+                    for (target, href) in links {
+                        graph.add_link(&page, &target, &href);
+                    }
+                }
+                GraphUpdate::Result(result) => graph.set_result(result),
+            }
+        }
+        graph
+    }
+}
+
+/// Take `mutex`'s lock, even if a worker panicked while holding it.
+/// That panic has already been reported for its URL, so carry on
+/// rather than panicking in every other worker too.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// State shared between the crawler and all its worker threads.
+struct Shared {
+    /// The host of the first seed, for resolving relative seeds.
+    domain: String,
+    /// The site we're crawling, unless we're only checking the seeds.
+    scope: Option<Scope>,
+    /// Whether to follow links from pages in `scope`.
+    recurse: bool,
+    concurrency: usize,
+    timeout: Duration,
+    /// The URLs we've seen, and the ones still to check.
+    store: Box<dyn CrawlStore>,
+    query_rules: QueryRules,
+    /// Whether URLs differing in their fragment are visited separately.
+    keep_fragments: bool,
+    soft_404: Option<Soft404Detector>,
+    detect_parked: bool,
+    blocklist: Blocklist,
+    schemes: Schemes,
+    /// Whether the hosts of other sites resolve, if we're checking.
+    hosts: Option<HostCache>,
+    check_favicon: bool,
+    assertions: Assertions,
+    /// Workers that have taken, or are about to take, a URL from the
+    /// store and haven't finished with it.
+    busy: AtomicUsize,
+    /// How many URLs workers have taken from the store, so we can tell
+    /// if one was taken while we were checking whether we're finished.
+    claimed: AtomicUsize,
+    metrics: Metrics,
+    graph: SharedGraph,
+    /// Whether to add the links found and the results to `graph`.
+    record_links: bool,
+    list_only: bool,
+    pipeline: Pipeline,
+    retries: usize,
+    tuning: Tuning,
+    max_depth: Option<usize>,
+    fetcher: Box<dyn Fetcher>,
+    extractors: Extractors,
+    hooks: Hooks,
+    /// The task polling the crawler as a stream, if it's waiting for
+    /// results.
+    waker: Mutex<Option<Waker>>,
+    cancelled: AtomicBool,
+    deadline: Option<Instant>,
+    progress: Tracker,
+    stats: Counters,
+    politeness: Option<Box<dyn Politeness>>,
+}
+
+impl Shared {
+    fn in_scope(&self, url: &Url) -> bool {
+        self.scope
+            .as_ref()
+            .map_or(false, |scope| scope.contains(url))
+    }
+
+    /// Stop checking new URLs, and wake anyone waiting for results.
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.wake();
+    }
+
+    /// The link graph, with every change the workers have made.
+    fn graph(&self) -> MutexGuard<'_, LinkGraph> {
+        self.graph.get()
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+            || self
+                .deadline
+                .map_or(false, |deadline| Instant::now() >= deadline)
+    }
+
+    /// How long to wait for `url`, without going past the deadline.
+    fn timeout(&self, url: Option<&Url>) -> Duration {
+        let timeout = url
+            .and_then(|url| self.tuning.timeout_for(url))
+            .unwrap_or(self.timeout);
+        match self.deadline {
+            Some(deadline) => timeout.min(deadline.saturating_duration_since(Instant::now())),
+            None => timeout,
+        }
+    }
+
+    /// How many more times to check `url` if it isn't accessible.
+    fn retries(&self, url: Option<&Url>) -> usize {
+        url.and_then(|url| self.tuning.retries_for(url))
+            .unwrap_or(self.retries)
+    }
+
+    /// Have the workers run out of URLs to check? Workers only queue
+    /// URLs while busy, so we're finished once no worker is busy and
+    /// the store's queue is empty. A worker can take a URL and finish
+    /// with it between us looking at the two, so we look at the busy
+    /// count on both sides of the queue, and check that nobody took a
+    /// URL meanwhile.
+    ///
+    /// With a store shared between processes, this is only whether
+    /// this process is finished.
+    fn is_finished(&self) -> bool {
+        let claimed = self.claimed.load(Ordering::SeqCst);
+        self.busy.load(Ordering::SeqCst) == 0
+            && self.store.is_empty()
+            && self.busy.load(Ordering::SeqCst) == 0
+            && self.claimed.load(Ordering::SeqCst) == claimed
+    }
+
+    /// Tell the task polling the crawler, if any, that there might be
+    /// something new.
+    fn wake(&self) {
+        if let Some(waker) = self.waker.lock().unwrap().take() {
+            waker.wake();
+        }
+    }
+}
+
+/// A crawl in progress. Iterate over it to get the result of checking
+/// each URL, as it's checked. Dropping it cancels the crawl.
 pub struct Crawler {
-    active_count: Arc<Mutex<i32>>,
-    url_states: Receiver<UrlState>,
+    url_states: Receiver<CheckResult>,
+    progress: Option<Receiver<Event>>,
+    shared: Arc<Shared>,
+}
+
+impl Crawler {
+    /// Metrics for the crawl so far.
+    pub fn metrics(&self) -> &Metrics {
+        &self.shared.metrics
+    }
+
+    /// Live statistics for the crawl so far.
+    pub fn stats(&self) -> Stats {
+        self.shared.stats.snapshot(self.shared.store.len())
+    }
+
+    /// Every (page, link target) pair found so far. Unlike the URL
+    /// states, this includes links to URLs that had already been
+    /// visited from another page.
+    pub fn links(&self) -> Vec<(String, String)> {
+        self.shared.graph().links()
+    }
+
+    /// All the pages linking to each URL found so far, keyed by the
+    /// URL as reported in `CheckResult`.
+    pub fn referrers(&self) -> HashMap<String, Vec<String>> {
+        self.shared.graph().referrers()
+    }
+
+    /// How `page` wrote each of its links to `url`, e.g. as a relative
+    /// path.
+    pub fn hrefs(&self, page: &str, url: &str) -> Vec<String> {
+        let graph = self.shared.graph();
+        graph
+            .outgoing(page)
+            .filter(|edge| graph.nodes()[edge.target].url == url)
+            .map(|edge| edge.href.clone())
+            .collect()
+    }
+
+    /// The graph of every URL and link found so far, with the result
+    /// of each URL checked. Usually taken once the crawl has finished.
+    /// It only has the seeds unless the crawl records links, see
+    /// `CrawlerBuilder::record_links`.
+    pub fn graph(&self) -> LinkGraph {
+        self.shared.graph().clone()
+    }
+
+    /// Progress events for the crawl, if the builder was asked to track
+    /// progress. All the receivers share one queue, so each event only
+    /// goes to one of them.
+    pub fn progress(&self) -> Option<ProgressEvents> {
+        self.progress.clone().map(ProgressEvents::new)
+    }
+
+    /// A handle for stopping the crawl from another thread.
+    pub fn cancel_handle(&self) -> CancelHandle {
+        CancelHandle {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+/// Stops a crawl, e.g. when the user closes a window. Once cancelled,
+/// workers stop checking new URLs, and the crawler stops returning
+/// results straight away. Requests already in flight are abandoned
+/// rather than waited for.
+#[derive(Clone)]
+pub struct CancelHandle {
+    shared: Arc<Shared>,
+}
+
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.shared.cancel();
+    }
+
+    /// Has the crawl been cancelled, or passed its deadline?
+    pub fn is_cancelled(&self) -> bool {
+        self.shared.is_cancelled()
+    }
+}
+
+/// Abandoning a crawl stops it, so the workers don't carry on checking
+/// URLs that nobody will see the results of.
+impl Drop for Crawler {
+    fn drop(&mut self) {
+        self.shared.cancel();
+    }
 }
 
 impl Iterator for Crawler {
-    type Item = UrlState;
+    type Item = CheckResult;
 
-    fn next(&mut self) -> Option<UrlState> {
+    fn next(&mut self) -> Option<CheckResult> {
         let backoff = Backoff::new();
         loop {
+            if self.shared.is_cancelled() {
+                return None;
+            }
             match self.url_states.try_recv() {
                 // If there's currently something in the channel, return
                 // it.
                 Ok(state) => return Some(state),
 
                 Err(_) => {
-                    {
-                        let active_count = self.active_count.lock().unwrap();
-                        if *active_count == 0 {
-                            // We're done, no values left.
-                            return None;
-                        }
+                    if self.shared.is_finished() {
+                        // We're done, but a worker may have sent its
+                        // last result after we looked.
+                        return self.url_states.try_recv().ok();
                     }
                     // The channel is currently empty, but we will
                     // have more values later.
@@ -40,64 +510,102 @@ impl Iterator for Crawler {
     }
 }
 
-const CRAWL_THREADS: i32 = 10;
+/// Results as they arrive, for async code. Workers wake the polling
+/// task whenever they send a result or finish.
+#[cfg(feature = "stream")]
+impl futures_core::Stream for Crawler {
+    type Item = CheckResult;
 
-/// Read URLs from the `url_r` channel, and write url states to the
-/// `url_states` channel. Write new URLs discovered back to the
-/// `url_s` channel.
-fn crawl_worker_thread(
-    domain: &str,
-    url_s: Sender<(String, String)>,
-    url_r: Receiver<(String, String)>,
-    visited: Arc<Mutex<HashSet<String>>>,
-    active_count: Arc<Mutex<i32>>,
-    url_states: Sender<UrlState>,
-) {
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<CheckResult>> {
+        // Register before looking, so we can't miss a result sent in
+        // between.
+        *self.shared.waker.lock().unwrap() = Some(cx.waker().clone());
+        if self.shared.is_cancelled() {
+            return Poll::Ready(None);
+        }
+        match self.url_states.try_recv() {
+            Ok(state) => Poll::Ready(Some(state)),
+            Err(_) if self.shared.is_finished() => Poll::Ready(self.url_states.try_recv().ok()),
+            Err(_) => Poll::Pending,
+        }
+    }
+}
+
+/// Take URLs from the store's queue, and write url states to the
+/// `url_states` channel. Queue new URLs discovered in the store.
+fn crawl_worker_thread(shared: &Shared, url_states: Sender<CheckResult>) {
+    debug!("worker started");
     loop {
-        match url_r.try_recv() {
-            Ok((old, current)) => {
-                {
-                    let mut active_count = active_count.lock().unwrap();
-                    *active_count += 1;
-                    assert!(*active_count <= CRAWL_THREADS);
-                }
+        if shared.is_cancelled() {
+            debug!("cancelled, worker stopping");
+            shared.wake();
+            break;
+        }
+        // Count as busy before taking a URL, so the crawl can't look
+        // finished while we hold one.
+        let busy = Busy::start(shared);
+        match shared.store.pop() {
+            Some((old, current, depth)) => {
+                shared.claimed.fetch_add(1, Ordering::SeqCst);
+                let span = info_span!("check", url = %current, referrer = %old, depth);
+                let _enter = span.enter();
 
-                // TODO: we are fetching the URL twice, which is silly.
-                let state = url_status(&domain, &old, &current);
-
-                // Fetch accessible URLs on the same domain and crawl them too.
-                if let UrlState::Accessible(_, ref url) = state.clone() {
-                    if url.domain() == Some(&domain) {
-                        // Lock `visited` and see if we've already visited these discovered URLs.
-                        let mut visited = visited.lock().unwrap();
-
-                        for new_url in fetch_all_urls(&url) {
-                            if !visited.contains(&new_url) {
-                                visited.insert(new_url.clone());
-                                url_s.send((url.as_str().into(), new_url)).unwrap();
-                            }
-                        }
+                // A bug checking one URL shouldn't kill the worker, or
+                // leave the crawl waiting for a result that never comes.
+                let checked = panic::catch_unwind(AssertUnwindSafe(|| {
+                    check_url(shared, &old, &current, depth)
+                }));
+                let mut state = match checked {
+                    Ok(Some(state)) => state,
+                    Ok(None) => {
+                        shared.store.skip(&current);
+                        continue;
                     }
-                }
+                    Err(panic) => {
+                        let message = panic_message(panic.as_ref());
+                        error!(panic = %message, "worker panicked");
+                        let mut state = CheckResult::new(&old, &current, Outcome::ConnectionFailed);
+                        state.error =
+                            Some(format!("linkdoc crashed checking this URL: {}", message));
+                        state
+                    }
+                };
+                state.depth = Some(depth);
 
-                {
-                    // This thread is now done, so decrement the count.
-                    let mut active_count = active_count.lock().unwrap();
-                    *active_count -= 1;
-                    assert!(*active_count >= 0);
+                // Nor should a hook that panics, since the result hooks
+                // are the embedding application's code.
+                let recorded = panic::catch_unwind(AssertUnwindSafe(|| {
+                    record_result(shared, &current, &state)
+                }));
+                if let Err(panic) = recorded {
+                    let message = panic_message(panic.as_ref());
+                    error!(panic = %message, "worker panicked recording a result");
+                    state.error = Some(format!(
+                        "linkdoc crashed recording the result of this URL: {}",
+                        message
+                    ));
                 }
-
-                url_states.send(state).unwrap();
+                shared.progress.record(EventKind::Finished, &current);
+                if url_states.send(state).is_err() {
+                    // The crawler was dropped, so nobody wants the
+                    // results any more. Dropping it cancels the crawl,
+                    // but make sure the other workers stop too.
+                    debug!("crawler dropped, worker stopping");
+                    shared.cancel();
+                    break;
+                }
+                shared.wake();
             }
-            Err(_) => {
-                let active_count = active_count.lock().unwrap();
-                // Nothing in the channel for us to do.
-                // If there are requests still in flight, we might
-                // get more work in the future.
-                if *active_count > 0 {
-                    // snooze
+            None => {
+                // Nothing in the queue for us to do. If other workers
+                // are busy, we might get more work in the future.
+                drop(busy);
+                if !shared.is_finished() {
+                    thread::yield_now();
                 } else {
                     // There won't be any more URLs to visit, so terminate this thread.
+                    debug!("worker finished");
+                    shared.wake();
                     break;
                 }
             }
@@ -105,45 +613,785 @@ fn crawl_worker_thread(
     }
 }
 
-/// Starting at start_url, recursively iterate over all the URLs which match
-/// the domain, and return an iterator of their URL status.
-pub fn crawl(domain: &str, start_url: &Url) -> Crawler {
-    let active_count = Arc::new(Mutex::new(0));
+/// Remember the result of checking `current`, and tell the hooks.
+fn record_result(shared: &Shared, current: &str, state: &CheckResult) {
+    shared.store.finish(current, state);
+    if shared.record_links {
+        shared.graph.set_result(state.clone());
+    }
+    shared.stats.record_result(state);
+    shared.hooks.result(state);
+}
+
+/// Counts a worker as busy until it's dropped, even if the worker
+/// panics.
+struct Busy<'a>(&'a Shared);
+
+impl<'a> Busy<'a> {
+    fn start(shared: &'a Shared) -> Self {
+        let count = shared.busy.fetch_add(1, Ordering::SeqCst) + 1;
+        let busy = Busy(shared);
+        assert!(count <= shared.concurrency);
+        busy
+    }
+}
+
+impl Drop for Busy<'_> {
+    fn drop(&mut self) {
+        self.0.busy.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// The text of a caught panic.
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    match (panic.downcast_ref::<&str>(), panic.downcast_ref::<String>()) {
+        (Some(message), _) => (*message).to_owned(),
+        (_, Some(message)) => message.clone(),
+        _ => "unknown panic".to_owned(),
+    }
+}
+
+/// Check `current`, linked from `old`, and queue the links on it if
+/// it's a page on the site. Returns None if we skipped the URL.
+fn check_url(shared: &Shared, old: &str, current: &str, depth: usize) -> Option<CheckResult> {
+    let domain = shared.domain.as_str();
+    if let Ok(url) = build_url(domain, current) {
+        if !shared.pipeline.filter(old, &url) {
+            trace!("filtered out, not checking");
+            shared.progress.record(EventKind::Skipped, current);
+            return None;
+        }
+        match shared.schemes.rule_for(&url) {
+            SchemeRule::Check => {}
+            SchemeRule::Skip => {
+                trace!(scheme = url.scheme(), "scheme skipped, not checking");
+                shared.progress.record(EventKind::Skipped, current);
+                return None;
+            }
+            SchemeRule::Forbid => {
+                debug!(scheme = url.scheme(), "forbidden scheme");
+                let mut state = CheckResult::new(old, url.as_str(), Outcome::ForbiddenScheme);
+                state.error = Some(format!("{}: links aren't allowed", url.scheme()));
+                let state = shared.pipeline.result(state);
+                shared
+                    .metrics
+                    .record(&state, Duration::default(), shared.store.len());
+                return Some(state);
+            }
+        }
+        if shared.list_only && !shared.in_scope(&url) {
+            trace!("not on {}, not checking", domain);
+            shared.progress.record(EventKind::Skipped, current);
+            return None;
+        }
+        // Don't request anything from a blocklisted domain.
+        if let Some(listed) = shared.blocklist.listed(&url) {
+            debug!(domain = listed, "blocklisted");
+            let mut state = CheckResult::new(old, url.as_str(), Outcome::Blocklisted);
+            state.error = Some(format!("{} is on a blocklist", listed));
+            let state = shared.pipeline.result(state);
+            shared
+                .metrics
+                .record(&state, Duration::default(), shared.store.len());
+            return Some(state);
+        }
+        // Don't wait for a connection to a host that doesn't exist.
+        let unresolvable = match shared.hosts {
+            Some(ref hosts) if !shared.in_scope(&url) => hosts.unresolvable(&url),
+            _ => None,
+        };
+        if let Some(error) = unresolvable {
+            debug!(error = %error, "host doesn't resolve");
+            let mut state = CheckResult::new(old, url.as_str(), Outcome::ConnectionFailed);
+            state.error = Some(error);
+            let state = shared.pipeline.result(state);
+            shared
+                .metrics
+                .record(&state, Duration::default(), shared.store.len());
+            return Some(state);
+        }
+    }
+
+    // We're still busy while waiting our turn, so the crawl isn't
+    // considered finished.
+    let allowed = match build_url(domain, current) {
+        Ok(url) => wait_for_turn(shared, &url),
+        Err(_) => true,
+    };
+    if !allowed {
+        trace!("not allowed by the politeness policy");
+        shared.progress.record(EventKind::Skipped, current);
+        return None;
+    }
+    shared.progress.record(EventKind::Started, current);
+
+    let url = build_url(domain, current).ok();
+    // TODO: we are fetching the URL twice, which is silly.
+    let start = Instant::now();
+    let mut state = url_status(
+        shared.fetcher.as_ref(),
+        domain,
+        old,
+        current,
+        shared.timeout(url.as_ref()),
+    );
+    for attempt in 1..=shared.retries(url.as_ref()) {
+        if state.is_accessible() || shared.is_cancelled() {
+            break;
+        }
+        debug!(attempt, reason = %state.reason(), "retrying");
+        shared.progress.record(EventKind::Retried, current);
+        state = url_status(
+            shared.fetcher.as_ref(),
+            domain,
+            old,
+            current,
+            shared.timeout(url.as_ref()),
+        );
+    }
+    let elapsed = start.elapsed();
+    state.duration = Some(elapsed);
+    let mut state = shared.pipeline.result(state);
+
+    // A page on the site that bounces us to sign in isn't really
+    // accessible, and we shouldn't crawl the sign-in page.
+    let internal = state
+        .parsed_url()
+        .map_or(false, |url| shared.in_scope(&url));
+    if state.is_accessible() && internal {
+        if let Some(reason) = login::login_redirect(&state.redirects) {
+            state.outcome = Outcome::LoginRequired;
+            state.error = Some(reason);
+        }
+    }
+
+    debug!(result = state.category(), "checked");
 
-    let mut visited = HashSet::with_capacity(1);
-    visited.insert(start_url.as_str().into());
-    let visited = Arc::new(Mutex::new(visited));
+    // Fetch accessible URLs on the same site and crawl them too.
+    let page_url = if state.is_accessible() {
+        state.parsed_url()
+    } else {
+        None
+    };
+    if let Some(ref url) = page_url {
+        let too_deep = shared.max_depth.map_or(false, |max| depth >= max);
+        if too_deep {
+            trace!("at the maximum depth, not crawling");
+            check_assertions(shared, url, None, &mut state);
+        } else if shared.recurse && shared.in_scope(url) {
+            match shared.fetcher.fetch(url, shared.timeout(Some(url))) {
+                Ok(page) => {
+                    let soft_404 = shared
+                        .soft_404
+                        .as_ref()
+                        .and_then(|detector| detector.check(&page));
+                    if let Some(reason) = soft_404 {
+                        debug!(reason = %reason, "soft 404");
+                        state.outcome = Outcome::Soft404;
+                        state.error = Some(reason);
+                    }
+                    shared.stats.record_page(page.body.len());
+                    shared.hooks.page_fetched(url, &page.body);
+                    crawl_page(shared, url, &page, depth);
+                    check_assertions(shared, url, Some(&page), &mut state);
+                }
+                Err(e) => {
+                    debug!(error = %e, "could not fetch page");
+                    state.error = Some(format!("could not fetch page: {}", e));
+                }
+            }
+        } else {
+            trace!("not on {}, not crawling", domain);
+            check_assertions(shared, url, None, &mut state);
+        }
+        if shared.detect_parked && !shared.in_scope(url) {
+            check_parked(shared, url, &mut state);
+        }
+    }
+    // Record the result once we know whether the page was a soft 404,
+    // parked or broke an assertion.
+    shared.metrics.record(&state, elapsed, shared.store.len());
+    Some(state)
+}
 
-    let (url_state_s, url_state_r) = unbounded();
-    let (visit_s, visit_r) = unbounded();
-    visit_s
-        .send((start_url.as_str().into(), start_url.as_str().into()))
-        .unwrap();
+/// Mark `state`, the accessible result for `url`, as failing if `page`
+/// breaks any of the assertions for it, or its response breaks any
+/// about headers. We fetch `url` if the crawl didn't, but only when
+/// there are assertions about the page.
+fn check_assertions(shared: &Shared, url: &Url, page: Option<&Page>, state: &mut CheckResult) {
+    if !state.is_accessible() {
+        return;
+    }
+    if shared.assertions.apply_to(url) {
+        let fetched;
+        let page = match page {
+            Some(page) => page,
+            None => match shared.fetcher.fetch(url, shared.timeout(Some(url))) {
+                Ok(page) => {
+                    fetched = page;
+                    &fetched
+                }
+                Err(e) => {
+                    debug!(error = %e, "could not fetch page to check assertions");
+                    state.error = Some(format!("could not fetch page: {}", e));
+                    return;
+                }
+            },
+        };
+        let broken = shared.assertions.broken(url, page, state.size);
+        if !broken.is_empty() {
+            debug!(count = broken.len(), "assertions failed");
+            state.outcome = Outcome::AssertionFailed;
+            state.error = Some(format!("expected {}", broken.join(", ")));
+            return;
+        }
+    }
+    // Only HTTP responses have headers.
+    if state.status.is_some() {
+        let broken = shared.assertions.broken_headers(url, &state.headers);
+        if !broken.is_empty() {
+            debug!(count = broken.len(), "header assertions failed");
+            state.outcome = Outcome::HeaderAssertionFailed;
+            state.error = Some(format!("expected {}", broken.join(", ")));
+        }
+    }
+}
 
-    let crawler = Crawler {
-        active_count: active_count.clone(),
-        url_states: url_state_r,
+/// Mark `state`, the accessible result for `url` on another site, as
+/// parked if it was redirected to a domain marketplace or the page
+/// looks like a parking page.
+fn check_parked(shared: &Shared, url: &Url, state: &mut CheckResult) {
+    let reason = match parking::parked_redirect(&state.redirects) {
+        Some(reason) => Some(reason),
+        None => match shared.fetcher.fetch(url, shared.timeout(Some(url))) {
+            Ok(page) => parking::parked_page(&page),
+            Err(e) => {
+                debug!(error = %e, "could not fetch page to check for parking");
+                None
+            }
+        },
+    };
+    if let Some(reason) = reason {
+        debug!(reason = %reason, "parked domain");
+        state.outcome = Outcome::Parked;
+        state.error = Some(reason);
+    }
+}
+
+/// Wait until the politeness policy lets us request `url`. Returns
+/// false if we shouldn't request it at all, or the crawl was cancelled
+/// while we waited.
+fn wait_for_turn(shared: &Shared, url: &Url) -> bool {
+    let politeness = match shared.politeness {
+        Some(ref politeness) => politeness,
+        None => return true,
     };
+    loop {
+        match politeness.permit(url, Instant::now()) {
+            Permit::Now => return true,
+            Permit::Never => return false,
+            Permit::After(delay) => {
+                trace!(delay_ms = delay.as_millis() as u64, "waiting our turn");
+                thread::sleep(delay);
+                if shared.is_cancelled() {
+                    return false;
+                }
+            }
+        }
+    }
+}
+
+/// Queue every link on `page` that we haven't already visited.
+fn crawl_page(shared: &Shared, url: &Url, page: &Page, depth: usize) {
+    let mut queued = 0;
+
+    let mut new_urls = shared
+        .extractors
+        .extract(page.content_type.as_deref(), &page.body);
+    let is_html = page.content_type.as_deref().map_or(true, |content_type| {
+        content_type.to_ascii_lowercase().starts_with("text/html")
+    });
+    if shared.check_favicon && is_html && !parsing::declares_icon(&page.body) {
+        new_urls.push("/favicon.ico".to_owned());
+    }
+    // Resolve relative links against the page, in the same form as the
+    // URL states report, so the two can be matched up. Malformed links
+    // are kept as written.
+    let links: Vec<_> = new_urls
+        .into_iter()
+        .map(|new_url| match url.join(&new_url) {
+            Ok(target) => {
+                let (target, key) = prepare(&shared.query_rules, shared.keep_fragments, target);
+                (new_url, target, key)
+            }
+            Err(_) => (new_url.clone(), new_url.clone(), new_url),
+        })
+        .collect();
+
+    if shared.record_links {
+        let edges = links
+            .iter()
+            .map(|(new_url, target, _)| (target.clone(), new_url.clone()))
+            .collect();
+        shared.graph.add_links(url.as_str(), edges);
+    }
 
-    for _ in 0..CRAWL_THREADS {
-        let domain = domain.to_owned();
-        let visited = visited.clone();
-        let active_count = active_count.clone();
-        let url_state_s = url_state_s.clone();
-        let visit_r = visit_r.clone();
-        let visit_s = visit_s.clone();
-
-        thread::spawn(move || {
-            crawl_worker_thread(
-                &domain,
-                visit_s,
-                visit_r,
-                visited,
-                active_count,
-                url_state_s,
-            );
+    for (_, target, key) in links {
+        shared.hooks.link_discovered(url.as_str(), &target);
+
+        // Forbidden schemes are queued, to be reported.
+        let skipped = Url::parse(&target).map_or(false, |target| {
+            shared.schemes.rule_for(&target) == SchemeRule::Skip
         });
+        if skipped {
+            trace!(link = %target, "scheme skipped, not queueing");
+            continue;
+        }
+        if shared.store.visit(&key) {
+            trace!(link = %target, "queueing");
+            shared.progress.record(EventKind::Queued, &target);
+            shared.store.push((url.as_str().into(), target, depth + 1));
+            queued += 1;
+        } else {
+            trace!(link = %target, "already visited, skipping");
+        }
+    }
+    debug!(queued, queue_depth = shared.store.len(), "crawled page");
+}
+
+/// `url` in the form we check it, and the key we deduplicate it by.
+fn prepare(query_rules: &QueryRules, keep_fragments: bool, url: Url) -> (String, String) {
+    let url = query_rules.strip(normalizing::normalize(url));
+    let mut key = url.clone();
+    if !keep_fragments {
+        key.set_fragment(None);
+    }
+    (url.into_string(), query_rules.visit_key(&key))
+}
+
+/// Configures and starts a `Crawler`, e.g.
+///
+/// ```no_run
+/// # use linkdoc::Crawler;
+/// # let start_url = url::Url::parse("http://example.com").unwrap();
+/// let crawler = Crawler::builder()
+///     .seed(&start_url)
+///     .max_depth(3)
+///     .concurrency(20)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Default)]
+pub struct CrawlerBuilder {
+    /// (referrer, URL) pairs to start from.
+    seeds: Vec<(String, String)>,
+    check_only: bool,
+    options: CrawlOptions,
+    middleware: Pipeline,
+    fetcher: Option<Box<dyn Fetcher>>,
+    extractors: Extractors,
+    hooks: Hooks,
+    deadline: Option<Instant>,
+    track_progress: bool,
+    politeness: Option<Box<dyn Politeness>>,
+    store: Option<Box<dyn CrawlStore>>,
+    record_links: Option<bool>,
+}
+
+impl Crawler {
+    pub fn builder() -> CrawlerBuilder {
+        CrawlerBuilder::default()
+    }
+}
+
+/// Crawl from `start_url`, returning the crawler to iterate over its
+/// results, each call to `next` blocking until one is ready. A shortcut
+/// for `Crawler::builder().seed(start_url).options(options).build()`,
+/// for scripts that don't need anything else from the builder.
+pub fn crawl_blocking(start_url: &Url, options: CrawlOptions) -> Result<Crawler, CrawlError> {
+    Crawler::builder().seed(start_url).options(options).build()
+}
+
+impl CrawlerBuilder {
+    /// Start crawling at `url`. We follow links on the host and port of
+    /// the first seed, see `Scope`.
+    pub fn seed(mut self, url: &Url) -> Self {
+        self.seeds.push((url.as_str().into(), url.as_str().into()));
+        self
+    }
+
+    /// Check `url`, reporting `referrer` as the page linking to it. A
+    /// URL added with several referrers is only checked once.
+    pub fn url(mut self, referrer: &str, url: &str) -> Self {
+        self.seeds.push((referrer.to_owned(), url.to_owned()));
+        self
+    }
+
+    /// Only check the seeds and URLs given, without following any
+    /// links.
+    pub fn check_only(mut self, check_only: bool) -> Self {
+        self.check_only = check_only;
+        self
+    }
+
+    /// Replace all the crawl settings at once.
+    pub fn options(mut self, options: CrawlOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.options.concurrency = concurrency;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = timeout;
+        self
+    }
+
+    /// Ask `politeness` before every request, e.g. `FixedDelay`,
+    /// `TokenBucket` or `Robots` from the `politeness` module.
+    pub fn politeness(mut self, politeness: impl Politeness + 'static) -> Self {
+        self.politeness = Some(Box::new(politeness));
+        self
+    }
+
+    /// Keep the crawl's state in `store` rather than in memory, e.g. a
+    /// `SqliteStore` to resume the crawl later.
+    pub fn store(mut self, store: impl CrawlStore + 'static) -> Self {
+        self.store = Some(Box::new(store));
+        self
+    }
+
+    /// Keep every link found, and the result of every URL, in the link
+    /// graph behind `Crawler::graph`, `links` and `referrers`. It grows
+    /// with every URL, so by default it's only kept if the store doesn't
+    /// bound its memory use, see `CrawlStore::bounds_memory`. Without it
+    /// those only have the seeds, and each result only names the page
+    /// its URL was first found on.
+    pub fn record_links(mut self, record_links: bool) -> Self {
+        self.record_links = Some(record_links);
+        self
+    }
+
+    /// Send progress events, see `Crawler::progress`.
+    pub fn track_progress(mut self, track_progress: bool) -> Self {
+        self.track_progress = track_progress;
+        self
     }
 
-    crawler
+    /// Cancel the crawl at `deadline`, if it hasn't finished by then.
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Treat `www.example.com` and `example.com` as the same site.
+    pub fn ignore_www(mut self, ignore_www: bool) -> Self {
+        self.options.ignore_www = ignore_www;
+        self
+    }
+
+    /// Check links to the same page with different fragments
+    /// separately, see `CrawlOptions::keep_fragments`.
+    pub fn keep_fragments(mut self, keep_fragments: bool) -> Self {
+        self.options.keep_fragments = keep_fragments;
+        self
+    }
+
+    /// Report pages on the site that look like error pages with
+    /// `detector`, see `CrawlOptions::soft_404`.
+    pub fn soft_404(mut self, detector: Soft404Detector) -> Self {
+        self.options.soft_404 = Some(detector);
+        self
+    }
+
+    /// Report links to parked domains, see
+    /// `CrawlOptions::detect_parked`.
+    pub fn detect_parked(mut self, detect_parked: bool) -> Self {
+        self.options.detect_parked = detect_parked;
+        self
+    }
+
+    /// Report links to domains on `blocklist`, see
+    /// `CrawlOptions::blocklist`.
+    pub fn blocklist(mut self, blocklist: Blocklist) -> Self {
+        self.options.blocklist = blocklist;
+        self
+    }
+
+    /// Check the `/favicon.ico` that browsers request for pages
+    /// without an icon, see `CrawlOptions::check_favicon`.
+    pub fn check_favicon(mut self, check_favicon: bool) -> Self {
+        self.options.check_favicon = check_favicon;
+        self
+    }
+
+    /// Check pages against `assertions`, see
+    /// `CrawlOptions::assertions`.
+    pub fn assertions(mut self, assertions: Assertions) -> Self {
+        self.options.assertions = assertions;
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.options.max_depth = Some(max_depth);
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.options.user_agent = Some(user_agent.to_owned());
+        self
+    }
+
+    pub fn retries(mut self, retries: usize) -> Self {
+        self.options.retries = retries;
+        self
+    }
+
+    pub fn ignore(mut self, ignore: IgnoreList) -> Self {
+        self.options.ignore = ignore;
+        self
+    }
+
+    pub fn query_rules(mut self, query_rules: QueryRules) -> Self {
+        self.options.query_rules = query_rules;
+        self
+    }
+
+    pub fn archive(mut self, archive: Arc<WarcWriter>) -> Self {
+        self.options.archive = Some(archive);
+        self
+    }
+
+    pub fn dump_failures(mut self, dump: Arc<FailureDump>) -> Self {
+        self.options.dump_failures = Some(dump);
+        self
+    }
+
+    /// Add `middleware` to the end of the pipeline. The ignore list
+    /// always runs first.
+    pub fn middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Check and fetch URLs with `fetcher`, rather than over HTTP. The
+    /// user agent, archive, failure dump, and the `request` and `response` stages of
+    /// any middleware are then up to `fetcher`.
+    pub fn fetcher(mut self, fetcher: impl Fetcher + 'static) -> Self {
+        self.fetcher = Some(Box::new(fetcher));
+        self
+    }
+
+    /// Find the links in pages of `content_type` with `extractor`,
+    /// replacing the built-in extractor if there is one.
+    pub fn extractor(
+        mut self,
+        content_type: &str,
+        extractor: impl LinkExtractor + 'static,
+    ) -> Self {
+        self.extractors.register(content_type, Arc::new(extractor));
+        self
+    }
+
+    /// Call `hook` with the URL and body of every page we fetch to find
+    /// its links.
+    pub fn on_page_fetched(mut self, hook: impl Fn(&Url, &str) + Send + Sync + 'static) -> Self {
+        self.hooks.page_fetched.push(Box::new(hook));
+        self
+    }
+
+    /// Call `hook` with the page and target of every link we find,
+    /// including links to URLs we've already visited.
+    pub fn on_link_discovered(mut self, hook: impl Fn(&str, &str) + Send + Sync + 'static) -> Self {
+        self.hooks.link_discovered.push(Box::new(hook));
+        self
+    }
+
+    /// Call `hook` with the state of every URL we check, before it's
+    /// returned by the iterator.
+    pub fn on_result(mut self, hook: impl Fn(&CheckResult) + Send + Sync + 'static) -> Self {
+        self.hooks.result.push(Box::new(hook));
+        self
+    }
+
+    /// Call `hook` with the state of every URL that isn't accessible.
+    pub fn on_error(mut self, hook: impl Fn(&CheckResult) + Send + Sync + 'static) -> Self {
+        self.hooks.error.push(Box::new(hook));
+        self
+    }
+
+    /// Start the worker threads, and return the crawler to iterate
+    /// over the results.
+    pub fn build(self) -> Result<Crawler, CrawlError> {
+        let options = self.options;
+        if options.concurrency == 0 {
+            return Err(CrawlError::ZeroConcurrency);
+        }
+        let scope = match (self.check_only, self.seeds.first()) {
+            (_, None) => return Err(CrawlError::NoSeeds),
+            (true, Some(_)) => None,
+            (false, Some((_, url))) => match Url::parse(url).ok().as_ref().and_then(Scope::new) {
+                Some(scope) => Some(scope.ignore_www(options.ignore_www)),
+                None => return Err(CrawlError::NoDomain(url.clone())),
+            },
+        };
+        let domain = scope
+            .as_ref()
+            .map(|scope| scope.host().to_owned())
+            .unwrap_or_default();
+        let mut pipeline = Pipeline::default();
+        pipeline.push(Arc::new(options.ignore));
+        for stage in self.middleware.stages() {
+            pipeline.push(stage.clone());
+        }
+        let fetcher = match self.fetcher {
+            Some(fetcher) => fetcher,
+            None => {
+                let client = fetching::client(options.user_agent.as_deref())?;
+                let mut headers = options.capture_headers.clone();
+                headers.extend(options.assertions.header_names());
+                let mut fetcher = HttpFetcher::new(client, pipeline.clone(), options.archive)
+                    .capture_headers(headers)
+                    .max_redirects(options.max_redirects)
+                    .method(options.method);
+                if let Some(dump) = options.dump_failures {
+                    fetcher = fetcher.dump_failures(dump);
+                }
+                Box::new(fetcher)
+            }
+        };
+
+        let keep_fragments = options.keep_fragments || fetcher.checks_fragments();
+
+        // See what the site's 404 page looks like, so we can spot pages
+        // that look the same.
+        let mut soft_404 = options.soft_404;
+        if let (Some(detector), Some(_)) = (soft_404.as_mut(), scope.as_ref()) {
+            if let Some(site) = self.seeds.first().and_then(|(_, url)| Url::parse(url).ok()) {
+                detector.probe(fetcher.as_ref(), &site, options.timeout);
+            }
+        }
+
+        // Check each URL once, but when only checking a list of URLs,
+        // remember every referrer we were given for it.
+        let store = self
+            .store
+            .unwrap_or_else(|| Box::new(MemoryStore::default()));
+        let record_links = self.record_links.unwrap_or(!store.bounds_memory());
+        let mut queue = vec![];
+        let mut graph = LinkGraph::default();
+        for (referrer, url) in self.seeds {
+            let (url, key) = match Url::parse(&url) {
+                Ok(parsed) => prepare(&options.query_rules, keep_fragments, parsed),
+                Err(_) => (url.clone(), url),
+            };
+            if self.check_only {
+                let target = match build_url(&domain, &url) {
+                    Ok(target) => target.into_string(),
+                    Err(_) => url.clone(),
+                };
+                graph.add_link(&referrer, &target, &url);
+            }
+            if store.visit(&key) {
+                queue.push((referrer, url, 0));
+            }
+        }
+
+        // Look up every other site's host at once, and check the seeds
+        // on hosts that don't exist first, since we know they're broken.
+        let hosts = if options.dns_prepass {
+            let hosts = HostCache::default();
+            let external = |url: &str| {
+                build_url(&domain, url)
+                    .ok()
+                    .filter(|url| scope.as_ref().map_or(true, |scope| !scope.contains(url)))
+            };
+            let urls: Vec<Url> = queue
+                .iter()
+                .filter_map(|(_, url, _)| external(url))
+                .collect();
+            hosts.resolve_all(&urls, options.concurrency);
+            // The sort is stable, so the seeds keep their order otherwise.
+            queue.sort_by_key(|(_, url, _)| match external(url) {
+                Some(url) => hosts.unresolvable(&url).is_none(),
+                None => true,
+            });
+            Some(hosts)
+        } else {
+            None
+        };
+
+        let (progress_s, progress_r) = if self.track_progress {
+            let (s, r) = unbounded();
+            (Some(s), Some(r))
+        } else {
+            (None, None)
+        };
+        let progress = Tracker::new(progress_s);
+        for (_, url, _) in &queue {
+            progress.record(EventKind::Queued, url);
+        }
+
+        let shared = Arc::new(Shared {
+            domain,
+            scope,
+            recurse: !self.check_only,
+            concurrency: options.concurrency,
+            timeout: options.timeout,
+            store,
+            query_rules: options.query_rules,
+            keep_fragments,
+            soft_404,
+            detect_parked: options.detect_parked,
+            blocklist: options.blocklist,
+            schemes: options.schemes,
+            hosts,
+            check_favicon: options.check_favicon,
+            assertions: options.assertions,
+            busy: AtomicUsize::new(0),
+            claimed: AtomicUsize::new(0),
+            metrics: Metrics::default(),
+            graph: SharedGraph::new(graph),
+            record_links,
+            list_only: options.list_only,
+            pipeline,
+            retries: options.retries,
+            tuning: options.tuning,
+            max_depth: options.max_depth,
+            fetcher,
+            extractors: self.extractors,
+            hooks: self.hooks,
+            waker: Mutex::new(None),
+            cancelled: AtomicBool::new(false),
+            deadline: self.deadline,
+            progress,
+            stats: Counters::default(),
+            politeness: self.politeness,
+        });
+
+        let (url_state_s, url_state_r) = unbounded();
+        for seed in queue {
+            shared.store.push(seed);
+        }
+
+        let crawler = Crawler {
+            url_states: url_state_r,
+            progress: progress_r,
+            shared: shared.clone(),
+        };
+
+        for _ in 0..shared.concurrency {
+            let shared = shared.clone();
+            let url_state_s = url_state_s.clone();
+
+            thread::spawn(move || {
+                crawl_worker_thread(&shared, url_state_s);
+            });
+        }
+
+        Ok(crawler)
+    }
 }
@@ -0,0 +1,240 @@
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tungstenite::Message;
+
+use linkdoc::fetching::CheckResult;
+use linkdoc::severity::Severity;
+
+/// How often we push updates to connected browsers.
+const UPDATE_INTERVAL: Duration = Duration::from_millis(500);
+
+const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Link Doctor</title>
+<style>
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+td, th { border: 1px solid #ccc; padding: 0.2em 0.5em; text-align: left; }
+.error { color: #c00; }
+.warning { color: #b80; }
+</style>
+</head>
+<body>
+<h1>Link Doctor: <span id="start-url"></span></h1>
+<p id="progress"></p>
+<h2>Hosts</h2>
+<table id="hosts"></table>
+<h2>Broken links</h2>
+<table id="failures"></table>
+<script>
+function cell(tag, text, cls) {
+  var el = document.createElement(tag);
+  el.textContent = text;
+  if (cls) { el.className = cls; }
+  return el;
+}
+function row(tag, values, cls) {
+  var tr = document.createElement("tr");
+  values.forEach(function (v) { tr.appendChild(cell(tag, v, cls)); });
+  return tr;
+}
+var ws = new WebSocket("ws://" + location.host + "/ws");
+ws.onmessage = function (event) {
+  var state = JSON.parse(event.data);
+  document.getElementById("start-url").textContent = state.start_url;
+  document.getElementById("progress").textContent =
+    "Succeeded: " + state.succeeded + " Failed: " + state.failed +
+    (state.finished ? " (finished)" : " (crawling...)");
+
+  var hosts = document.getElementById("hosts");
+  hosts.innerHTML = "";
+  hosts.appendChild(row("th", ["Host", "OK", "Broken"]));
+  Object.keys(state.hosts).forEach(function (host) {
+    var counts = state.hosts[host];
+    hosts.appendChild(row("td", [host, counts.ok, counts.broken]));
+  });
+
+  // Only failures we haven't seen yet are sent after the first update.
+  var failures = document.getElementById("failures");
+  if (state.failures_from === 0) {
+    failures.innerHTML = "";
+    failures.appendChild(row("th", ["URL", "Linked from", "Problem"]));
+  }
+  state.failures.forEach(function (f) {
+    failures.appendChild(row("td", [f.url, f.referrer, f.reason], f.severity));
+  });
+};
+</script>
+</body>
+</html>
+"#;
+
+#[derive(Debug, Default)]
+struct HostCounts {
+    ok: usize,
+    broken: usize,
+}
+
+/// The crawl progress, as shown on the dashboard.
+#[derive(Debug, Default)]
+struct Progress {
+    /// The sites being crawled, more than one under `linkdoc serve`.
+    sites: Vec<String>,
+    /// Crawls that haven't finished yet.
+    running: usize,
+    succeeded: usize,
+    failures: Vec<serde_json::Value>,
+    hosts: BTreeMap<String, HostCounts>,
+    /// Bumped on every change, so we only send updates when there's
+    /// something new.
+    version: usize,
+}
+
+impl Progress {
+    /// The progress as JSON, with only the failures from index
+    /// `failures_from` on.
+    fn to_json(&self, failures_from: usize) -> serde_json::Value {
+        let hosts: BTreeMap<_, _> = self
+            .hosts
+            .iter()
+            .map(|(host, counts)| (host, json!({"ok": counts.ok, "broken": counts.broken})))
+            .collect();
+        json!({
+            "start_url": self.sites.join(", "),
+            "succeeded": self.succeeded,
+            "failed": self.failures.len(),
+            "failures_from": failures_from,
+            "failures": &self.failures[failures_from..],
+            "hosts": hosts,
+            "finished": self.running == 0,
+        })
+    }
+}
+
+/// A small web UI showing crawl progress, updated live over a
+/// websocket.
+#[derive(Clone)]
+pub struct Dashboard {
+    progress: Arc<Mutex<Progress>>,
+}
+
+impl Dashboard {
+    /// Start serving the dashboard on `addr` in the background. Call
+    /// `crawling` for each crawl it should show.
+    pub fn start(addr: &str) -> std::io::Result<Dashboard> {
+        let listener = TcpListener::bind(addr)?;
+        let dashboard = Dashboard {
+            progress: Arc::new(Mutex::new(Progress::default())),
+        };
+
+        let server = dashboard.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let server = server.clone();
+                thread::spawn(move || server.handle(stream));
+            }
+        });
+
+        Ok(dashboard)
+    }
+
+    /// Show a crawl of `start_url` starting, until its `finish`.
+    pub fn crawling(&self, start_url: &str) {
+        let mut progress = self.progress.lock().unwrap();
+        progress.sites.push(start_url.to_owned());
+        progress.running += 1;
+        progress.version += 1;
+    }
+
+    /// Add a result to the dashboard.
+    pub fn record(&self, state: &CheckResult, severity: Severity) {
+        let mut progress = self.progress.lock().unwrap();
+        progress.version += 1;
+        let host = match state.parsed_url() {
+            Some(url) => url.host_str().map(|h| h.to_owned()).unwrap_or_default(),
+            None => "(malformed)".to_owned(),
+        };
+        let counts = progress.hosts.entry(host).or_default();
+
+        if state.is_accessible() {
+            counts.ok += 1;
+            progress.succeeded += 1;
+        } else {
+            counts.broken += 1;
+            progress.failures.push(json!({
+                "url": state.url_str(),
+                "referrer": state.referrer(),
+                "reason": state.reason(),
+                "severity": severity.name(),
+            }));
+        }
+    }
+
+    /// Mark one of the crawls as finished.
+    pub fn finish(&self) {
+        let mut progress = self.progress.lock().unwrap();
+        progress.running = progress.running.saturating_sub(1);
+        progress.version += 1;
+    }
+
+    fn handle(&self, mut stream: TcpStream) {
+        // Peek at the request line to see whether this is the
+        // websocket, without consuming the handshake.
+        let mut buf = [0; 64];
+        let n = match stream.peek(&mut buf) {
+            Ok(n) => n,
+            Err(_) => return,
+        };
+        if buf[..n].starts_with(b"GET /ws ") {
+            self.stream_updates(stream);
+        } else {
+            // We serve the same page for every other path, so we
+            // don't care what the request actually said.
+            let mut request = [0; 4096];
+            let _ = stream.read(&mut request);
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                PAGE.len(),
+                PAGE
+            );
+        }
+    }
+
+    fn stream_updates(&self, stream: TcpStream) {
+        let mut socket = match tungstenite::accept(stream) {
+            Ok(socket) => socket,
+            Err(_) => return,
+        };
+
+        // The first message has every failure so far, later ones only
+        // add the new ones: long crawls can have thousands.
+        let mut sent: Option<(usize, usize)> = None;
+        loop {
+            let message = {
+                let progress = self.progress.lock().unwrap();
+                match sent {
+                    Some((version, _)) if version == progress.version => None,
+                    _ => {
+                        let failures_from = sent.map_or(0, |(_, failures)| failures);
+                        sent = Some((progress.version, progress.failures.len()));
+                        Some(progress.to_json(failures_from).to_string())
+                    }
+                }
+            };
+            if let Some(message) = message {
+                if socket.write_message(Message::Text(message)).is_err() {
+                    break;
+                }
+            }
+            thread::sleep(UPDATE_INTERVAL);
+        }
+    }
+}
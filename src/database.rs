@@ -0,0 +1,106 @@
+use chrono::Utc;
+use rusqlite::{params, Connection};
+use std::path::Path;
+
+use crate::fetching::CheckResult;
+use crate::reporting::Report;
+use crate::severity::Severity;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    start_url TEXT NOT NULL,
+    started_at TEXT NOT NULL,
+    finished_at TEXT,
+    succeeded INTEGER,
+    failed INTEGER
+);
+CREATE TABLE IF NOT EXISTS results (
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    url TEXT NOT NULL,
+    category TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    severity TEXT NOT NULL,
+    status INTEGER,
+    duration_ms INTEGER,
+    checked_at TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS referrers (
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    url TEXT NOT NULL,
+    referrer TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS results_url ON results(url);
+";
+
+/// A SQLite database of results, accumulating history across runs.
+pub struct ResultsDb {
+    conn: Connection,
+    run_id: i64,
+}
+
+impl ResultsDb {
+    /// Open (or create) the database at `path` and start a new run.
+    pub fn open(path: &Path, start_url: &str) -> rusqlite::Result<ResultsDb> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+
+        // Write the whole run in one transaction, which is much faster
+        // than committing every result.
+        conn.execute_batch("BEGIN")?;
+        conn.execute(
+            "INSERT INTO runs (start_url, started_at) VALUES (?1, ?2)",
+            params![start_url, Utc::now().to_rfc3339()],
+        )?;
+        let run_id = conn.last_insert_rowid();
+
+        Ok(ResultsDb { conn, run_id })
+    }
+
+    /// Record the result of checking a single URL.
+    pub fn record(&self, state: &CheckResult, severity: Severity) -> rusqlite::Result<()> {
+        let status = state.status.map(|status| i64::from(status.as_u16()));
+        let duration_ms = state.duration.map(|d| d.as_millis() as i64);
+
+        self.conn.execute(
+            "INSERT INTO results (run_id, url, category, reason, severity, status, duration_ms, checked_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                self.run_id,
+                state.url_str(),
+                state.category(),
+                state.reason(),
+                severity.name(),
+                status,
+                duration_ms,
+                Utc::now().to_rfc3339()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Record the referrers and totals for the run, and commit it.
+    pub fn finish(self, report: &Report) -> rusqlite::Result<()> {
+        {
+            let mut insert = self
+                .conn
+                .prepare("INSERT INTO referrers (run_id, url, referrer) VALUES (?1, ?2, ?3)")?;
+            for (url, referrers) in &report.referrers {
+                for referrer in referrers {
+                    insert.execute(params![self.run_id, url, referrer])?;
+                }
+            }
+        }
+
+        self.conn.execute(
+            "UPDATE runs SET finished_at = ?1, succeeded = ?2, failed = ?3 WHERE id = ?4",
+            params![
+                Utc::now().to_rfc3339(),
+                report.success_count as i64,
+                report.failures.len() as i64,
+                self.run_id
+            ],
+        )?;
+        self.conn.execute_batch("COMMIT")
+    }
+}
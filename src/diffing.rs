@@ -0,0 +1,104 @@
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fs;
+
+/// Read the JSON report at `path`.
+fn read_report(path: &str) -> Result<serde_json::Value, String> {
+    let src = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    serde_json::from_str(&src).map_err(|e| format!("{}: {}", path, e))
+}
+
+/// The failures in a JSON report, keyed by URL.
+fn failures_of(report: &serde_json::Value) -> BTreeMap<String, serde_json::Value> {
+    let mut failures = BTreeMap::new();
+    if let Some(items) = report["failures"].as_array() {
+        for failure in items {
+            if let Some(url) = failure["url"].as_str() {
+                failures.insert(url.to_owned(), failure.clone());
+            }
+        }
+    }
+    failures
+}
+
+/// The difference between two reports.
+pub struct Diff {
+    /// Broken in the new report, but not the old one.
+    pub new: Vec<serde_json::Value>,
+    /// Broken in the old report, but not the new one.
+    pub fixed: Vec<serde_json::Value>,
+    /// Broken in both reports.
+    pub still_broken: Vec<serde_json::Value>,
+}
+
+impl Diff {
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "new": self.new,
+            "fixed": self.fixed,
+            "still_broken": self.still_broken,
+        })
+    }
+
+    /// Whether anything broke or was fixed.
+    pub fn has_changes(&self) -> bool {
+        !self.new.is_empty() || !self.fixed.is_empty()
+    }
+
+    pub fn to_text(&self) -> String {
+        sections_text(&[
+            ("Newly broken", &self.new),
+            ("Fixed", &self.fixed),
+            ("Still broken", &self.still_broken),
+        ])
+    }
+
+    /// Like `to_text`, but without the links that are still broken.
+    pub fn changes_text(&self) -> String {
+        sections_text(&[("Newly broken", &self.new), ("Fixed", &self.fixed)])
+    }
+}
+
+fn sections_text(sections: &[(&str, &Vec<serde_json::Value>)]) -> String {
+    let mut s = String::new();
+    for (heading, failures) in sections {
+        s.push_str(&format!("{} ({}):\n", heading, failures.len()));
+        for failure in failures.iter() {
+            s.push_str(&format!(
+                "  {} ({})\n",
+                failure["url"].as_str().unwrap_or(""),
+                failure["reason"].as_str().unwrap_or("")
+            ));
+        }
+    }
+    s
+}
+
+/// Compare the JSON reports at `old_path` and `new_path`.
+pub fn diff(old_path: &str, new_path: &str) -> Result<Diff, String> {
+    Ok(compare(&read_report(old_path)?, &read_report(new_path)?))
+}
+
+/// Compare two JSON reports.
+pub fn compare(old: &serde_json::Value, new: &serde_json::Value) -> Diff {
+    let old = failures_of(old);
+    let new = failures_of(new);
+
+    Diff {
+        new: new
+            .iter()
+            .filter(|(url, _)| !old.contains_key(*url))
+            .map(|(_, failure)| failure.clone())
+            .collect(),
+        fixed: old
+            .iter()
+            .filter(|(url, _)| !new.contains_key(*url))
+            .map(|(_, failure)| failure.clone())
+            .collect(),
+        still_broken: new
+            .iter()
+            .filter(|(url, _)| old.contains_key(*url))
+            .map(|(_, failure)| failure.clone())
+            .collect(),
+    }
+}
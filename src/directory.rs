@@ -0,0 +1,295 @@
+use percent_encoding::percent_decode_str;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+use tracing::trace;
+use url::Url;
+
+use crate::fetching::{CheckResult, FetchError, Fetcher, Outcome, Page};
+use crate::ignoring::GitIgnore;
+use crate::parsing;
+
+/// The URL we pretend a directory is served at, unless configured
+/// otherwise.
+pub const DEFAULT_BASE_URL: &str = "http://localhost/";
+
+/// Checks URLs against a directory of files, e.g. a static site before
+/// it's deployed, as if the directory was served at a base URL. Other
+/// URLs go to `external`, if given.
+pub struct DirectoryFetcher {
+    root: PathBuf,
+    base_url: Url,
+    external: Option<Box<dyn Fetcher>>,
+    check_anchors: bool,
+    directory_links: bool,
+}
+
+impl DirectoryFetcher {
+    pub fn new(root: &Path, base_url: Url, external: Option<Box<dyn Fetcher>>) -> Self {
+        DirectoryFetcher {
+            root: root.to_owned(),
+            base_url,
+            external,
+            check_anchors: false,
+            directory_links: false,
+        }
+    }
+
+    /// Also check that the fragment of each local URL matches an
+    /// anchor in the file: an `id` in HTML, or a heading in Markdown.
+    pub fn check_anchors(mut self, check_anchors: bool) -> Self {
+        self.check_anchors = check_anchors;
+        self
+    }
+
+    /// Accept links to directories without an index.html, the way a
+    /// repository host shows a listing of the files.
+    pub fn directory_links(mut self, directory_links: bool) -> Self {
+        self.directory_links = directory_links;
+        self
+    }
+
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    /// Is `url` under the base URL? We ignore the scheme, since links
+    /// are often written with http:// when the site is served over
+    /// https://, or vice versa.
+    fn is_local(&self, url: &Url) -> bool {
+        url.host_str() == self.base_url.host_str()
+            && url.port_or_known_default() == self.base_url.port_or_known_default()
+            && url.path().starts_with(self.base_url.path())
+    }
+
+    /// The file `url` would be served from, if it exists. Like most
+    /// static servers, a directory serves its index.html, and a path
+    /// without an extension may be a .html file.
+    fn local_path(&self, url: &Url) -> Option<PathBuf> {
+        let rest = &url.path()[self.base_url.path().len()..];
+        let rest = percent_decode_str(rest).decode_utf8_lossy();
+        let mut path = self.root.clone();
+        for part in rest.split('/').filter(|part| !part.is_empty()) {
+            let part = Path::new(part);
+            // Don't let a link escape the directory.
+            if part
+                .components()
+                .any(|c| !matches!(c, Component::Normal(_)))
+            {
+                return None;
+            }
+            path.push(part);
+        }
+
+        if path.is_dir() {
+            if self.directory_links && !path.join("index.html").is_file() {
+                return Some(path);
+            }
+            path.push("index.html");
+        }
+        if path.is_file() {
+            return Some(path);
+        }
+        if path.extension().is_none() {
+            let with_html = path.with_extension("html");
+            if with_html.is_file() {
+                return Some(with_html);
+            }
+        }
+        None
+    }
+
+    /// The file in the directory that `url` is served from, if it's on
+    /// the base URL and exists.
+    pub fn path_of(&self, url: &Url) -> Option<PathBuf> {
+        if !self.is_local(url) {
+            return None;
+        }
+        self.local_path(url)
+    }
+
+    /// The URL that `path`, a file in the directory, is served at.
+    pub fn url_of(&self, path: &Path) -> Option<Url> {
+        let relative = path.strip_prefix(&self.root).ok()?;
+        let mut parts: Vec<String> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        if parts.last().map(|part| part.as_str()) == Some("index.html") {
+            parts.pop();
+            parts.push(String::new());
+        }
+        self.base_url.join(&parts.join("/")).ok()
+    }
+}
+
+/// Does the file at `path` have an anchor called `fragment`? Files we
+/// can't find anchors in are given the benefit of the doubt.
+fn has_anchor(path: &Path, fragment: &str) -> bool {
+    // Browsers scroll to the top for #top, even without an anchor.
+    if fragment == "top" {
+        return true;
+    }
+    let ids = match (content_type(path), fs::read_to_string(path)) {
+        (Some("text/html"), Ok(src)) | (Some("application/xhtml+xml"), Ok(src)) => {
+            parsing::get_ids(&src)
+        }
+        (Some("text/markdown"), Ok(src)) => parsing::heading_ids(&src),
+        _ => return true,
+    };
+    let fragment = percent_decode_str(fragment).decode_utf8_lossy();
+    ids.iter().any(|id| *id == fragment)
+}
+
+/// Guess a file's Content-Type from its extension, for choosing a link
+/// extractor. We only need the types we can find links in.
+fn content_type(path: &Path) -> Option<&'static str> {
+    match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+        "html" | "htm" => Some("text/html"),
+        "xhtml" => Some("application/xhtml+xml"),
+        "md" | "markdown" => Some("text/markdown"),
+        "css" => Some("text/css"),
+        "pdf" => Some("application/pdf"),
+        _ => None,
+    }
+}
+
+impl Fetcher for DirectoryFetcher {
+    fn check(&self, referrer: &str, url: &Url, timeout: Duration) -> CheckResult {
+        if !self.is_local(url) {
+            return match self.external {
+                Some(ref external) => external.check(referrer, url, timeout),
+                None => {
+                    let mut result =
+                        CheckResult::new(referrer, url.as_str(), Outcome::ConnectionFailed);
+                    result.error = Some("not checking links to other sites".to_owned());
+                    result
+                }
+            };
+        }
+
+        match self.local_path(url) {
+            Some(path) => {
+                trace!(url = %url, path = %path.display(), "found file");
+                let fragment = url.fragment().filter(|fragment| !fragment.is_empty());
+                if let Some(fragment) = fragment {
+                    if self.check_anchors && !has_anchor(&path, fragment) {
+                        let mut result =
+                            CheckResult::new(referrer, url.as_str(), Outcome::MissingAnchor);
+                        result.error =
+                            Some(format!("no anchor #{} in {}", fragment, path.display()));
+                        return result;
+                    }
+                }
+                let mut result = CheckResult::new(referrer, url.as_str(), Outcome::Accessible);
+                result.size = fs::metadata(&path).ok().map(|metadata| metadata.len());
+                result
+            }
+            None => {
+                // Report it the way the deployed site would.
+                let mut result = CheckResult::new(referrer, url.as_str(), Outcome::BadStatus);
+                result.status = Some(http::StatusCode::NOT_FOUND);
+                result.error = Some(format!(
+                    "no file for {} in {}",
+                    url.path(),
+                    self.root.display()
+                ));
+                result
+            }
+        }
+    }
+
+    fn fetch(&self, url: &Url, timeout: Duration) -> Result<Page, FetchError> {
+        if !self.is_local(url) {
+            return match self.external {
+                Some(ref external) => external.fetch(url, timeout),
+                None => Err(FetchError::Abandoned),
+            };
+        }
+
+        let path = match self.local_path(url) {
+            Some(path) => path,
+            None => {
+                return Err(FetchError::Io {
+                    path: self.root.join(url.path().trim_start_matches('/')),
+                    source: io::Error::from(io::ErrorKind::NotFound),
+                })
+            }
+        };
+        let content_type = content_type(&path);
+        // Don't read files we can't find links in, e.g. images.
+        let body = match content_type {
+            Some(_) => fs::read(&path).map_err(|source| FetchError::Io {
+                path: path.clone(),
+                source,
+            })?,
+            None => vec![],
+        };
+        Ok(Page {
+            content_type: content_type.map(|content_type| content_type.to_owned()),
+            body: String::from_utf8_lossy(&body).into_owned(),
+        })
+    }
+
+    fn checks_fragments(&self) -> bool {
+        self.check_anchors
+    }
+}
+
+/// Every HTML file under `root`, sorted so runs are repeatable.
+pub fn html_files(root: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    let mut dirs = vec![root.to_owned()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if content_type(&path) == Some("text/html") {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Every Markdown file under `root`, except those git ignores, sorted
+/// so runs are repeatable.
+pub fn markdown_files(root: &Path) -> io::Result<Vec<PathBuf>> {
+    Ok(repo_files(root)?
+        .into_iter()
+        .filter(|path| content_type(path) == Some("text/markdown"))
+        .collect())
+}
+
+/// Every file under `root`, except those git ignores, sorted so runs
+/// are repeatable.
+pub fn repo_files(root: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    let mut ignore = GitIgnore::default();
+    ignore.add_dir(root);
+    let mut dirs = vec![(root.to_owned(), ignore)];
+    while let Some((dir, ignore)) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let is_dir = path.is_dir();
+            if ignore.is_ignored(&path, is_dir) {
+                continue;
+            }
+            if is_dir {
+                if path.file_name().map_or(false, |name| name == ".git") {
+                    continue;
+                }
+                let mut ignore = ignore.clone();
+                ignore.add_dir(&path);
+                dirs.push((path, ignore));
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
@@ -0,0 +1,58 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+
+/// A hash of `body` ignoring differences in whitespace, which templates
+/// often vary without changing the page.
+fn fingerprint(body: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for word in body.split_whitespace() {
+        word.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Finds pages with the same content at different URLs, usually
+/// aliases like `/page` and `/page/`, or `/Page` and `/page`. Pages
+/// are recorded from the worker threads as they're fetched.
+#[derive(Debug, Default)]
+pub struct Duplicates {
+    /// The URLs of the pages with each fingerprint.
+    pages: Mutex<HashMap<u64, Vec<String>>>,
+}
+
+impl Duplicates {
+    /// Remember that `url` served `body`. Empty pages are all alike,
+    /// so aren't recorded.
+    pub fn record(&self, url: &str, body: &str) {
+        if body.trim().is_empty() {
+            return;
+        }
+        let fingerprint = fingerprint(body);
+        self.pages
+            .lock()
+            .unwrap()
+            .entry(fingerprint)
+            .or_default()
+            .push(url.to_owned());
+    }
+
+    /// The groups of URLs serving identical content, each sorted, and
+    /// sorted by their first URL.
+    pub fn clusters(&self) -> Vec<Vec<String>> {
+        let mut clusters: Vec<Vec<String>> = self
+            .pages
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|urls| urls.len() > 1)
+            .cloned()
+            .collect();
+        for urls in &mut clusters {
+            urls.sort();
+        }
+        clusters.sort();
+        clusters
+    }
+}
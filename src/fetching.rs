@@ -1,104 +1,778 @@
 use colored::*;
-use crossbeam_channel::{select, unbounded};
-use reqwest::StatusCode;
+#[cfg(feature = "native")]
+use crossbeam_channel::{select, unbounded, RecvTimeoutError};
+use http::StatusCode;
+#[cfg(feature = "native")]
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE, LAST_MODIFIED, LOCATION, USER_AGENT};
+#[cfg(feature = "native")]
+use reqwest::{Client, RedirectPolicy, Response};
+use std::collections::BTreeMap;
 use std::fmt;
+use std::io;
+#[cfg(feature = "native")]
+use std::io::Read;
+use std::path::PathBuf;
+#[cfg(feature = "native")]
+use std::sync::Arc;
+#[cfg(feature = "native")]
 use std::thread;
 use std::time::Duration;
+use thiserror::Error;
+use tracing::debug;
+#[cfg(feature = "native")]
+use tracing::{trace, warn};
 use url::{ParseError, Url};
 
+#[cfg(feature = "native")]
+use crate::archiving::{FailureDump, WarcWriter};
+#[cfg(feature = "native")]
+use crate::content_types;
+#[cfg(feature = "native")]
+use crate::images;
+use crate::iri;
+#[cfg(feature = "native")]
+use crate::middleware::Pipeline;
+#[cfg(feature = "native")]
 use crate::parsing;
+use crate::parsing::Extractors;
 
+/// What happened when we checked a URL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde1",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Outcome {
+    Accessible,
+    BadStatus,
+    ConnectionFailed,
+    TimedOut,
+    Malformed,
+    /// The page exists, but has no anchor matching the URL's fragment.
+    MissingAnchor,
+    /// The page loads, but breaks one of the assertions for it.
+    AssertionFailed,
+    /// The URL loads, but the response breaks one of the assertions
+    /// about its headers, e.g. it has no `Cache-Control`.
+    HeaderAssertionFailed,
+    /// The URL names an image, and loads, but isn't an image, e.g. it's
+    /// an HTML error page.
+    NotAnImage,
+    /// The URL loads, but is served as the wrong type for its
+    /// extension, e.g. a `.css` file served as HTML.
+    ContentTypeMismatch,
+    /// The page was served successfully, but looks like an error page.
+    Soft404,
+    /// The page is on another site whose domain has lapsed, and now
+    /// shows a parking or for-sale page.
+    Parked,
+    /// The page is on the site, but redirects to a sign-in page.
+    LoginRequired,
+    /// The URL is on a domain in a blocklist, so we didn't request it.
+    Blocklisted,
+    /// The URL redirects more times than we follow.
+    TooManyRedirects,
+    /// The URL uses a scheme links aren't allowed to, so we didn't
+    /// request it.
+    ForbiddenScheme,
+}
+
+impl Outcome {
+    /// A short machine-readable name for the outcome.
+    pub fn name(self) -> &'static str {
+        match self {
+            Outcome::Accessible => "accessible",
+            Outcome::BadStatus => "bad_status",
+            Outcome::ConnectionFailed => "connection_failed",
+            Outcome::TimedOut => "timed_out",
+            Outcome::Malformed => "malformed",
+            Outcome::MissingAnchor => "missing_anchor",
+            Outcome::AssertionFailed => "assertion_failed",
+            Outcome::HeaderAssertionFailed => "header_assertion_failed",
+            Outcome::NotAnImage => "not_an_image",
+            Outcome::ContentTypeMismatch => "content_type_mismatch",
+            Outcome::Soft404 => "soft_404",
+            Outcome::Parked => "parked",
+            Outcome::LoginRequired => "login_required",
+            Outcome::Blocklisted => "blocklisted",
+            Outcome::TooManyRedirects => "too_many_redirects",
+            Outcome::ForbiddenScheme => "forbidden_scheme",
+        }
+    }
+}
+
+/// Everything we found out by checking a URL.
 #[derive(Debug, Clone)]
-pub enum UrlState {
-    Accessible(String, Url),
-    BadStatus(String, Url, StatusCode),
-    ConnectionFailed(String, Url),
-    TimedOut(String, Url),
-    Malformed(String, String),
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct CheckResult {
+    /// The URL we checked, as written if it was malformed.
+    pub url: String,
+    /// The pages linking to the URL that we knew of when we checked
+    /// it. The first is the page we found it on.
+    pub referrers: Vec<String>,
+    pub outcome: Outcome,
+    /// The HTTP status of the final response, if we got one.
+    #[cfg_attr(feature = "serde1", serde(with = "status_code"))]
+    pub status: Option<StatusCode>,
+    /// Every URL we were redirected to, in order.
+    pub redirects: Vec<String>,
+    /// Whether every redirect was permanent (301 or 308), so links to
+    /// the URL can be updated to the last of `redirects`.
+    #[cfg_attr(feature = "serde1", serde(default))]
+    pub permanent_redirect: bool,
+    /// The Last-Modified header, if the server sent one.
+    pub last_modified: Option<String>,
+    /// The HTTP version of the final response, e.g. `HTTP/2`, if we
+    /// got one.
+    #[cfg_attr(feature = "serde1", serde(default))]
+    pub http_version: Option<String>,
+    /// The response headers we were asked to keep, by lowercase name.
+    #[cfg_attr(feature = "serde1", serde(default))]
+    pub headers: BTreeMap<String, String>,
+    /// How many clicks from a seed the crawl found the URL. Pages are
+    /// checked in parallel, so a shorter path may turn up later, see
+    /// `LinkGraph::click_depths`.
+    #[cfg_attr(feature = "serde1", serde(default))]
+    pub depth: Option<usize>,
+    /// How long the check took, including any retries.
+    pub duration: Option<Duration>,
+    /// The Content-Length of the final response, if the server sent
+    /// one.
+    pub size: Option<u64>,
+    /// What went wrong, in more detail than `reason`. Accessible pages
+    /// that we couldn't fetch to find their links also have an error.
+    pub error: Option<String>,
+}
+
+impl CheckResult {
+    /// All the values returned by `category`.
+    pub const CATEGORIES: &'static [&'static str] = &[
+        "accessible",
+        "bad_status",
+        "connection_failed",
+        "timed_out",
+        "malformed",
+        "missing_anchor",
+        "assertion_failed",
+        "header_assertion_failed",
+        "not_an_image",
+        "content_type_mismatch",
+        "soft_404",
+        "parked",
+        "login_required",
+        "blocklisted",
+        "too_many_redirects",
+        "forbidden_scheme",
+    ];
+
+    /// A result for `url`, linked from `referrer`, with nothing else
+    /// known yet.
+    pub fn new(referrer: &str, url: &str, outcome: Outcome) -> CheckResult {
+        CheckResult {
+            url: url.to_owned(),
+            referrers: vec![referrer.to_owned()],
+            outcome,
+            status: None,
+            redirects: vec![],
+            permanent_redirect: false,
+            last_modified: None,
+            http_version: None,
+            headers: BTreeMap::new(),
+            depth: None,
+            duration: None,
+            size: None,
+            error: None,
+        }
+    }
+
+    pub fn is_accessible(&self) -> bool {
+        self.outcome == Outcome::Accessible
+    }
+
+    /// A short machine-readable name for the kind of result.
+    pub fn category(&self) -> &'static str {
+        self.outcome.name()
+    }
+
+    /// The page that linked to this URL.
+    pub fn referrer(&self) -> &str {
+        self.referrers
+            .first()
+            .map_or("", |referrer| referrer.as_str())
+    }
+
+    /// The URL that we checked, as written if it was malformed.
+    pub fn url_str(&self) -> &str {
+        &self.url
+    }
+
+    /// The URL we checked, with an internationalized host and path in
+    /// Unicode, for showing to people.
+    pub fn display_url(&self) -> String {
+        iri::display(&self.url)
+    }
+
+    /// The URL we checked, unless it was malformed.
+    pub fn parsed_url(&self) -> Option<Url> {
+        match self.outcome {
+            Outcome::Malformed => None,
+            _ => Url::parse(&self.url).ok(),
+        }
+    }
+
+    /// A short description of what went wrong (or "ok").
+    pub fn reason(&self) -> String {
+        match (self.outcome, self.status) {
+            (Outcome::Accessible, _) => "ok".to_owned(),
+            (Outcome::BadStatus, Some(status)) => status.to_string(),
+            (Outcome::BadStatus, None) => "bad status".to_owned(),
+            (Outcome::ConnectionFailed, _) => "connection failed".to_owned(),
+            (Outcome::TimedOut, _) => "timed out".to_owned(),
+            (Outcome::Malformed, _) => "malformed".to_owned(),
+            (Outcome::MissingAnchor, _) => "missing anchor".to_owned(),
+            (Outcome::AssertionFailed, _) => "assertion failed".to_owned(),
+            (Outcome::HeaderAssertionFailed, _) => "header assertion failed".to_owned(),
+            (Outcome::NotAnImage, _) => "not an image".to_owned(),
+            (Outcome::ContentTypeMismatch, _) => "wrong content type".to_owned(),
+            (Outcome::Soft404, _) => "soft 404".to_owned(),
+            (Outcome::Parked, _) => "parked domain".to_owned(),
+            (Outcome::LoginRequired, _) => "login required".to_owned(),
+            (Outcome::Blocklisted, _) => "blocklisted".to_owned(),
+            (Outcome::TooManyRedirects, _) => "too many redirects".to_owned(),
+            (Outcome::ForbiddenScheme, _) => "forbidden scheme".to_owned(),
+        }
+    }
+}
+
+/// (De)serialize HTTP statuses as their numeric code.
+#[cfg(feature = "serde1")]
+mod status_code {
+    use http::StatusCode;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        status: &Option<StatusCode>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        status.map(|status| status.as_u16()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<StatusCode>, D::Error> {
+        match Option::<u16>::deserialize(deserializer)? {
+            Some(code) => StatusCode::from_u16(code)
+                .map(Some)
+                .map_err(D::Error::custom),
+            None => Ok(None),
+        }
+    }
 }
 
-impl fmt::Display for UrlState {
+impl fmt::Display for CheckResult {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let tick = "✔".green();
-        let cross = "✘".red();
-        match *self {
-            UrlState::Accessible(ref old_url, ref url) => {
-                format!("{} {} {}", tick, old_url, url).fmt(f)
-            }
-            UrlState::BadStatus(ref old_url, ref url, ref status) => {
-                format!("{} {} {} ({})", cross, old_url, url, status).fmt(f)
-            }
-            UrlState::ConnectionFailed(ref old_url, ref url) => {
-                format!("{} {} {} (connection failed)", cross, old_url, url).fmt(f)
-            }
-            UrlState::TimedOut(ref old_url, ref url) => {
-                format!("{} {} {} (timed out)", cross, old_url, url).fmt(f)
-            }
-            UrlState::Malformed(ref old_url, ref url) => {
-                format!("{} {} {} (malformed)", cross, old_url, url).fmt(f)
-            }
+        if self.is_accessible() {
+            format!("{} {} {}", "✔".green(), self.referrer(), self.display_url()).fmt(f)
+        } else {
+            format!(
+                "{} {} {} ({})",
+                "✘".red(),
+                self.referrer(),
+                self.display_url(),
+                self.reason()
+            )
+            .fmt(f)
         }
     }
 }
 
-fn build_url(domain: &str, path: &str) -> Result<Url, ParseError> {
+pub fn build_url(domain: &str, path: &str) -> Result<Url, ParseError> {
     let base_url_string = format!("http://{}", domain);
     let base_url = Url::parse(&base_url_string)?;
     base_url.join(path)
 }
 
-const TIMEOUT_SECS: u64 = 10;
+/// Why we couldn't check or fetch a URL.
+#[derive(Debug, Error)]
+pub enum FetchError {
+    #[error("invalid User-Agent {0}")]
+    InvalidUserAgent(String),
+    #[cfg(feature = "native")]
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    #[error("invalid redirect to {location}: {source}")]
+    InvalidRedirect {
+        location: String,
+        source: ParseError,
+    },
+    #[error("redirect loop at {0}")]
+    RedirectLoop(Url),
+    #[error("more than {0} redirects")]
+    TooManyRedirects(usize),
+    #[error("the request was abandoned")]
+    Abandoned,
+    #[error("no response after {}s", .0.as_secs())]
+    TimedOut(Duration),
+    #[error("could not read {}: {source}", .path.display())]
+    Io { path: PathBuf, source: io::Error },
+}
 
-pub fn url_status(domain: &str, old_path: &str, path: &str) -> UrlState {
-    match build_url(domain, path) {
-        Ok(url) => {
-            let (s, r) = unbounded();
-            let url2 = url.clone();
-            let old_path_static = old_path.to_owned();
-
-            // Try to do the request.
-            thread::spawn(move || {
-                let response = reqwest::get(url.as_str());
-
-                let _ = s.send(match response {
-                    Ok(response) => {
-                        if response.status().is_success() {
-                            UrlState::Accessible(old_path_static, url)
-                        } else {
-                            // TODO: allow redirects unless they're circular
-                            UrlState::BadStatus(old_path_static, url, response.status())
+/// How long we wait for a URL to respond, unless configured otherwise.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The User-Agent we send, unless configured otherwise.
+pub const DEFAULT_USER_AGENT: &str = concat!("linkdoc/", env!("CARGO_PKG_VERSION"));
+
+/// An HTTP client sending `user_agent`, or `DEFAULT_USER_AGENT`.
+#[cfg(feature = "native")]
+pub fn client(user_agent: Option<&str>) -> Result<Client, FetchError> {
+    let user_agent = user_agent.unwrap_or(DEFAULT_USER_AGENT);
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        USER_AGENT,
+        HeaderValue::from_str(user_agent)
+            .map_err(|_| FetchError::InvalidUserAgent(user_agent.to_owned()))?,
+    );
+    // We follow redirects ourselves, to see where they go.
+    let client = Client::builder()
+        .default_headers(headers)
+        .redirect(RedirectPolicy::none())
+        .build()?;
+    Ok(client)
+}
+
+/// A fetched page.
+#[derive(Debug, Clone, Default)]
+pub struct Page {
+    /// The Content-Type, if we know it. Pages without one are treated
+    /// as HTML.
+    pub content_type: Option<String>,
+    pub body: String,
+}
+
+/// How we check and fetch URLs. The crawler uses an `HttpFetcher`
+/// unless it's given another, e.g. one returning canned responses, or
+/// reading pages from disk.
+pub trait Fetcher: Send + Sync {
+    /// Check whether `url`, linked from `referrer`, is accessible,
+    /// giving up after `timeout`.
+    fn check(&self, referrer: &str, url: &Url, timeout: Duration) -> CheckResult;
+
+    /// Fetch the body of `url`, so we can find its links, giving up
+    /// after `timeout`.
+    fn fetch(&self, url: &Url, timeout: Duration) -> Result<Page, FetchError>;
+
+    /// Does `check` look at the URL's fragment, e.g. to find the
+    /// anchor? If not, the crawl checks each page once, however many
+    /// fragments it's linked with.
+    fn checks_fragments(&self) -> bool {
+        false
+    }
+}
+
+/// Fetches URLs over HTTP, running requests and responses through the
+/// crawl's middleware.
+#[cfg(feature = "native")]
+pub struct HttpFetcher {
+    client: Client,
+    pipeline: Pipeline,
+    archive: Option<Arc<WarcWriter>>,
+    dump: Option<Arc<FailureDump>>,
+    capture_headers: Vec<String>,
+    max_redirects: usize,
+    method: Method,
+}
+
+#[cfg(feature = "native")]
+impl HttpFetcher {
+    /// Send requests with `client`, writing every page we fetch to
+    /// `archive` if given.
+    pub fn new(client: Client, pipeline: Pipeline, archive: Option<Arc<WarcWriter>>) -> Self {
+        HttpFetcher {
+            client,
+            pipeline,
+            archive,
+            dump: None,
+            capture_headers: vec![],
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            method: Method::Get,
+        }
+    }
+
+    /// Check URLs with `method`. Pages are always fetched with GET.
+    pub fn method(mut self, method: Method) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// Give up on URLs that redirect more than `max_redirects` times,
+    /// rather than `DEFAULT_MAX_REDIRECTS`.
+    pub fn max_redirects(mut self, max_redirects: usize) -> Self {
+        self.max_redirects = max_redirects;
+        self
+    }
+
+    /// Keep the response headers named `names` in each result.
+    pub fn capture_headers(mut self, names: Vec<String>) -> Self {
+        self.capture_headers = names.iter().map(|name| name.to_ascii_lowercase()).collect();
+        self
+    }
+
+    /// Save the response of every URL that isn't accessible to `dump`.
+    pub fn dump_failures(mut self, dump: Arc<FailureDump>) -> Self {
+        self.dump = Some(dump);
+        self
+    }
+}
+
+/// How `version` is usually written.
+#[cfg(feature = "native")]
+fn version_name(version: http::Version) -> String {
+    use http::Version;
+    match version {
+        Version::HTTP_09 => "HTTP/0.9".to_owned(),
+        Version::HTTP_10 => "HTTP/1.0".to_owned(),
+        Version::HTTP_11 => "HTTP/1.1".to_owned(),
+        Version::HTTP_2 => "HTTP/2".to_owned(),
+        version => format!("{:?}", version),
+    }
+}
+
+/// The HTTP method we check URLs with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Get,
+    /// Only ask for the headers, which is faster but can't see what the
+    /// body is, and some servers don't support.
+    Head,
+}
+
+impl Method {
+    pub const NAMES: &'static [&'static str] = &["get", "head"];
+
+    pub fn from_name(name: &str) -> Option<Method> {
+        match name.to_ascii_lowercase().as_str() {
+            "get" => Some(Method::Get),
+            "head" => Some(Method::Head),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Method {
+    fn default() -> Self {
+        Method::Get
+    }
+}
+
+/// How many redirects we follow before giving up, unless configured
+/// otherwise.
+pub const DEFAULT_MAX_REDIRECTS: usize = 10;
+
+/// Send a `method` request for `url`, following up to `max_redirects`
+/// redirects ourselves so we know where they went. Returns the final
+/// response, every URL we were redirected to, and whether every
+/// redirect was permanent.
+#[cfg(feature = "native")]
+fn send(
+    client: &Client,
+    pipeline: &Pipeline,
+    url: &Url,
+    method: Method,
+    max_redirects: usize,
+) -> Result<(Response, Vec<Url>, bool), FetchError> {
+    let mut current = url.clone();
+    let mut redirects: Vec<Url> = vec![];
+    let mut permanent = true;
+    loop {
+        let mut response = match method {
+            Method::Get => pipeline.request(client.get(current.as_str())).send()?,
+            Method::Head => pipeline.request(client.head(current.as_str())).send()?,
+        };
+        // Fall back to GET for servers that don't support HEAD, without
+        // reading the body.
+        if method == Method::Head
+            && (response.status() == StatusCode::METHOD_NOT_ALLOWED
+                || response.status() == StatusCode::NOT_IMPLEMENTED)
+        {
+            trace!(url = %current, "HEAD not supported, trying GET");
+            response = pipeline.request(client.get(current.as_str())).send()?;
+        }
+        if !response.status().is_redirection() {
+            let permanent = permanent && !redirects.is_empty();
+            return Ok((response, redirects, permanent));
+        }
+        // Some servers send non-ASCII paths as raw UTF-8, which parsing
+        // percent-encodes for us.
+        let location = match response.headers().get(LOCATION) {
+            Some(location) => String::from_utf8_lossy(location.as_bytes()).into_owned(),
+            // A redirect to nowhere is just a bad status.
+            None => return Ok((response, redirects, false)),
+        };
+        let next = current
+            .join(&location)
+            .map_err(|source| FetchError::InvalidRedirect {
+                location: location.clone(),
+                source,
+            })?;
+        if next == *url || redirects.contains(&next) {
+            return Err(FetchError::RedirectLoop(next));
+        }
+        if redirects.len() >= max_redirects {
+            return Err(FetchError::TooManyRedirects(max_redirects));
+        }
+        permanent = permanent
+            && (response.status() == StatusCode::MOVED_PERMANENTLY
+                || response.status() == StatusCode::PERMANENT_REDIRECT);
+        trace!(from = %current, to = %next, "following redirect");
+        redirects.push(next.clone());
+        current = next;
+    }
+}
+
+#[cfg(feature = "native")]
+impl Fetcher for HttpFetcher {
+    fn check(&self, referrer: &str, url: &Url, timeout: Duration) -> CheckResult {
+        let (s, r) = unbounded();
+        let url = url.clone();
+        let mut result = CheckResult::new(referrer, url.as_str(), Outcome::ConnectionFailed);
+        let mut failed = result.clone();
+        let client = self.client.clone();
+        let pipeline = self.pipeline.clone();
+        let dump = self.dump.clone();
+        let capture_headers = self.capture_headers.clone();
+        let max_redirects = self.max_redirects;
+        let method = self.method;
+
+        // Try to do the request.
+        thread::spawn(move || {
+            let sent = send(&client, &pipeline, &url, method, max_redirects);
+            let _ = s.send(match sent {
+                Ok((mut response, redirects, permanent)) => {
+                    trace!(url = %url, status = %response.status(), "got response");
+                    result.outcome = if response.status().is_success() {
+                        Outcome::Accessible
+                    } else {
+                        Outcome::BadStatus
+                    };
+                    result.status = Some(response.status());
+                    result.redirects = redirects.iter().map(|url| url.to_string()).collect();
+                    result.permanent_redirect = permanent;
+                    result.http_version = Some(version_name(response.version()));
+                    for name in &capture_headers {
+                        let values: Vec<_> = response
+                            .headers()
+                            .get_all(name.as_str())
+                            .iter()
+                            .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned())
+                            .collect();
+                        if !values.is_empty() {
+                            result.headers.insert(name.clone(), values.join(", "));
+                        }
+                    }
+                    result.last_modified = response
+                        .headers()
+                        .get(LAST_MODIFIED)
+                        .and_then(|value| value.to_str().ok())
+                        .map(|value| value.to_owned());
+                    result.size = response.content_length();
+                    let content_type = response
+                        .headers()
+                        .get(CONTENT_TYPE)
+                        .and_then(|value| value.to_str().ok())
+                        .map(|value| value.to_owned());
+                    let mut head = vec![];
+                    // An image URL can load fine but serve an error page.
+                    // A HEAD response has no body to look at.
+                    if result.is_accessible() && images::is_image_url(&url) && method == Method::Get
+                    {
+                        let _ = (&mut response)
+                            .take(images::SNIFF_BYTES as u64)
+                            .read_to_end(&mut head);
+                        if let Some(reason) = images::not_an_image(content_type.as_deref(), &head) {
+                            result.outcome = Outcome::NotAnImage;
+                            result.error = Some(reason);
+                        }
+                    } else if let (true, Some(content_type)) =
+                        (result.is_accessible(), content_type.as_deref())
+                    {
+                        // Often a rewrite rule serving a page for any
+                        // path, so a missing file still loads.
+                        if let Some(reason) = content_types::mismatch(&url, content_type) {
+                            result.outcome = Outcome::ContentTypeMismatch;
+                            result.error = Some(reason);
                         }
                     }
-                    Err(_) => UrlState::ConnectionFailed(old_path_static, url),
-                });
+                    let result = pipeline.response(&response, result);
+                    if let (false, Some(dump)) = (result.is_accessible(), dump.as_ref()) {
+                        // We may have read the start already.
+                        let mut body = head;
+                        let _ = response.read_to_end(&mut body);
+                        if let Err(e) = dump.write_response(&url, &response, &body) {
+                            warn!(url = %url, error = %e, "could not save response");
+                        }
+                    }
+                    result
+                }
+                Err(e) => {
+                    debug!(url = %url, error = %e, "connection failed");
+                    if let FetchError::TooManyRedirects(_) = e {
+                        result.outcome = Outcome::TooManyRedirects;
+                    }
+                    result.error = Some(e.to_string());
+                    result
+                }
             });
+        });
 
-            // Return the request result, or timeout.
-            select! {
-                recv(r) -> msg => msg.unwrap(),
-                default(Duration::from_secs(TIMEOUT_SECS)) => UrlState::TimedOut(old_path.to_owned(), url2)
+        // Return the request result, or timeout.
+        select! {
+            recv(r) -> msg => match msg {
+                Ok(result) => result,
+                // The request thread died without answering.
+                Err(_) => {
+                    failed.error = Some(FetchError::Abandoned.to_string());
+                    failed
+                }
+            },
+            default(timeout) => {
+                debug!(url = %failed.url, timeout_secs = timeout.as_secs(), "timed out");
+                failed.outcome = Outcome::TimedOut;
+                failed.error = Some(format!("no response after {}s", timeout.as_secs()));
+                failed
             }
         }
-        Err(_) => UrlState::Malformed(old_path.to_owned(), path.to_owned()),
+    }
+
+    fn fetch(&self, url: &Url, timeout: Duration) -> Result<Page, FetchError> {
+        // Like checks, the request carries on in the background if it
+        // takes too long, and we stop waiting for it.
+        let (s, r) = unbounded();
+        let url = url.clone();
+        let client = self.client.clone();
+        let pipeline = self.pipeline.clone();
+        let archive = self.archive.clone();
+        let max_redirects = self.max_redirects;
+        thread::spawn(move || {
+            let _ = s.send(fetch_page(
+                &client,
+                &pipeline,
+                &url,
+                max_redirects,
+                archive.as_deref(),
+            ));
+        });
+
+        match r.recv_timeout(timeout) {
+            Ok(page) => page,
+            Err(RecvTimeoutError::Timeout) => Err(FetchError::TimedOut(timeout)),
+            // The request thread died without answering.
+            Err(RecvTimeoutError::Disconnected) => Err(FetchError::Abandoned),
+        }
     }
 }
 
-pub fn fetch_url(url: &Url) -> String {
+/// Fetch the page at `url` with `client`, writing it to `archive` if
+/// given.
+#[cfg(feature = "native")]
+fn fetch_page(
+    client: &Client,
+    pipeline: &Pipeline,
+    url: &Url,
+    max_redirects: usize,
+    archive: Option<&WarcWriter>,
+) -> Result<Page, FetchError> {
     // Creating an outgoing request.
-    let mut res = reqwest::get(url.as_str()).expect("could not fetch URL");
+    let (mut res, _, _) = send(client, pipeline, url, Method::Get, max_redirects)?;
+    let content_type = res
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_owned());
 
     // Read the body.
-    match res.text() {
-        Ok(body) => body,
-        // TODO: handle malformed data more gracefully.
-        Err(_) => String::new(),
+    let mut body = vec![];
+    res.copy_to(&mut body)?;
+    trace!(url = %url, bytes = body.len(), "fetched body");
+
+    if let Some(archive) = archive {
+        if let Err(e) = archive.write_response(url, &res, &body) {
+            warn!(url = %url, error = %e, "could not archive response");
+        }
+    }
+
+    Ok(Page {
+        content_type,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    })
+}
+
+pub fn url_status(
+    fetcher: &dyn Fetcher,
+    domain: &str,
+    old_path: &str,
+    path: &str,
+    timeout: Duration,
+) -> CheckResult {
+    match build_url(domain, path) {
+        Ok(url) => fetcher.check(old_path, &url, timeout),
+        Err(e) => {
+            debug!(path, error = %e, "malformed URL");
+            let mut result = CheckResult::new(old_path, path, Outcome::Malformed);
+            result.error = Some(e.to_string());
+            result
+        }
     }
 }
 
 /// Fetch the requested URL, and return a list of all the URLs on the
 /// page. We deliberately return strings because we're also interested
 /// in malformed URLs.
-pub fn fetch_all_urls(url: &Url) -> Vec<String> {
-    let html_src = fetch_url(url);
-    parsing::get_urls(&html_src)
+pub fn fetch_all_urls(
+    fetcher: &dyn Fetcher,
+    extractors: &Extractors,
+    url: &Url,
+    timeout: Duration,
+) -> Result<Vec<String>, FetchError> {
+    let page = fetcher.fetch(url, timeout)?;
+    Ok(extractors.extract(page.content_type.as_deref(), &page.body))
+}
+
+/// Fetch the sitemap.xml for the site at `url` with `client`, returning
+/// the page URLs it lists. Sites without a sitemap, or that don't send
+/// it within `timeout`, just give an empty list.
+#[cfg(feature = "native")]
+pub fn fetch_sitemap_urls(client: &Client, url: &Url, timeout: Duration) -> Vec<String> {
+    let sitemap_url = match url.join("/sitemap.xml") {
+        Ok(sitemap_url) => sitemap_url,
+        Err(_) => return vec![],
+    };
+
+    // Like checks, the request carries on in the background if it
+    // takes too long, and we stop waiting for it.
+    let (s, r) = unbounded();
+    let client = client.clone();
+    thread::spawn(move || {
+        let pipeline = Pipeline::default();
+        let body = match send(
+            &client,
+            &pipeline,
+            &sitemap_url,
+            Method::Get,
+            DEFAULT_MAX_REDIRECTS,
+        ) {
+            Ok((mut res, _, _)) if res.status().is_success() => res.text().ok(),
+            _ => None,
+        };
+        let _ = s.send(body);
+    });
+    match r.recv_timeout(timeout) {
+        Ok(Some(body)) => parsing::get_sitemap_urls(&body),
+        _ => {
+            debug!(url = %url, "no sitemap");
+            vec![]
+        }
+    }
 }
@@ -0,0 +1,180 @@
+use std::collections::BTreeMap;
+use url::Url;
+
+/// A link we rewrote.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    /// The line the link is on, counting from 1.
+    pub line: usize,
+    pub old: String,
+    pub new: String,
+}
+
+/// The https:// version of an http:// URL.
+pub fn https_url(url: &Url) -> Option<Url> {
+    if url.scheme() != "http" {
+        return None;
+    }
+    let mut https = url.clone();
+    https.set_scheme("https").ok()?;
+    // An explicit port 80 would now be wrong.
+    if url.port() == Some(80) {
+        https.set_port(None).ok()?;
+    }
+    Some(https)
+}
+
+/// Where to link instead of `url`, if it only redirects to `target`
+/// because of a missing or extra trailing slash, or the case of its
+/// path, on the same site. Any fragment is kept.
+pub fn slash_or_case_fix(url: &Url, target: &str) -> Option<String> {
+    let target = Url::parse(target).ok()?;
+    let same_site = url.scheme() == target.scheme()
+        && url.host_str() == target.host_str()
+        && url.port_or_known_default() == target.port_or_known_default()
+        && url.query() == target.query();
+    let (path, target_path) = (url.path(), target.path());
+    if !same_site || path == target_path {
+        return None;
+    }
+    let trim = |path: &str| path.trim_end_matches('/').to_owned();
+    if !trim(path).eq_ignore_ascii_case(&trim(target_path)) {
+        return None;
+    }
+    let mut fixed = target;
+    fixed.set_fragment(url.fragment());
+    Some(fixed.to_string())
+}
+
+/// Where a URL written at the start of `s` might end: the whole run of
+/// URL characters, then with trailing punctuation dropped one
+/// character at a time, longest first.
+fn candidates(s: &str) -> Vec<&str> {
+    let end = s
+        .find(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"' | '\'' | '`'))
+        .unwrap_or(s.len());
+    let mut candidates = vec![&s[..end]];
+    let mut url = &s[..end];
+    while let Some(c) = url.chars().last() {
+        if !matches!(c, '.' | ',' | ';' | ':' | '!' | '?' | '*' | ')' | ']' | '}') {
+            break;
+        }
+        url = &url[..url.len() - c.len_utf8()];
+        candidates.push(url);
+    }
+    candidates
+}
+
+/// Replace the links in `source` that are keys of `replacements` with
+/// their values, returning the new source and what we changed. Only
+/// whole URLs are replaced, not URLs that merely start with a key.
+pub fn rewrite(source: &str, replacements: &BTreeMap<String, String>) -> (String, Vec<Edit>) {
+    let mut output = String::with_capacity(source.len());
+    let mut edits = vec![];
+    for (i, line) in source.split_inclusive('\n').enumerate() {
+        let mut rest = line;
+        while let Some(start) = ["http://", "https://"]
+            .iter()
+            .filter_map(|scheme| rest.find(scheme))
+            .min()
+        {
+            output.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let candidates = candidates(rest);
+            let found = candidates
+                .iter()
+                .find_map(|candidate| replacements.get_key_value(*candidate));
+            match found {
+                Some((old, new)) => {
+                    output.push_str(new);
+                    edits.push(Edit {
+                        line: i + 1,
+                        old: old.clone(),
+                        new: new.clone(),
+                    });
+                    rest = &rest[old.len()..];
+                }
+                None => {
+                    output.push_str(candidates[0]);
+                    rest = &rest[candidates[0].len()..];
+                }
+            }
+        }
+        output.push_str(rest);
+    }
+    (output, edits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fix(url: &str, target: &str) -> Option<String> {
+        slash_or_case_fix(&Url::parse(url).unwrap(), target)
+    }
+
+    #[test]
+    fn fixes_trailing_slashes() {
+        assert_eq!(
+            fix("https://example.com/docs", "https://example.com/docs/"),
+            Some("https://example.com/docs/".to_owned())
+        );
+        assert_eq!(
+            fix("https://example.com/docs/", "https://example.com/docs"),
+            Some("https://example.com/docs".to_owned())
+        );
+    }
+
+    #[test]
+    fn fixes_case() {
+        assert_eq!(
+            fix(
+                "https://example.com/Docs/Intro",
+                "https://example.com/docs/intro"
+            ),
+            Some("https://example.com/docs/intro".to_owned())
+        );
+        assert_eq!(
+            fix("https://example.com/Docs", "https://example.com/docs/"),
+            Some("https://example.com/docs/".to_owned())
+        );
+    }
+
+    #[test]
+    fn keeps_the_fragment() {
+        assert_eq!(
+            fix(
+                "https://example.com/docs#intro",
+                "https://example.com/docs/"
+            ),
+            Some("https://example.com/docs/#intro".to_owned())
+        );
+    }
+
+    #[test]
+    fn ignores_other_redirects() {
+        // A different page.
+        assert_eq!(
+            fix("https://example.com/old", "https://example.com/new/"),
+            None
+        );
+        // Another host, or scheme.
+        assert_eq!(
+            fix("https://example.com/docs", "https://www.example.com/docs/"),
+            None
+        );
+        assert_eq!(
+            fix("http://example.com/docs", "https://example.com/docs/"),
+            None
+        );
+        // A different query.
+        assert_eq!(
+            fix("https://example.com/a?x=1", "https://example.com/a/"),
+            None
+        );
+        // Nothing to fix.
+        assert_eq!(fix("https://example.com/a", "https://example.com/a"), None);
+        // Not a URL.
+        assert_eq!(fix("https://example.com/a", "/a/"), None);
+    }
+}
@@ -0,0 +1,308 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::Path;
+
+use crate::fetching::CheckResult;
+
+/// A URL in the link graph.
+#[derive(Debug, Clone)]
+pub struct Node {
+    /// The URL, in the same form as `CheckResult::url`.
+    pub url: String,
+    /// The result of checking the URL, if it's been checked.
+    pub result: Option<CheckResult>,
+}
+
+/// A link from one page to another. A page linking to the same URL
+/// twice gives two edges.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edge {
+    /// The index of the linking page in `LinkGraph::nodes`.
+    pub source: usize,
+    /// The index of the link target in `LinkGraph::nodes`.
+    pub target: usize,
+    /// The link as written on the page, before resolving it.
+    pub href: String,
+}
+
+/// Every URL we found and every link between them, in the order we
+/// found them.
+#[derive(Debug, Clone, Default)]
+pub struct LinkGraph {
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+    /// The index of each URL in `nodes`.
+    index: HashMap<String, usize>,
+}
+
+impl LinkGraph {
+    /// A graph of the links in `referrers` (target -> linking pages),
+    /// e.g. from a saved `Report`. We don't know how the links were
+    /// written, so each `href` is the target URL.
+    pub fn from_referrers(referrers: &HashMap<String, Vec<String>>) -> LinkGraph {
+        let mut graph = LinkGraph::default();
+        // Sort so node indexes are stable between runs.
+        let mut targets: Vec<_> = referrers.keys().collect();
+        targets.sort();
+        for target in targets {
+            for page in &referrers[target] {
+                graph.add_link(page, target, target);
+            }
+        }
+        graph
+    }
+
+    /// The index of the node for `url`, adding one if needed.
+    pub fn add_node(&mut self, url: &str) -> usize {
+        if let Some(&i) = self.index.get(url) {
+            return i;
+        }
+        let i = self.nodes.len();
+        self.nodes.push(Node {
+            url: url.to_owned(),
+            result: None,
+        });
+        self.index.insert(url.to_owned(), i);
+        i
+    }
+
+    /// Record that `page` links to `target`, written as `href`.
+    pub fn add_link(&mut self, page: &str, target: &str, href: &str) {
+        let source = self.add_node(page);
+        let target = self.add_node(target);
+        self.edges.push(Edge {
+            source,
+            target,
+            href: href.to_owned(),
+        });
+    }
+
+    /// Attach the result of checking a URL to its node.
+    pub fn set_result(&mut self, result: CheckResult) {
+        let i = self.add_node(result.url_str());
+        self.nodes[i].result = Some(result);
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    pub fn node(&self, url: &str) -> Option<&Node> {
+        self.index.get(url).map(|&i| &self.nodes[i])
+    }
+
+    /// The links from `url`.
+    pub fn outgoing<'a>(&'a self, url: &str) -> impl Iterator<Item = &'a Edge> {
+        let i = self.index.get(url).cloned();
+        self.edges.iter().filter(move |edge| Some(edge.source) == i)
+    }
+
+    /// The links to `url`.
+    pub fn incoming<'a>(&'a self, url: &str) -> impl Iterator<Item = &'a Edge> {
+        let i = self.index.get(url).cloned();
+        self.edges.iter().filter(move |edge| Some(edge.target) == i)
+    }
+
+    /// Every (page, target) pair, in the order we found them.
+    pub fn links(&self) -> Vec<(String, String)> {
+        self.edges
+            .iter()
+            .map(|edge| {
+                (
+                    self.nodes[edge.source].url.clone(),
+                    self.nodes[edge.target].url.clone(),
+                )
+            })
+            .collect()
+    }
+
+    /// The pages linking to each URL, without duplicates.
+    pub fn referrers(&self) -> HashMap<String, Vec<String>> {
+        let mut referrers: HashMap<String, Vec<String>> = HashMap::new();
+        for edge in &self.edges {
+            let page = &self.nodes[edge.source].url;
+            let pages = referrers
+                .entry(self.nodes[edge.target].url.clone())
+                .or_default();
+            if !pages.contains(page) {
+                pages.push(page.clone());
+            }
+        }
+        referrers
+    }
+
+    /// The fewest clicks needed to reach each URL from `start_url`.
+    /// URLs we can't reach aren't included.
+    pub fn depths(&self, start_url: &str) -> HashMap<String, usize> {
+        self.depths_from(&[start_url])
+    }
+
+    /// The fewest clicks needed to reach each URL from the nearest of
+    /// `start_urls`.
+    pub fn depths_from(&self, start_urls: &[&str]) -> HashMap<String, usize> {
+        let mut outgoing: Vec<Vec<usize>> = vec![vec![]; self.nodes.len()];
+        for edge in &self.edges {
+            outgoing[edge.source].push(edge.target);
+        }
+
+        let mut seen = vec![false; self.nodes.len()];
+        let mut queue = VecDeque::new();
+        for start_url in start_urls {
+            if let Some(&start) = self.index.get(*start_url) {
+                if !seen[start] {
+                    seen[start] = true;
+                    queue.push_back((start, 0));
+                }
+            }
+        }
+        let mut depths = HashMap::new();
+        while let Some((i, depth)) = queue.pop_front() {
+            depths.insert(self.nodes[i].url.clone(), depth);
+            for &target in &outgoing[i] {
+                if !seen[target] {
+                    seen[target] = true;
+                    queue.push_back((target, depth + 1));
+                }
+            }
+        }
+        depths
+    }
+
+    /// The fewest clicks needed to reach each URL from any of the
+    /// crawl's seeds, i.e. the URLs checked at depth 0.
+    pub fn click_depths(&self) -> HashMap<String, usize> {
+        let seeds: Vec<&str> = self
+            .nodes
+            .iter()
+            .filter(|node| node.result.as_ref().and_then(|result| result.depth) == Some(0))
+            .map(|node| node.url.as_str())
+            .collect();
+        self.depths_from(&seeds)
+    }
+
+    /// The URLs in `pages` (e.g. from a sitemap) that no other page
+    /// links to.
+    pub fn orphans<'a>(&self, pages: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+        pages
+            .into_iter()
+            .filter(|page| {
+                !self
+                    .incoming(page)
+                    .any(|edge| self.nodes[edge.source].url != *page)
+            })
+            .collect()
+    }
+}
+
+/// Formats we can export the link graph in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GraphFormat {
+    Dot,
+    GraphML,
+}
+
+impl GraphFormat {
+    /// Guess the format from a file extension.
+    pub fn from_path(path: &Path) -> Option<GraphFormat> {
+        match path.extension()?.to_str()? {
+            "dot" | "gv" => Some(GraphFormat::Dot),
+            "graphml" => Some(GraphFormat::GraphML),
+            _ => None,
+        }
+    }
+}
+
+/// Render `graph` in `format`. Links to URLs in `broken` are
+/// highlighted.
+pub fn render(format: GraphFormat, graph: &LinkGraph, broken: &HashSet<&str>) -> String {
+    let links = graph.links();
+    match format {
+        GraphFormat::Dot => to_dot(&links, broken),
+        GraphFormat::GraphML => to_graphml(&links, broken),
+    }
+}
+
+fn escape_dot(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn to_dot(links: &[(String, String)], broken: &HashSet<&str>) -> String {
+    let mut s = String::from("digraph links {\n");
+    // Sort so the output is stable between runs.
+    let mut broken_targets: Vec<_> = broken.iter().collect();
+    broken_targets.sort();
+    for target in broken_targets {
+        s.push_str(&format!(
+            "    \"{}\" [color=red, fontcolor=red];\n",
+            escape_dot(target)
+        ));
+    }
+    for (page, target) in links {
+        let attrs = if broken.contains(target.as_str()) {
+            " [color=red]"
+        } else {
+            ""
+        };
+        s.push_str(&format!(
+            "    \"{}\" -> \"{}\"{};\n",
+            escape_dot(page),
+            escape_dot(target),
+            attrs
+        ));
+    }
+    s.push_str("}\n");
+    s
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn to_graphml(links: &[(String, String)], broken: &HashSet<&str>) -> String {
+    let mut s = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+  <key id="broken" for="all" attr.name="broken" attr.type="boolean">
+    <default>false</default>
+  </key>
+  <graph id="links" edgedefault="directed">
+"#,
+    );
+
+    // GraphML requires every node to be declared.
+    let mut seen = HashSet::new();
+    for (page, target) in links {
+        for node in [page.as_str(), target.as_str()].iter() {
+            if seen.insert(*node) {
+                s.push_str(&format!("    <node id=\"{}\"", escape_xml(node)));
+                if broken.contains(node) {
+                    s.push_str("><data key=\"broken\">true</data></node>\n");
+                } else {
+                    s.push_str("/>\n");
+                }
+            }
+        }
+    }
+
+    for (page, target) in links {
+        s.push_str(&format!(
+            "    <edge source=\"{}\" target=\"{}\"",
+            escape_xml(page),
+            escape_xml(target)
+        ));
+        if broken.contains(target.as_str()) {
+            s.push_str("><data key=\"broken\">true</data></edge>\n");
+        } else {
+            s.push_str("/>\n");
+        }
+    }
+
+    s.push_str("  </graph>\n</graphml>\n");
+    s
+}
@@ -0,0 +1,159 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The ignore file we look for in the working directory.
+pub const DEFAULT_PATH: &str = ".linkdocignore";
+
+/// URL patterns we shouldn't check, in the style of a .gitignore file.
+///
+/// Each line is a pattern matched against the whole URL, where `*`
+/// matches any run of characters and `?` matches a single character.
+/// Blank lines and lines starting with `#` are skipped. A pattern
+/// starting with `!` un-ignores URLs matched by earlier patterns, and
+/// later patterns take priority.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreList {
+    /// (negated, pattern) pairs, in file order.
+    rules: Vec<(bool, Vec<char>)>,
+}
+
+impl IgnoreList {
+    pub fn parse(src: &str) -> IgnoreList {
+        let rules = src
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| match line.strip_prefix('!') {
+                Some(pattern) => (true, pattern.chars().collect()),
+                None => (false, line.chars().collect()),
+            })
+            .collect();
+        IgnoreList { rules }
+    }
+
+    pub fn load(path: &Path) -> Result<IgnoreList, String> {
+        fs::read_to_string(path)
+            .map(|src| IgnoreList::parse(&src))
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn is_ignored(&self, url: &str) -> bool {
+        let url: Vec<char> = url.chars().collect();
+        self.rules
+            .iter()
+            .rev()
+            .find(|(_, pattern)| matches(pattern, &url))
+            .map_or(false, |(negated, _)| !negated)
+    }
+}
+
+/// A rule from a .gitignore file.
+#[derive(Debug, Clone)]
+struct GitRule {
+    /// The directory of the .gitignore the rule came from.
+    base: PathBuf,
+    negated: bool,
+    /// Patterns ending with `/` only match directories.
+    dir_only: bool,
+    /// Patterns containing a `/`, other than at the end, match paths
+    /// relative to `base`. Others match file names at any depth.
+    anchored: bool,
+    pattern: Vec<char>,
+}
+
+/// The paths git ignores in a tree, from its .gitignore files.
+///
+/// We handle the common subset of the syntax: `*`, `?`, `!`, and
+/// leading and trailing slashes. Unlike git, `*` can match `/` too.
+#[derive(Debug, Clone, Default)]
+pub struct GitIgnore {
+    rules: Vec<GitRule>,
+}
+
+impl GitIgnore {
+    /// Add the rules from the .gitignore in `dir`, if it has one.
+    /// Directories should be added from the top down, since rules added
+    /// later take priority.
+    pub fn add_dir(&mut self, dir: &Path) {
+        let src = match fs::read_to_string(dir.join(".gitignore")) {
+            Ok(src) => src,
+            Err(_) => return,
+        };
+        for line in src.lines().map(|line| line.trim_end()) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (negated, line) = match line.strip_prefix('!') {
+                Some(line) => (true, line),
+                None => (false, line),
+            };
+            let (dir_only, line) = match line.strip_suffix('/') {
+                Some(line) => (true, line),
+                None => (false, line),
+            };
+            let anchored = line.contains('/');
+            let pattern = line.trim_start_matches('/').chars().collect();
+            self.rules.push(GitRule {
+                base: dir.to_owned(),
+                negated,
+                dir_only,
+                anchored,
+                pattern,
+            });
+        }
+    }
+
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let name: Vec<char> = match path.file_name() {
+            Some(name) => name.to_string_lossy().chars().collect(),
+            None => return false,
+        };
+        self.rules
+            .iter()
+            .rev()
+            .filter(|rule| is_dir || !rule.dir_only)
+            .find(|rule| {
+                if !rule.anchored {
+                    return matches(&rule.pattern, &name);
+                }
+                match path.strip_prefix(&rule.base) {
+                    Ok(relative) => {
+                        let relative: Vec<char> = relative
+                            .components()
+                            .map(|c| c.as_os_str().to_string_lossy())
+                            .collect::<Vec<_>>()
+                            .join("/")
+                            .chars()
+                            .collect();
+                        matches(&rule.pattern, &relative)
+                    }
+                    Err(_) => false,
+                }
+            })
+            .map_or(false, |rule| !rule.negated)
+    }
+}
+
+/// Does the glob `pattern` match all of `s`?
+pub(crate) fn matches(pattern: &[char], s: &[char]) -> bool {
+    // Classic backtracking wildcard match: on a mismatch, let the most
+    // recent `*` swallow one more character and try again.
+    let (mut p, mut i) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while i < s.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == s[i]) {
+            p += 1;
+            i += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, i));
+            p += 1;
+        } else if let Some((star_p, star_i)) = star {
+            p = star_p + 1;
+            i = star_i + 1;
+            star = Some((star_p, star_i + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
@@ -0,0 +1,67 @@
+use url::Url;
+
+/// Extensions of image files, in lowercase.
+const IMAGE_EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "gif", "webp", "avif", "svg", "ico", "bmp", "tif", "tiff",
+];
+
+/// How much of the body we need to recognize an image format.
+pub const SNIFF_BYTES: usize = 512;
+
+/// Does `url` name an image file, judging by its extension?
+pub fn is_image_url(url: &Url) -> bool {
+    let name = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .unwrap_or_default();
+    match name.rsplit_once('.') {
+        Some((_, extension)) => IMAGE_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str()),
+        None => false,
+    }
+}
+
+/// Do the first bytes of a body, `head`, start like an image format we
+/// know?
+fn has_image_signature(head: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(head);
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    head.starts_with(b"\x89PNG\r\n\x1a\n")
+        || head.starts_with(b"\xff\xd8\xff")
+        || head.starts_with(b"GIF87a")
+        || head.starts_with(b"GIF89a")
+        || (head.starts_with(b"RIFF") && head.get(8..12) == Some(&b"WEBP"[..]))
+        || head.get(4..8) == Some(&b"ftyp"[..])
+        || head.starts_with(b"BM")
+        || head.starts_with(b"\x00\x00\x01\x00")
+        || head.starts_with(b"II*\x00")
+        || head.starts_with(b"MM\x00*")
+        || text.starts_with("<svg")
+        || (text.starts_with("<?xml") && text.contains("<svg"))
+}
+
+/// Why a successful response for an image URL isn't an image, if it
+/// isn't: it's served as something else, or as nothing in particular
+/// and doesn't look like one. `head` is the start of the body.
+pub fn not_an_image(content_type: Option<&str>, head: &[u8]) -> Option<String> {
+    let content_type = content_type.map(|content_type| {
+        content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+    });
+    match content_type.as_deref() {
+        Some(content_type) if content_type.starts_with("image/") => None,
+        // Servers that don't know the type let the body speak for
+        // itself.
+        None | Some("application/octet-stream") | Some("binary/octet-stream") => {
+            if has_image_signature(head) {
+                None
+            } else {
+                Some("the response doesn't look like an image".to_owned())
+            }
+        }
+        Some(content_type) => Some(format!("served as {}, not an image", content_type)),
+    }
+}
@@ -0,0 +1,175 @@
+#[cfg(feature = "native")]
+use crossbeam_channel::unbounded;
+#[cfg(feature = "native")]
+use reqwest::Client;
+use scraper::{Html, Selector};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+#[cfg(feature = "native")]
+use std::collections::HashMap;
+#[cfg(feature = "native")]
+use std::thread;
+#[cfg(feature = "native")]
+use std::time::Duration;
+#[cfg(feature = "native")]
+use tracing::debug;
+use url::Url;
+
+/// A script or stylesheet with an `integrity` attribute, which browsers
+/// refuse to use unless its hash matches.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Subresource {
+    pub page: String,
+    pub url: String,
+    pub integrity: String,
+}
+
+/// A subresource whose content doesn't match its `integrity` attribute,
+/// so it's blocked in every browser even though it loads.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct IntegrityMismatch {
+    pub page: String,
+    pub url: String,
+    /// The `integrity` attribute, as written.
+    pub expected: String,
+    /// The hash of the resource, with the algorithm `expected` uses.
+    pub actual: String,
+}
+
+/// Every script and stylesheet with an `integrity` attribute on `body`,
+/// the page at `url`.
+pub fn find(url: &Url, body: &str) -> Vec<Subresource> {
+    let document = Html::parse_document(body);
+    let mut found = vec![];
+    for (selector, attr) in &[
+        ("script[src][integrity]", "src"),
+        ("link[href][integrity]", "href"),
+    ] {
+        let selector = Selector::parse(selector).unwrap();
+        for element in document.select(&selector) {
+            let resource = element
+                .value()
+                .attr(attr)
+                .and_then(|src| url.join(src.trim()).ok());
+            let integrity = element.value().attr("integrity").unwrap_or_default();
+            if let Some(resource) = resource {
+                found.push(Subresource {
+                    page: url.to_string(),
+                    url: resource.into_string(),
+                    integrity: integrity.trim().to_owned(),
+                });
+            }
+        }
+    }
+    found
+}
+
+/// The strongest algorithm in `integrity` and the hashes given for it.
+/// Browsers ignore weaker algorithms and ones they don't know, and
+/// accept the resource if it matches any of the hashes.
+pub fn strongest_hashes(integrity: &str) -> Option<(&str, Vec<&str>)> {
+    const ALGORITHMS: &[&str] = &["sha512", "sha384", "sha256"];
+    let hashes: Vec<(&str, &str)> = integrity
+        .split_whitespace()
+        .filter_map(|entry| {
+            // Anything after a `?` is an option, which we ignore.
+            let entry = entry.split('?').next().unwrap_or_default();
+            entry.split_once('-')
+        })
+        .collect();
+    ALGORITHMS.iter().find_map(|algorithm| {
+        let matching: Vec<&str> = hashes
+            .iter()
+            .filter(|(alg, _)| alg.eq_ignore_ascii_case(algorithm))
+            .map(|(_, hash)| *hash)
+            .collect();
+        if matching.is_empty() {
+            None
+        } else {
+            Some((*algorithm, matching))
+        }
+    })
+}
+
+/// The base64 hash of `bytes` with `algorithm`.
+pub fn digest(algorithm: &str, bytes: &[u8]) -> String {
+    match algorithm {
+        "sha512" => base64::encode(Sha512::digest(bytes)),
+        "sha384" => base64::encode(Sha384::digest(bytes)),
+        _ => base64::encode(Sha256::digest(bytes)),
+    }
+}
+
+/// Download `url`, following redirects like a browser would, if it
+/// loads within `timeout`.
+#[cfg(feature = "native")]
+fn download(client: &Client, url: &str, timeout: Duration) -> Option<Vec<u8>> {
+    // Like checks, the request carries on in the background if it
+    // takes too long, and we stop waiting for it.
+    let (s, r) = unbounded();
+    let client = client.clone();
+    let owned_url = url.to_owned();
+    thread::spawn(move || {
+        let url = owned_url;
+        let mut response = match client.get(&url).send() {
+            Ok(response) if response.status().is_success() => response,
+            Ok(response) => {
+                debug!(%url, status = %response.status(), "could not download subresource");
+                let _ = s.send(None);
+                return;
+            }
+            Err(e) => {
+                debug!(%url, error = %e, "could not download subresource");
+                let _ = s.send(None);
+                return;
+            }
+        };
+        let mut body = vec![];
+        let _ = s.send(response.copy_to(&mut body).ok().map(|_| body));
+    });
+    match r.recv_timeout(timeout) {
+        Ok(body) => body,
+        Err(_) => {
+            debug!(%url, timeout_secs = timeout.as_secs(), "subresource timed out");
+            None
+        }
+    }
+}
+
+/// Download each of `resources` and return the ones that don't match
+/// their `integrity` attribute. We download each URL once, one at a
+/// time, and skip resources that don't load, since checking the links
+/// reports those, or that take longer than `timeout`. Requests use
+/// `client`, e.g. from `fetching::client`, so they send its User-Agent.
+#[cfg(feature = "native")]
+pub fn verify(
+    client: &Client,
+    timeout: Duration,
+    resources: &[Subresource],
+) -> Vec<IntegrityMismatch> {
+    let mut downloads: HashMap<&str, Option<Vec<u8>>> = HashMap::new();
+    let mut mismatches = vec![];
+    for resource in resources {
+        let (algorithm, hashes) = match strongest_hashes(&resource.integrity) {
+            Some(strongest) => strongest,
+            None => continue,
+        };
+        let body = downloads
+            .entry(&resource.url)
+            .or_insert_with(|| download(client, &resource.url, timeout));
+        let body = match body {
+            Some(body) => body,
+            None => continue,
+        };
+        let actual = digest(algorithm, body);
+        if !hashes.contains(&actual.as_str()) {
+            mismatches.push(IntegrityMismatch {
+                page: resource.page.clone(),
+                url: resource.url.clone(),
+                expected: resource.integrity.clone(),
+                actual: format!("{}-{}", algorithm, actual),
+            });
+        }
+    }
+    mismatches
+}
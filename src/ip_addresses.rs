@@ -0,0 +1,84 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+use url::{Host, Url};
+
+use crate::graphing::LinkGraph;
+use crate::scoping::Scope;
+
+/// A link from a page on the site to a bare IP address, rather than a
+/// host name. These often point at internal infrastructure, and break
+/// when the server moves.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct IpLink {
+    pub page: String,
+    pub url: String,
+    /// What sort of address it is: `loopback`, `private`, `link-local`
+    /// or `public`.
+    pub kind: String,
+}
+
+fn ipv4_kind(ip: Ipv4Addr) -> &'static str {
+    if ip.is_loopback() {
+        "loopback"
+    } else if ip.is_private() {
+        "private"
+    } else if ip.is_link_local() {
+        "link-local"
+    } else {
+        "public"
+    }
+}
+
+fn ipv6_kind(ip: Ipv6Addr) -> &'static str {
+    let first = ip.segments()[0];
+    if let Some(ip) = ip.to_ipv4_mapped() {
+        ipv4_kind(ip)
+    } else if ip.is_loopback() {
+        "loopback"
+    } else if first & 0xfe00 == 0xfc00 {
+        // Unique local addresses, fc00::/7.
+        "private"
+    } else if first & 0xffc0 == 0xfe80 {
+        "link-local"
+    } else {
+        "public"
+    }
+}
+
+/// What sort of IP address the host of `url` is, if it's one.
+pub fn ip_kind(url: &Url) -> Option<&'static str> {
+    match url.host()? {
+        Host::Ipv4(ip) => Some(ipv4_kind(ip)),
+        Host::Ipv6(ip) => Some(ipv6_kind(ip)),
+        Host::Domain(_) => None,
+    }
+}
+
+/// Every link in `graph` from a page in `scope` to an IP address that
+/// isn't the site itself, in the order we found them.
+pub fn ip_links(graph: &LinkGraph, scope: &Scope) -> Vec<IpLink> {
+    let nodes = graph.nodes();
+    let mut links: Vec<IpLink> = vec![];
+    for edge in graph.edges() {
+        let (page, url) = (&nodes[edge.source].url, &nodes[edge.target].url);
+        let parsed = match (Url::parse(page), Url::parse(url)) {
+            (Ok(page), Ok(url)) if scope.contains(&page) && !scope.contains(&url) => url,
+            _ => continue,
+        };
+        let kind = match ip_kind(&parsed) {
+            Some(kind) => kind,
+            None => continue,
+        };
+        let seen = links
+            .iter()
+            .any(|link| link.page == *page && link.url == *url);
+        if !seen {
+            links.push(IpLink {
+                page: page.clone(),
+                url: url.clone(),
+                kind: kind.to_owned(),
+            });
+        }
+    }
+    links
+}
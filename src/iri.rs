@@ -0,0 +1,64 @@
+use percent_encoding::percent_decode;
+use url::{Position, Url};
+
+/// `url` as a person would write it: an internationalized host in
+/// Unicode rather than punycode, and percent-encoded non-ASCII text in
+/// the path, query and fragment decoded. Requests always use the ASCII
+/// form, and malformed URLs are returned as written.
+pub fn display(url: &str) -> String {
+    let parsed = match Url::parse(url) {
+        Ok(parsed) => parsed,
+        Err(_) => return url.to_owned(),
+    };
+    let host = match parsed.domain() {
+        Some(host) => host,
+        None => return decode_non_ascii(parsed.as_str()),
+    };
+    let (unicode, result) = idna::domain_to_unicode(host);
+    let unicode = if result.is_ok() {
+        unicode.as_str()
+    } else {
+        host
+    };
+    format!(
+        "{}{}{}",
+        &parsed[..Position::BeforeHost],
+        unicode,
+        decode_non_ascii(&parsed[Position::AfterHost..])
+    )
+}
+
+/// Decode the runs of percent escapes in `s` that spell out non-ASCII
+/// UTF-8, leaving ASCII escapes such as `%2F` alone since decoding them
+/// could change the URL's meaning.
+fn decode_non_ascii(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = String::with_capacity(s.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        while i + 2 < bytes.len() && bytes[i] == b'%' && is_high_escape(&bytes[i + 1..i + 3]) {
+            i += 3;
+        }
+        if i > start {
+            let escaped = &s[start..i];
+            match percent_decode(escaped.as_bytes()).decode_utf8() {
+                Ok(decoded) if !decoded.chars().any(char::is_control) => out.push_str(&decoded),
+                _ => out.push_str(escaped),
+            }
+            continue;
+        }
+        let c = s[i..].chars().next().unwrap();
+        out.push(c);
+        i += c.len_utf8();
+    }
+    out
+}
+
+/// Is `hex` two hex digits for a byte that isn't ASCII?
+fn is_high_escape(hex: &[u8]) -> bool {
+    std::str::from_utf8(hex)
+        .ok()
+        .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        .map_or(false, |byte| byte >= 0x80)
+}
@@ -0,0 +1,124 @@
+//! Link Doctor finds broken links in web sites.
+//!
+//! Configure a crawl with [`Crawler::builder`], then iterate over the
+//! [`Crawler`] to get a [`CheckResult`] for every URL as it's checked:
+//!
+//! ```no_run
+//! use linkdoc::Crawler;
+//! use url::Url;
+//!
+//! let start_url = Url::parse("http://example.com").unwrap();
+//! let crawler = Crawler::builder()
+//!     .seed(&start_url)
+//!     .max_depth(3)
+//!     .build()
+//!     .unwrap();
+//! for result in crawler {
+//!     if result.is_accessible() {
+//!         continue;
+//!     }
+//!     println!("{}", result);
+//! }
+//! ```
+//!
+//! # Stability
+//!
+//! The items re-exported here at the crate root follow semantic
+//! versioning: they won't change incompatibly without a major version
+//! bump. The modules are also public, since the `linkdoc` binary is
+//! built on them, but anything only reachable through a module path
+//! may change in any release.
+//!
+//! # Features
+//!
+//! The default `native` feature provides the crawler, which checks
+//! URLs over HTTP on its own threads, along with the binary's config
+//! and template rendering. Without it, the crate is just
+//! link extraction, the result model and reporting, which also build
+//! for `wasm32`, so browser tools can check URLs their own way and
+//! still use them.
+//!
+//! The crawler doesn't need an async runtime: iterating a [`Crawler`]
+//! blocks until the next result, and [`crawl_blocking`] starts one with
+//! the default [`CrawlOptions`] or your own. The `stream` feature makes it a
+//! `futures::Stream` too, for callers that already have a runtime.
+
+#[cfg(feature = "native")]
+pub mod archiving;
+pub mod assertions;
+pub mod baseline;
+pub mod blocklist;
+pub mod comments;
+#[cfg(feature = "native")]
+pub mod config;
+pub mod confusables;
+pub mod content_types;
+#[cfg(feature = "native")]
+pub mod crawling;
+#[cfg(feature = "native")]
+pub mod database;
+pub mod diffing;
+pub mod directory;
+pub mod duplicates;
+pub mod fetching;
+pub mod fixing;
+pub mod graphing;
+pub mod ignoring;
+pub mod images;
+pub mod integrity;
+pub mod ip_addresses;
+pub mod iri;
+pub mod locales;
+pub mod login;
+pub mod mdbook;
+pub mod merging;
+pub mod metrics;
+pub mod middleware;
+pub mod mixed_content;
+pub mod normalizing;
+#[cfg(feature = "native")]
+pub mod notifying;
+pub mod openapi;
+pub mod parking;
+pub mod parsing;
+#[cfg(feature = "native")]
+pub mod politeness;
+pub mod prioritizing;
+#[cfg(feature = "native")]
+pub mod progress;
+pub mod ranking;
+pub mod reporting;
+#[cfg(feature = "native")]
+pub mod resolving;
+pub mod rustdoc;
+pub mod schemes;
+pub mod scoping;
+pub mod severity;
+pub mod sitemap;
+pub mod soft404;
+#[cfg(feature = "native")]
+pub mod stats;
+#[cfg(feature = "native")]
+pub mod store;
+pub mod tuning;
+#[cfg(feature = "native")]
+pub mod upgrading;
+#[cfg(feature = "native")]
+pub mod wayback;
+
+#[cfg(feature = "native")]
+pub use crate::config::Config;
+#[cfg(feature = "native")]
+pub use crate::crawling::{
+    crawl_blocking, CancelHandle, CrawlError, CrawlOptions, Crawler, CrawlerBuilder,
+};
+pub use crate::fetching::{CheckResult, FetchError, Fetcher, Outcome};
+pub use crate::middleware::Middleware;
+pub use crate::parsing::LinkExtractor;
+#[cfg(feature = "native")]
+pub use crate::politeness::Politeness;
+pub use crate::prioritizing::Priority;
+pub use crate::reporting::{Format, Report};
+pub use crate::severity::{Severities, Severity};
+#[cfg(feature = "native")]
+pub use crate::store::CrawlStore;
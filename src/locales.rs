@@ -0,0 +1,91 @@
+use std::collections::BTreeMap;
+use url::Url;
+
+use crate::graphing::LinkGraph;
+
+/// A page that's broken or missing in some locales but not others.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct LocaleDrift {
+    /// The page's path within each locale, e.g. `about/` for
+    /// `/en/about/` and `/de/about/`.
+    pub path: String,
+    /// The locales where the page works.
+    pub present: Vec<String>,
+    /// The locales where the page is linked to but broken.
+    pub broken: Vec<String>,
+    /// The locales where nothing links to the page.
+    pub missing: Vec<String>,
+}
+
+/// The name of the locale with `prefix`, e.g. `de` for `/de/`.
+fn locale_name(prefix: &str) -> String {
+    prefix.trim_matches('/').to_owned()
+}
+
+/// The URL of each locale's home page, so each locale is crawled even
+/// if the start page doesn't link to it.
+pub fn locale_urls(start_url: &Url, prefixes: &[String]) -> Vec<Url> {
+    prefixes
+        .iter()
+        .filter_map(|prefix| {
+            start_url
+                .join(&format!("/{}/", prefix.trim_matches('/')))
+                .ok()
+        })
+        .collect()
+}
+
+/// Compare the pages under each locale prefix on the site of
+/// `start_url`, returning the pages that aren't fine in every locale,
+/// sorted by path. Only pages we've checked count, so a page is
+/// missing from a locale when no page in the crawl links to it.
+pub fn compare(graph: &LinkGraph, start_url: &Url, prefixes: &[String]) -> Vec<LocaleDrift> {
+    let prefixes: Vec<String> = prefixes
+        .iter()
+        .map(|prefix| format!("/{}/", prefix.trim_matches('/')))
+        .collect();
+
+    // Whether each page works, by path and then locale.
+    let mut pages: BTreeMap<String, BTreeMap<&str, bool>> = BTreeMap::new();
+    for node in graph.nodes() {
+        let result = match node.result {
+            Some(ref result) => result,
+            None => continue,
+        };
+        let url = match Url::parse(&node.url) {
+            Ok(url) if url.origin() == start_url.origin() => url,
+            _ => continue,
+        };
+        for prefix in &prefixes {
+            if let Some(path) = url.path().strip_prefix(prefix.as_str()) {
+                let ok = pages
+                    .entry(path.to_owned())
+                    .or_default()
+                    .entry(prefix.as_str())
+                    .or_insert(true);
+                *ok = *ok && result.is_accessible();
+            }
+        }
+    }
+
+    let mut drift = vec![];
+    for (path, locales) in pages {
+        let mut page = LocaleDrift {
+            path,
+            ..LocaleDrift::default()
+        };
+        for prefix in &prefixes {
+            let name = locale_name(prefix);
+            match locales.get(prefix.as_str()) {
+                Some(true) => page.present.push(name),
+                Some(false) => page.broken.push(name),
+                None => page.missing.push(name),
+            }
+        }
+        if !page.broken.is_empty() || !page.missing.is_empty() {
+            drift.push(page);
+        }
+    }
+    drift
+}
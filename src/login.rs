@@ -0,0 +1,61 @@
+use url::Url;
+
+/// Path segments of sign-in pages, ignoring case and any extension,
+/// e.g. `/users/sign_in` or `/wp-login.php`.
+const LOGIN_SEGMENTS: &[&str] = &[
+    "login",
+    "log-in",
+    "log_in",
+    "logon",
+    "signin",
+    "sign-in",
+    "sign_in",
+    "wp-login",
+    "sso",
+    "saml",
+    "oauth",
+    "oauth2",
+    "authorize",
+    "authenticate",
+];
+
+/// Hosts of identity providers, whose every page is a sign-in page.
+const LOGIN_HOSTS: &[&str] = &[
+    "accounts.google.com",
+    "login.microsoftonline.com",
+    "login.live.com",
+    "okta.com",
+    "auth0.com",
+    "onelogin.com",
+];
+
+/// Is `url` a sign-in page?
+fn is_login_page(url: &Url) -> bool {
+    let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+    let on_login_host = LOGIN_HOSTS.iter().any(|login| {
+        host == *login
+            || host
+                .strip_suffix(login)
+                .map_or(false, |sub| sub.ends_with('.'))
+    });
+    on_login_host
+        || url.path_segments().map_or(false, |mut segments| {
+            segments.any(|segment| {
+                let segment = segment.to_ascii_lowercase();
+                let name = segment.split('.').next().unwrap_or_default();
+                LOGIN_SEGMENTS.contains(&name)
+            })
+        })
+}
+
+/// Why a link that was redirected through `redirects` ends up on a
+/// sign-in page, if it does.
+pub fn login_redirect(redirects: &[String]) -> Option<String> {
+    let last = redirects.last()?;
+    let url = Url::parse(last).ok()?;
+    if is_login_page(&url) {
+        Some(format!("redirects to the sign-in page {}", url))
+    } else {
+        None
+    }
+}
@@ -1,53 +1,1564 @@
-use clap::{App, Arg};
+use clap::ArgMatches;
 use colored::*;
-use std::io::stdout;
-use std::io::Write;
+use std::cell::OnceCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::io::{stderr, stdin, stdout, Read};
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+use std::process;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
 use url::Url;
 
-use crate::fetching::UrlState;
+use linkdoc::config::{self, Config};
+use linkdoc::crawling::{Crawler, CrawlerBuilder};
+use linkdoc::fetching::{self, CheckResult};
+use linkdoc::reporting::{self, Format, Report};
+use linkdoc::scoping::Scope;
+use linkdoc::severity::{self, Severity};
+use linkdoc::{
+    archiving, assertions, baseline, blocklist, comments, confusables, crawling, database, diffing,
+    directory, duplicates, fixing, graphing, ignoring, integrity, ip_addresses, locales, mdbook,
+    merging, mixed_content, normalizing, notifying, openapi, prioritizing, ranking, rustdoc,
+    schemes, sitemap, soft404, store, tuning, upgrading, wayback,
+};
 
-mod crawling;
-mod fetching;
-mod parsing;
+mod cli;
+mod dashboard;
+mod serving;
+mod triaging;
+mod tui;
 
-fn main() {
-    let matches = App::new("LinkDoctor")
-        .version("0.2")
-        .about("Walks all the web pages in a domain to find dead links.")
-        .author("Wilfred Hughes")
-        .arg(Arg::with_name("START URL").required(true))
-        .get_matches();
+/// Decide whether to color output. In `auto` mode, we only use color
+/// when writing to a terminal and NO_COLOR (https://no-color.org/)
+/// isn't set.
+fn use_color(when: &str) -> bool {
+    match when {
+        "always" => true,
+        "never" => false,
+        _ => {
+            let no_color = env::var_os("NO_COLOR").map_or(false, |v| !v.is_empty());
+            !no_color && stdout().is_terminal()
+        }
+    }
+}
 
-    let start_url_string = matches.value_of("START URL").unwrap();
+fn init_logging(level: &str, json: bool, color: bool) {
+    let level: tracing::Level = match level.parse() {
+        Ok(level) => level,
+        Err(_) => fail(&format!("Unknown log level {}", level)),
+    };
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(stderr);
+    if json {
+        subscriber.json().init();
+    } else {
+        subscriber.with_ansi(color).init();
+    }
+}
 
-    // TODO: a proper error message here.
-    let start_url = Url::parse(start_url_string).unwrap();
+/// `linkdoc merge`: combine JSON reports into one.
+fn run_merge(matches: &ArgMatches) {
+    let paths: Vec<_> = matches.values_of("REPORTS").unwrap().collect();
+    let merged = match merging::merge(&paths) {
+        Ok(merged) => merged,
+        Err(e) => {
+            eprintln!("Could not merge reports: {}", e);
+            process::exit(2);
+        }
+    };
 
-    let domain = start_url
-        .domain()
-        .expect("I can't find a domain in your URL");
+    let rendered = match matches.value_of("format").unwrap() {
+        "text" => merging::to_text(&merged),
+        _ => serde_json::to_string_pretty(&merged).unwrap(),
+    };
+    match matches.value_of("output") {
+        Some(path) => {
+            if let Err(e) = fs::write(path, rendered) {
+                eprintln!("Could not write merged report to {}: {}", path, e);
+                process::exit(2);
+            }
+        }
+        None => println!("{}", rendered),
+    }
+}
 
-    let mut success_count = 0;
-    let mut fail_count = 0;
+/// Print `message` and exit with a status that isn't confused with
+/// finding broken links.
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(2);
+}
 
-    for url_state in crawling::crawl(&domain, &start_url) {
-        match url_state {
-            UrlState::Accessible(_, _) => {
-                success_count += 1;
+/// Read the config file given with `--config`, or the default one if
+/// it exists.
+fn load_config_file(matches: &ArgMatches) -> Config {
+    let path = matches
+        .value_of("config")
+        .map(|path| path.to_owned())
+        .or_else(|| env::var(format!("{}CONFIG", config::ENV_PREFIX)).ok());
+    match path.as_deref() {
+        Some(path) => match Config::load(Path::new(path)) {
+            Ok(config) => config,
+            Err(e) => fail(&format!("Could not read config file {}: {}", path, e)),
+        },
+        None if Path::new(config::DEFAULT_PATH).exists() => {
+            match Config::load(Path::new(config::DEFAULT_PATH)) {
+                Ok(config) => config,
+                Err(e) => fail(&format!(
+                    "Could not read config file {}: {}",
+                    config::DEFAULT_PATH,
+                    e
+                )),
             }
-            status => {
-                fail_count += 1;
-                println!("{}", status);
+        }
+        None => Config::default(),
+    }
+}
+
+/// Combine settings from the config file (with the selected profile,
+/// if any), the environment and the command line, in increasing order
+/// of priority.
+fn load_config(matches: &ArgMatches) -> Config {
+    let mut file_config = load_config_file(matches);
+    let profile = matches
+        .value_of("profile")
+        .map(|name| name.to_owned())
+        .or_else(|| env::var(format!("{}PROFILE", config::ENV_PREFIX)).ok());
+    if let Some(name) = profile {
+        file_config = match file_config.with_profile(&name) {
+            Ok(config) => config,
+            Err(e) => fail(&format!("Could not use profile: {}", e)),
+        };
+    }
+
+    let env_config = match Config::from_env() {
+        Ok(config) => config,
+        Err(e) => fail(&e),
+    };
+    file_config
+        .merge(env_config)
+        .merge(Config::from_matches(matches))
+}
+
+/// `linkdoc completions`: print a shell completion script.
+fn run_completions(matches: &ArgMatches) {
+    let shell: clap::Shell = matches.value_of("SHELL").unwrap().parse().unwrap();
+
+    // Completion scripts are static, so we offer the profiles in the
+    // config file as it is now. The app needs 'static strings, but we
+    // exit straight after generating the script.
+    let profiles: Vec<&'static str> = load_config_file(matches)
+        .profile
+        .unwrap_or_default()
+        .into_keys()
+        .map(|name| &*Box::leak(name.into_boxed_str()))
+        .collect();
+    let profiles = Box::leak(profiles.into_boxed_slice());
+
+    cli::build(profiles).gen_completions_to("linkdoc", shell, &mut stdout());
+}
+
+/// `linkdoc diff`: compare two JSON reports.
+fn run_diff(matches: &ArgMatches) {
+    let diff = match diffing::diff(
+        matches.value_of("OLD").unwrap(),
+        matches.value_of("NEW").unwrap(),
+    ) {
+        Ok(diff) => diff,
+        Err(e) => fail(&format!("Could not compare reports: {}", e)),
+    };
+
+    match matches.value_of("format").unwrap() {
+        "json" => println!("{}", serde_json::to_string_pretty(&diff.to_json()).unwrap()),
+        _ => print!("{}", diff.to_text()),
+    }
+
+    if !diff.new.is_empty() {
+        process::exit(1);
+    }
+}
+
+/// `linkdoc crawl`: check every page on a site.
+fn run_crawl(matches: &ArgMatches) {
+    let config = load_config(matches);
+    init_output(&config);
+
+    let start_url = start_url(&config);
+    if config.list_only.unwrap_or(false) {
+        list_urls(&config, &start_url);
+        return;
+    }
+
+    if let Some(interval) = config.watch.as_deref() {
+        let interval = match cli::parse_interval(interval) {
+            Ok(interval) => interval,
+            Err(e) => fail(&e),
+        };
+        watch(&config, &start_url, interval);
+    }
+
+    let (_, too_many_broken) = check_and_report(
+        &config,
+        start_url.as_str(),
+        Some(&CrawledSite::new(&start_url)),
+        false,
+        crawl_seeds(&config, &start_url),
+        None,
+    );
+    if too_many_broken {
+        process::exit(1);
+    }
+}
+
+/// The site we're crawling, with its sitemap once we've needed it, so
+/// repeated crawls (e.g. with `--watch`) only fetch it once.
+struct CrawledSite {
+    start_url: Url,
+    sitemap: OnceCell<Vec<String>>,
+}
+
+impl CrawledSite {
+    fn new(start_url: &Url) -> CrawledSite {
+        CrawledSite {
+            start_url: start_url.clone(),
+            sitemap: OnceCell::new(),
+        }
+    }
+
+    /// The page URLs in the site's sitemap.xml, if it has one.
+    fn sitemap(&self, config: &Config) -> &[String] {
+        self.sitemap.get_or_init(|| {
+            let client = match fetching::client(config.user_agent.as_deref()) {
+                Ok(client) => client,
+                Err(e) => fail(&e.to_string()),
+            };
+            let timeout = config
+                .timeout
+                .map(Duration::from_secs)
+                .unwrap_or(fetching::DEFAULT_TIMEOUT);
+            fetching::fetch_sitemap_urls(&client, &self.start_url, timeout)
+        })
+    }
+}
+
+/// Start crawling at `start_url`, and at the home page of each locale
+/// we're comparing.
+fn crawl_seeds(config: &Config, start_url: &Url) -> CrawlerBuilder {
+    let mut crawler = Crawler::builder().seed(start_url);
+    for url in locales::locale_urls(start_url, config.locales.as_deref().unwrap_or_default()) {
+        crawler = crawler.seed(&url);
+    }
+    crawler
+}
+
+/// `linkdoc list`: a dry run of `linkdoc crawl`, the same as
+/// `linkdoc crawl --list-only`.
+fn run_list(matches: &ArgMatches) {
+    let config = load_config(matches);
+    init_output(&config);
+
+    list_urls(&config, &start_url(&config));
+}
+
+/// The URL to start crawling from, from the command line or the config
+/// file.
+fn start_url(config: &Config) -> Url {
+    let start_url = match config.start_url {
+        Some(ref start_url) => start_url,
+        None => fail(
+            "No start URL given, pass one on the command line or set start-url in the config file.",
+        ),
+    };
+
+    // URLs from the command line have already been validated, but
+    // not those from the config file.
+    if let Err(e) = cli::is_start_url(start_url.to_owned()) {
+        fail(&e);
+    }
+    Url::parse(start_url).unwrap()
+}
+
+/// `linkdoc crawl --list-only`: print every URL found on the site,
+/// with the pages linking to it.
+fn list_urls(config: &Config, start_url: &Url) {
+    let format = config.format.as_deref().unwrap_or("text");
+    if format != "text" && format != "json" {
+        fail("Listing URLs only supports the text and json formats");
+    }
+
+    let mut crawler = start(
+        config,
+        crawl_seeds(config, start_url),
+        Some(&CrawledSite::new(start_url)),
+    );
+    // Without the link graph, e.g. with --bloom-filter, we only know
+    // the page each URL was first found on.
+    let mut referrers: BTreeMap<_, _> = crawler
+        .by_ref()
+        .map(|state| (state.url_str().to_owned(), state.referrers))
+        .collect();
+    referrers.extend(crawler.referrers());
+
+    if format == "json" {
+        let urls: Vec<_> = referrers
+            .iter()
+            .map(|(url, pages)| serde_json::json!({ "url": url, "referrers": pages }))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&urls).unwrap());
+    } else {
+        for (url, pages) in &referrers {
+            println!("{}", url);
+            for page in pages {
+                println!("    from {}", page);
             }
         }
+    }
+}
+
+/// `linkdoc crawl --watch`: crawl the site every `interval`, printing
+/// the links that broke or were fixed since the previous crawl.
+fn watch(config: &Config, start_url: &Url, interval: Duration) -> ! {
+    if config.dashboard.is_some() {
+        fail("--dashboard can't be used with --watch");
+    }
 
-        print!(
-            "{}: {} {}: {}\r",
-            "Succeeded".green(),
-            success_count,
-            "Failed".red(),
-            fail_count
+    let crawled = CrawledSite::new(start_url);
+    let mut previous = serde_json::json!({});
+    loop {
+        let (report, _) = check_and_report(
+            config,
+            start_url.as_str(),
+            Some(&crawled),
+            true,
+            crawl_seeds(config, start_url),
+            None,
         );
-        stdout().flush().unwrap();
+        let current = reporting::report_json(&report);
+        let diff = diffing::compare(&previous, &current);
+
+        if diff.has_changes() {
+            let time = chrono::Utc::now().to_rfc3339();
+            match config.format.as_deref() {
+                Some("json") => {
+                    let mut changes = diff.to_json();
+                    changes["time"] = time.into();
+                    println!("{}", changes);
+                }
+                _ => print!("{}:\n{}", time, diff.changes_text()),
+            }
+            stdout().flush().unwrap();
+        }
+
+        previous = current;
+        thread::sleep(interval);
+    }
+}
+
+/// How many times `linkdoc recheck` retries each URL, unless configured
+/// otherwise.
+const DEFAULT_RECHECK_RETRIES: usize = 2;
+
+/// `linkdoc recheck`: check the broken links in a JSON report again.
+fn run_recheck(matches: &ArgMatches) {
+    let mut config = load_config(matches);
+    init_output(&config);
+    config.retries = config.retries.or(Some(DEFAULT_RECHECK_RETRIES));
+
+    let path = matches.value_of("REPORT").unwrap();
+    let old_report: serde_json::Value = match fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|src| serde_json::from_str(&src).map_err(|e| e.to_string()))
+    {
+        Ok(report) => report,
+        Err(e) => fail(&format!("Could not read report {}: {}", path, e)),
+    };
+
+    let mut crawler = Crawler::builder().check_only(true);
+    for failure in old_report["failures"].as_array().into_iter().flatten() {
+        let url = match failure["url"].as_str() {
+            Some(url) => url,
+            None => continue,
+        };
+        let referrers: Vec<&str> = match failure["referrers"].as_array() {
+            Some(referrers) => referrers.iter().filter_map(|r| r.as_str()).collect(),
+            None => failure["referrer"].as_str().into_iter().collect(),
+        };
+        for referrer in referrers {
+            crawler = crawler.url(referrer, url);
+        }
+    }
+
+    let site = old_report["start_url"].as_str().unwrap_or(path);
+    let (_, too_many_broken) = check_and_report(&config, site, None, false, crawler, None);
+    if too_many_broken {
+        process::exit(1);
+    }
+}
+
+/// `linkdoc serve`: serve an HTTP API for running crawls.
+fn run_serve(matches: &ArgMatches) {
+    let config = load_config(matches);
+    init_output(&config);
+
+    let options = crawl_options(&config);
+    let dashboard =
+        config
+            .dashboard
+            .as_deref()
+            .map(|addr| match dashboard::Dashboard::start(addr) {
+                Ok(dashboard) => dashboard,
+                Err(e) => fail(&format!("Could not start dashboard on {}: {}", addr, e)),
+            });
+    let server = serving::Server::new(
+        serving::Defaults {
+            concurrency: options.concurrency,
+            timeout: options.timeout,
+            severities: severities(&config),
+        },
+        dashboard,
+    );
+    let addr = config.listen.as_deref().unwrap_or("127.0.0.1:8000");
+    if let Err(e) = server.run(addr) {
+        fail(&format!("Could not listen on {}: {}", addr, e));
+    }
+}
+
+/// `linkdoc urls`: check a list of URLs without crawling.
+fn run_urls(matches: &ArgMatches) {
+    let config = load_config(matches);
+    init_output(&config);
+
+    let (source, input) = match config.input.as_deref() {
+        Some(path) => match fs::read_to_string(path) {
+            Ok(input) => (path, input),
+            Err(e) => fail(&format!("Could not read {}: {}", path, e)),
+        },
+        None => {
+            let mut input = String::new();
+            if let Err(e) = stdin().read_to_string(&mut input) {
+                fail(&format!("Could not read URLs from stdin: {}", e));
+            }
+            ("stdin", input)
+        }
+    };
+
+    // Each URL's referrer is where we read it from, so reports point
+    // back at the line in the list.
+    let mut seen = HashSet::new();
+    let mut crawler = Crawler::builder().check_only(true);
+    for (i, line) in input.lines().enumerate() {
+        let url = line.trim();
+        if url.is_empty() || url.starts_with('#') || !seen.insert(url) {
+            continue;
+        }
+        crawler = crawler.url(&format!("{}:{}", source, i + 1), url);
+    }
+
+    let (_, too_many_broken) = check_and_report(&config, source, None, false, crawler, None);
+    if too_many_broken {
+        process::exit(1);
+    }
+}
+
+/// `linkdoc openapi`: check the URLs in some OpenAPI specs. Each URL's
+/// referrer is the spec and a JSON pointer to where the URL is in it.
+fn run_openapi(matches: &ArgMatches) {
+    let config = load_config(matches);
+    init_output(&config);
+
+    let paths: Vec<_> = matches.values_of("SPECS").unwrap().collect();
+    let mut crawler = Crawler::builder().check_only(true);
+    for path in &paths {
+        let spec = match fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|src| openapi::parse(&src))
+        {
+            Ok(spec) => spec,
+            Err(e) => fail(&format!("Could not read spec {}: {}", path, e)),
+        };
+        for (pointer, url) in openapi::urls(&spec, None) {
+            crawler = crawler.url(&format!("{}#{}", path, pointer), &url);
+        }
+    }
+
+    let site = paths.join(", ");
+    let (_, too_many_broken) = check_and_report(&config, &site, None, false, crawler, None);
+    if too_many_broken {
+        process::exit(1);
+    }
+}
+
+/// `linkdoc check`: check the links in a directory of HTML files,
+/// resolving links on the base URL to files in the directory.
+fn run_check(matches: &ArgMatches) {
+    let mut config = load_config(matches);
+    init_output(&config);
+
+    let dir = match config.dir.as_deref() {
+        Some(dir) => Path::new(dir),
+        None => {
+            fail("No directory given, pass one on the command line or set dir in the config file.")
+        }
+    };
+    if !dir.is_dir() {
+        fail(&format!("{} is not a directory", dir.display()));
+    }
+    let base_url = config
+        .base_url
+        .as_deref()
+        .unwrap_or(directory::DEFAULT_BASE_URL);
+    if let Err(e) = cli::is_start_url(base_url.to_owned()) {
+        fail(&e);
+    }
+    let mut base_url = Url::parse(base_url).unwrap();
+    // Treat the base URL as a directory, so files resolve beneath it.
+    if !base_url.path().ends_with('/') {
+        base_url.set_path(&format!("{}/", base_url.path()));
+    }
+
+    let check_external = config.check_external.unwrap_or(false);
+    let fetcher =
+        directory::DirectoryFetcher::new(dir, base_url.clone(), external_fetcher(&config));
+    let files_fetcher = directory::DirectoryFetcher::new(dir, base_url.clone(), None);
+
+    // Start from every page, so pages nothing links to are checked
+    // too.
+    let files = match directory::html_files(dir) {
+        Ok(files) => files,
+        Err(e) => fail(&format!("Could not read {}: {}", dir.display(), e)),
+    };
+    let mut crawler = Crawler::builder().seed(&base_url);
+    for file in &files {
+        if let Some(url) = fetcher.url_of(file) {
+            crawler = crawler.seed(&url);
+        }
+    }
+    // Only crawl the directory, skipping links to other sites unless
+    // we're checking them.
+    config.list_only = Some(!check_external);
+
+    let site = dir.display().to_string();
+    let (report, too_many_broken) = check_and_report(
+        &config,
+        &site,
+        None,
+        false,
+        crawler.fetcher(fetcher),
+        Some(&files_fetcher),
+    );
+    if config.fix.unwrap_or(false) {
+        fix_links(&config, &report, &files_fetcher);
+    }
+    if too_many_broken {
+        process::exit(1);
+    }
+}
+
+/// `--fix`: rewrite links in the files we checked that permanently
+/// redirect to where they end up, links to the site that only redirect
+/// to fix a slash or case, and http:// links that work over HTTPS to
+/// https://. We print each change, and the files can be
+/// reviewed with e.g. git diff.
+fn fix_links(config: &Config, report: &Report, files: &directory::DirectoryFetcher) {
+    let external = match external_fetcher(config) {
+        Some(external) => external,
+        None => fail("--fix needs links to other sites to be checked"),
+    };
+    let timeout = config
+        .timeout
+        .map(Duration::from_secs)
+        .unwrap_or(fetching::DEFAULT_TIMEOUT);
+
+    // Where each link should point instead, as it might be written.
+    let mut replacements = BTreeMap::new();
+    let mut pages = BTreeSet::new();
+    for (url, referrers) in &report.referrers {
+        let parsed = match Url::parse(url) {
+            Ok(parsed) => parsed,
+            Err(_) => continue,
+        };
+        let local_referrers: Vec<_> = referrers
+            .iter()
+            .filter_map(|referrer| Url::parse(referrer).ok())
+            .filter_map(|referrer| files.path_of(&referrer))
+            .collect();
+        if local_referrers.is_empty() {
+            continue;
+        }
+        // Links to files in the directory don't redirect, unless only
+        // the slash or case is off.
+        if files.path_of(&parsed).is_some() && !report.suggested_fixes.contains_key(url) {
+            continue;
+        }
+
+        let moved = report
+            .suggested_fixes
+            .get(url)
+            .or_else(|| report.moved.get(url));
+        let new = match (moved, fixing::https_url(&parsed)) {
+            (Some(target), _) => target.clone(),
+            (None, Some(https)) if external.check(url, &https, timeout).is_accessible() => {
+                https.to_string()
+            }
+            _ => continue,
+        };
+        // Links to a site's root are often written without the slash.
+        if parsed.path() == "/" && parsed.query().is_none() && parsed.fragment().is_none() {
+            replacements.insert(url.trim_end_matches('/').to_owned(), new.clone());
+        }
+        replacements.insert(url.clone(), new);
+        pages.extend(local_referrers);
+    }
+
+    let (mut fixed, mut fixed_pages) = (0, 0);
+    for page in &pages {
+        let source = match fs::read_to_string(page) {
+            Ok(source) => source,
+            Err(e) => fail(&format!("Could not read {}: {}", page.display(), e)),
+        };
+        let (new_source, edits) = fixing::rewrite(&source, &replacements);
+        if edits.is_empty() {
+            continue;
+        }
+        if let Err(e) = fs::write(page, new_source) {
+            fail(&format!("Could not write {}: {}", page.display(), e));
+        }
+        for edit in &edits {
+            println!(
+                "{}:{}: {} -> {}",
+                page.display(),
+                edit.line,
+                edit.old,
+                edit.new
+            );
+        }
+        fixed += edits.len();
+        fixed_pages += 1;
+    }
+    println!("Fixed {} links in {} files", fixed, fixed_pages);
+}
+
+/// The Wayback Machine's copies of the broken links to other sites in
+/// `report`.
+fn archived_copies(
+    config: &Config,
+    report: &Report,
+    start_url: Option<&Url>,
+) -> HashMap<String, String> {
+    let client = match fetching::client(config.user_agent.as_deref()) {
+        Ok(client) => client,
+        Err(e) => fail(&e.to_string()),
+    };
+    let host = start_url.and_then(|url| url.host_str());
+    let urls: Vec<&str> = report
+        .failures
+        .iter()
+        .filter(|state| {
+            state.parsed_url().map_or(false, |url| {
+                matches!(url.scheme(), "http" | "https") && url.host_str() != host
+            })
+        })
+        .map(|state| state.url_str())
+        .collect();
+    wayback::snapshots(&client, &urls)
+}
+
+/// The working HTTPS version of each of `urls`, which work over plain
+/// HTTP.
+fn https_upgrades(config: &Config, urls: &[Url]) -> HashMap<String, String> {
+    let client = match fetching::client(config.user_agent.as_deref()) {
+        Ok(client) => client,
+        Err(e) => fail(&e.to_string()),
+    };
+    let fetcher = fetching::HttpFetcher::new(client, Default::default(), None);
+    upgrading::https_upgrades(
+        &fetcher,
+        urls,
+        config
+            .timeout
+            .map(Duration::from_secs)
+            .unwrap_or(fetching::DEFAULT_TIMEOUT),
+        config.concurrency.unwrap_or(crawling::DEFAULT_CONCURRENCY),
+    )
+}
+
+/// An HTTP fetcher for links to other sites, if we're checking them.
+fn external_fetcher(config: &Config) -> Option<Box<dyn fetching::Fetcher>> {
+    if !config.check_external.unwrap_or(false) {
+        return None;
+    }
+    let client = match fetching::client(config.user_agent.as_deref()) {
+        Ok(client) => client,
+        Err(e) => fail(&e.to_string()),
+    };
+    Some(Box::new(fetching::HttpFetcher::new(
+        client,
+        Default::default(),
+        None,
+    )))
+}
+
+/// Where the link to `state` from its first referrer is written, if
+/// that's a local file: referrers written as `path:line` (by `linkdoc
+/// urls` and `linkdoc comments`), `path#pointer` (by `linkdoc
+/// openapi`), or pages in `files`. Without a line number, it's the
+/// first line the link is on.
+fn source_location(
+    state: &CheckResult,
+    crawler: &Crawler,
+    files: Option<&directory::DirectoryFetcher>,
+) -> Option<(String, usize)> {
+    let referrer = state.referrer();
+    if let Some((path, line)) = referrer.rsplit_once(':') {
+        if let (Ok(line), true) = (line.parse(), Path::new(path).is_file()) {
+            return Some((path.to_owned(), line));
+        }
+    }
+    let page = Url::parse(referrer).ok();
+    let (path, hrefs) = match (page.as_ref(), files) {
+        (Some(page), Some(files)) => (
+            files.path_of(page)?,
+            crawler.hrefs(referrer, state.url_str()),
+        ),
+        _ => {
+            let path = Path::new(referrer.split('#').next().unwrap_or_default());
+            if !path.is_file() {
+                return None;
+            }
+            (path.to_owned(), vec![state.url_str().to_owned()])
+        }
+    };
+    let source = fs::read_to_string(&path).ok()?;
+    let line = source
+        .lines()
+        .position(|line| hrefs.iter().any(|href| line.contains(href.as_str())))
+        .map_or(1, |i| i + 1);
+    Some((path.display().to_string(), line))
+}
+
+/// `linkdoc docs`: check the Markdown files in a repository, the links
+/// between them (including heading anchors) and, unless offline, the
+/// links to other sites.
+fn run_docs(matches: &ArgMatches) {
+    let mut config = load_config(matches);
+    init_output(&config);
+
+    let dir = Path::new(config.dir.as_deref().unwrap_or("."));
+    if !dir.is_dir() {
+        fail(&format!("{} is not a directory", dir.display()));
+    }
+    let files = match directory::markdown_files(dir) {
+        Ok(files) => files,
+        Err(e) => fail(&format!("Could not read {}: {}", dir.display(), e)),
+    };
+    if files.is_empty() {
+        fail(&format!("No Markdown files in {}", dir.display()));
+    }
+
+    let check_external = config.check_external.unwrap_or(true);
+    config.check_external = Some(check_external);
+    let base_url = Url::parse(directory::DEFAULT_BASE_URL).unwrap();
+    let files_fetcher = directory::DirectoryFetcher::new(dir, base_url.clone(), None);
+    let fetcher = directory::DirectoryFetcher::new(dir, base_url, external_fetcher(&config))
+        .check_anchors(true)
+        .directory_links(true);
+    let mut crawler = Crawler::builder();
+    for file in &files {
+        if let Some(url) = fetcher.url_of(file) {
+            crawler = crawler.seed(&url);
+        }
+    }
+    config.list_only = Some(!check_external);
+
+    let site = dir.display().to_string();
+    let (report, too_many_broken) = check_and_report(
+        &config,
+        &site,
+        None,
+        false,
+        crawler.fetcher(fetcher),
+        Some(&files_fetcher),
+    );
+    if config.fix.unwrap_or(false) {
+        fix_links(&config, &report, &files_fetcher);
+    }
+    if too_many_broken {
+        process::exit(1);
+    }
+}
+
+/// `linkdoc comments`: check the URLs in source code comments. Each
+/// URL's referrer is the file and line it's on.
+fn run_comments(matches: &ArgMatches) {
+    let config = load_config(matches);
+    init_output(&config);
+
+    let dir = Path::new(config.dir.as_deref().unwrap_or("."));
+    if !dir.is_dir() {
+        fail(&format!("{} is not a directory", dir.display()));
+    }
+    let mut syntaxes = comments::default_syntaxes();
+    for (extension, markers) in config.comment_syntax.iter().flatten() {
+        match comments::CommentSyntax::parse(markers) {
+            Ok(syntax) => {
+                syntaxes.insert(extension.trim_start_matches('.').to_owned(), syntax);
+            }
+            Err(e) => fail(&format!("Invalid comment syntax for {}: {}", extension, e)),
+        }
+    }
+    let files = match directory::repo_files(dir) {
+        Ok(files) => files,
+        Err(e) => fail(&format!("Could not read {}: {}", dir.display(), e)),
+    };
+
+    let mut crawler = Crawler::builder().check_only(true);
+    for file in &files {
+        let syntax = match file
+            .extension()
+            .and_then(|extension| syntaxes.get(extension.to_string_lossy().as_ref()))
+        {
+            Some(syntax) => syntax,
+            None => continue,
+        };
+        // Skip files that aren't text, whatever their extension.
+        let source = match fs::read_to_string(file) {
+            Ok(source) => source,
+            Err(_) => continue,
+        };
+        for (line, url) in comments::comment_urls(&source, syntax) {
+            crawler = crawler.url(&format!("{}:{}", file.display(), line), &url);
+        }
+    }
+
+    let site = dir.display().to_string();
+    let (_, too_many_broken) = check_and_report(&config, &site, None, false, crawler, None);
+    if too_many_broken {
+        process::exit(1);
+    }
+}
+
+/// `linkdoc mdbook`: check a book's source or HTML output, including
+/// the anchors that links point at.
+fn run_mdbook(matches: &ArgMatches) {
+    let mut config = load_config(matches);
+    init_output(&config);
+
+    let path = match config.dir.as_deref() {
+        Some(dir) => Path::new(dir),
+        None => fail("No book given, pass one on the command line or set dir in the config file."),
+    };
+    let book = match mdbook::Book::find(path) {
+        Ok(book) => book,
+        Err(e) => fail(&e),
+    };
+    let start_files = match book.start_files() {
+        Ok(files) => files,
+        Err(e) => fail(&format!("Could not read {}: {}", book.root().display(), e)),
+    };
+
+    let base_url = Url::parse(directory::DEFAULT_BASE_URL).unwrap();
+    let files_fetcher = directory::DirectoryFetcher::new(book.root(), base_url.clone(), None);
+    let fetcher =
+        directory::DirectoryFetcher::new(book.root(), base_url, external_fetcher(&config))
+            .check_anchors(true);
+    let mut crawler = Crawler::builder();
+    for file in &start_files {
+        if let Some(url) = fetcher.url_of(file) {
+            crawler = crawler.seed(&url);
+        }
+    }
+    if let mdbook::Book::Source(_) = book {
+        crawler = crawler.extractor("text/markdown", mdbook::ChapterExtractor);
+    }
+    config.list_only = Some(!config.check_external.unwrap_or(false));
+
+    let site = path.display().to_string();
+    let crawler = crawler.fetcher(mdbook::BookFetcher::new(fetcher));
+    let (_, too_many_broken) =
+        check_and_report(&config, &site, None, false, crawler, Some(&files_fetcher));
+    if too_many_broken {
+        process::exit(1);
+    }
+}
+
+/// `linkdoc rustdoc`: check the output of `cargo doc`, including the
+/// anchors of items, and links to our own crates on docs.rs.
+fn run_rustdoc(matches: &ArgMatches) {
+    let mut config = load_config(matches);
+    init_output(&config);
+
+    let dir = Path::new(config.dir.as_deref().unwrap_or("target/doc"));
+    let crates = match rustdoc::crates(dir) {
+        Ok(crates) => crates,
+        Err(e) => fail(&format!("Could not read {}: {}", dir.display(), e)),
+    };
+    if crates.is_empty() {
+        fail(&format!(
+            "No crates documented in {}, run cargo doc first",
+            dir.display()
+        ));
+    }
+    let files = match rustdoc::start_files(dir) {
+        Ok(files) => files,
+        Err(e) => fail(&format!("Could not read {}: {}", dir.display(), e)),
+    };
+
+    let base_url = Url::parse(directory::DEFAULT_BASE_URL).unwrap();
+    let files_fetcher = directory::DirectoryFetcher::new(dir, base_url.clone(), None);
+    let fetcher = directory::DirectoryFetcher::new(dir, base_url, external_fetcher(&config));
+    let mut crawler = Crawler::builder().extractor("text/html", rustdoc::RustdocExtractor);
+    for file in &files {
+        if let Some(url) = fetcher.url_of(file) {
+            crawler = crawler.seed(&url);
+        }
+    }
+    config.list_only = Some(!config.check_external.unwrap_or(false));
+
+    let site = dir.display().to_string();
+    let crawler = crawler.fetcher(rustdoc::RustdocFetcher::new(fetcher, crates));
+    let (_, too_many_broken) =
+        check_and_report(&config, &site, None, false, crawler, Some(&files_fetcher));
+    if too_many_broken {
+        process::exit(1);
+    }
+}
+
+/// Set up colored output and logging.
+fn init_output(config: &Config) {
+    let color = use_color(config.color.as_deref().unwrap_or("auto"));
+    colored::control::set_override(color);
+    init_logging(
+        config.log_level.as_deref().unwrap_or("warn"),
+        config.log_json.unwrap_or(false),
+        color,
+    );
+}
+
+/// Severity rules from the config.
+fn severities(config: &Config) -> severity::Severities {
+    let mut severities = severity::Severities::default();
+    for (key, value) in config.severity.iter().flatten() {
+        match severity::Severities::parse_rule(&format!("{}={}", key, value)) {
+            Ok((key, severity)) => severities.set(&key, severity),
+            Err(e) => fail(&e),
+        }
+    }
+    severities
+}
+
+/// The HTTP method to check URLs with.
+fn method(config: &Config) -> fetching::Method {
+    let name = config.method.as_deref().unwrap_or("get");
+    match fetching::Method::from_name(name) {
+        Some(method) => method,
+        None => fail(&format!("Unknown method {}, expected get or head", name)),
+    }
+}
+
+/// Timeouts and retries for URLs matching patterns, from the config.
+fn tuning_rules(config: &Config) -> tuning::Tuning {
+    let mut tuning = tuning::Tuning::default();
+    for (pattern, secs) in config.timeout_for.iter().flatten() {
+        tuning = tuning.timeout(pattern, Duration::from_secs(*secs));
+    }
+    for (pattern, retries) in config.retries_for.iter().flatten() {
+        tuning = tuning.retries(pattern, *retries as usize);
+    }
+    tuning
+}
+
+/// What to do with each URL scheme, from the config.
+fn scheme_rules(config: &Config) -> schemes::Schemes {
+    let mut rules = schemes::Schemes::default();
+    for (key, value) in config.scheme.iter().flatten() {
+        match schemes::Schemes::parse_rule(&format!("{}={}", key, value)) {
+            Ok((scheme, rule)) => rules.set(&scheme, rule),
+            Err(e) => fail(&e),
+        }
+    }
+    rules
+}
+
+/// Crawl settings from the config.
+fn crawl_options(config: &Config) -> crawling::CrawlOptions {
+    let archive =
+        config.warc.as_deref().map(
+            |path| match archiving::WarcWriter::create(Path::new(path)) {
+                Ok(archive) => Arc::new(archive),
+                Err(e) => {
+                    eprintln!("Could not create WARC file {}: {}", path, e);
+                    process::exit(2);
+                }
+            },
+        );
+    let dump_failures = config.dump_failures.as_deref().map(|dir| {
+        match archiving::FailureDump::create(Path::new(dir)) {
+            Ok(dump) => Arc::new(dump),
+            Err(e) => fail(&format!("Could not create directory {}: {}", dir, e)),
+        }
+    });
+
+    let ignore = match config.ignore_file.as_deref() {
+        Some(path) => match ignoring::IgnoreList::load(Path::new(path)) {
+            Ok(ignore) => ignore,
+            Err(e) => fail(&format!("Could not read ignore file {}: {}", path, e)),
+        },
+        None if Path::new(ignoring::DEFAULT_PATH).exists() => {
+            match ignoring::IgnoreList::load(Path::new(ignoring::DEFAULT_PATH)) {
+                Ok(ignore) => ignore,
+                Err(e) => fail(&format!(
+                    "Could not read ignore file {}: {}",
+                    ignoring::DEFAULT_PATH,
+                    e
+                )),
+            }
+        }
+        None => ignoring::IgnoreList::default(),
+    };
+    let mut blocklist = blocklist::Blocklist::default();
+    for path in config.blocklist.as_deref().unwrap_or_default() {
+        match blocklist::Blocklist::load(Path::new(path)) {
+            Ok(list) => blocklist.extend(list),
+            Err(e) => fail(&format!("Could not read blocklist {}: {}", path, e)),
+        }
+    }
+
+    crawling::CrawlOptions {
+        concurrency: config.concurrency.unwrap_or(crawling::DEFAULT_CONCURRENCY),
+        timeout: config
+            .timeout
+            .map(Duration::from_secs)
+            .unwrap_or(fetching::DEFAULT_TIMEOUT),
+        archive,
+        dump_failures,
+        list_only: config.list_only.unwrap_or(false),
+        ignore,
+        retries: config.retries.unwrap_or(0),
+        tuning: tuning_rules(config),
+        method: method(config),
+        max_redirects: config
+            .max_redirects
+            .unwrap_or(fetching::DEFAULT_MAX_REDIRECTS),
+        max_depth: config.max_depth,
+        user_agent: config.user_agent.clone(),
+        ignore_www: config.ignore_www.unwrap_or(false),
+        keep_fragments: config.keep_fragments.unwrap_or(false),
+        soft_404: if config.soft_404.unwrap_or(false) || config.soft_404_phrases.is_some() {
+            Some(soft404::Soft404Detector::new(
+                config.soft_404_phrases.as_deref().unwrap_or_default(),
+            ))
+        } else {
+            None
+        },
+        detect_parked: config.detect_parked.unwrap_or(false),
+        dns_prepass: config.dns_prepass.unwrap_or(false),
+        schemes: scheme_rules(config),
+        check_favicon: config.check_favicon.unwrap_or(false),
+        assertions: match assertions::Assertions::parse(
+            config.assert.as_deref().unwrap_or_default(),
+        ) {
+            Ok(assertions) => assertions,
+            Err(e) => fail(&e),
+        },
+        capture_headers: config.capture_header.clone().unwrap_or_default(),
+        blocklist,
+        query_rules: normalizing::QueryRules::new(
+            config.strip_params.as_deref().unwrap_or_default(),
+            config.ignore_params.as_deref().unwrap_or_default(),
+        ),
+    }
+}
+
+/// How often `--bloom-filter` wrongly skips a URL.
+const BLOOM_FALSE_POSITIVES: f64 = 0.001;
+
+/// Start `crawler` with the settings from the config. `crawled` is
+/// only given when crawling a site.
+fn start(config: &Config, crawler: CrawlerBuilder, crawled: Option<&CrawledSite>) -> Crawler {
+    // HEAD requests don't give us pages to crawl.
+    let crawler = match method(config) {
+        fetching::Method::Head => crawler.check_only(true),
+        fetching::Method::Get => crawler,
+    };
+    let crawler = if config.prioritize.unwrap_or(false) {
+        let sitemap = match crawled {
+            Some(crawled) => crawled.sitemap(config).to_vec(),
+            None => vec![],
+        };
+        let store = store::PriorityStore::new(prioritizing::Heuristics::default().sitemap(sitemap));
+        crawler.store(match config.bloom_filter {
+            Some(expected) => store.bloom(expected, BLOOM_FALSE_POSITIVES),
+            None => store,
+        })
+    } else {
+        match config.bloom_filter {
+            Some(expected) => {
+                crawler.store(store::MemoryStore::bloom(expected, BLOOM_FALSE_POSITIVES))
+            }
+            None => crawler,
+        }
+    };
+    // A Bloom filter means we don't keep the link graph, unless it was
+    // asked for.
+    let crawler = match config.graph {
+        Some(_) => crawler.record_links(true),
+        None => crawler,
+    };
+    match crawler.options(crawl_options(config)).build() {
+        Ok(crawler) => crawler,
+        Err(e) => fail(&e.to_string()),
+    }
+}
+
+/// Start `crawler`, then report the results in every way `config`
+/// asks for. `site` names what we're checking in
+/// reports and notifications. `crawled` is only given when crawling
+/// a site. Unless `quiet`, results are also printed to stdout.
+/// `files` is given when checking local files served by it, so
+/// annotations can point at the line with each broken link.
+///
+/// Returns the report and whether there were too many broken links.
+fn check_and_report(
+    config: &Config,
+    site: &str,
+    crawled: Option<&CrawledSite>,
+    quiet: bool,
+    crawler: CrawlerBuilder,
+    files: Option<&directory::DirectoryFetcher>,
+) -> (Report, bool) {
+    let start_url = crawled.map(|crawled| &crawled.start_url);
+    let max_broken = config.max_broken.unwrap_or(0);
+    let ignore_warnings = config.ignore_warnings.unwrap_or(false);
+    let format_name = config.format.as_deref().unwrap_or("text");
+    let format = match Format::from_name(format_name) {
+        Some(format) => format,
+        None => fail(&format!("Unknown format {}", format_name)),
+    };
+    let stdout_format = if quiet { None } else { Some(format) };
+    let mut outputs = vec![];
+    for spec in config.output.iter().flatten() {
+        match reporting::parse_output(spec) {
+            Ok(output) => outputs.push(output),
+            Err(e) => fail(&e),
+        }
+    }
+
+    let webhook = config.webhook.as_ref().map(|url| notifying::Webhook {
+        url: url.to_owned(),
+        threshold: config.webhook_threshold,
+        full_results: config.webhook_full.unwrap_or(false),
+    });
+
+    let mut chat_notifiers = vec![];
+    for (url, service) in &[
+        (&config.slack_webhook, notifying::ChatService::Slack),
+        (&config.teams_webhook, notifying::ChatService::Teams),
+    ] {
+        if let Some(url) = url {
+            chat_notifiers.push(notifying::ChatNotifier {
+                service: *service,
+                url: url.to_owned(),
+                report_link: config.report_link.clone(),
+            });
+        }
+    }
+
+    let mut report = Report {
+        start_url: site.to_owned(),
+        severities: severities(config),
+        ..Report::default()
+    };
+    // Accessible pages on this site, for the sitemap.
+    let mut pages = vec![];
+    // Working http:// links, to try over HTTPS.
+    let mut insecure_links = vec![];
+    let warn_redirects = config.warn_redirects;
+    // Failures that count towards the exit code.
+    let mut broken_count = 0;
+    let counts = |severity| match severity {
+        Severity::Error => true,
+        Severity::Warning => !ignore_warnings,
+        Severity::Info => false,
+    };
+
+    let baseline = match config.baseline.as_deref() {
+        Some(path) => match baseline::Baseline::load(Path::new(path)) {
+            Ok(baseline) => baseline,
+            Err(e) => fail(&format!("Could not read baseline {}: {}", path, e)),
+        },
+        None => baseline::Baseline::default(),
+    };
+
+    let graph_path = config.graph.as_deref().map(Path::new);
+    if let Some(path) = graph_path {
+        if graphing::GraphFormat::from_path(path).is_none() {
+            fail("The graph file must end with .dot, .gv or .graphml");
+        }
+    }
+
+    // When checking local files, the site is wherever they're served.
+    let scope = start_url
+        .or_else(|| files.map(|files| files.base_url()))
+        .and_then(Scope::new)
+        .map(|scope| scope.ignore_www(config.ignore_www.unwrap_or(false)));
+    // Look for insecure resources, duplicated content and, if we're
+    // checking them, resources with an integrity hash on each page as
+    // it's fetched, since the link graph doesn't say what loads each
+    // link.
+    let insecure = Arc::new(Mutex::new(vec![]));
+    let subresources = Arc::new(Mutex::new(vec![]));
+    let duplicates = Arc::new(duplicates::Duplicates::default());
+    let check_integrity = config.check_integrity.unwrap_or(false);
+    let crawler = {
+        let insecure = insecure.clone();
+        let subresources = subresources.clone();
+        let duplicates = duplicates.clone();
+        crawler.on_page_fetched(move |url, body| {
+            duplicates.record(url.as_str(), body);
+            let found = mixed_content::find(url, body);
+            insecure.lock().unwrap().extend(found);
+            if check_integrity {
+                let found = integrity::find(url, body);
+                subresources.lock().unwrap().extend(found);
+            }
+        })
+    };
+    let mut crawler = start(config, crawler, crawled);
+    let database = config.database.as_deref().map(|path| {
+        match database::ResultsDb::open(Path::new(path), site) {
+            Ok(database) => database,
+            Err(e) => {
+                eprintln!("Could not open database {}: {}", path, e);
+                process::exit(2);
+            }
+        }
+    });
+
+    let dashboard =
+        config
+            .dashboard
+            .as_deref()
+            .map(|addr| match dashboard::Dashboard::start(addr) {
+                Ok(dashboard) => {
+                    dashboard.crawling(site);
+                    dashboard
+                }
+                Err(e) => {
+                    eprintln!("Could not start dashboard on {}: {}", addr, e);
+                    process::exit(2);
+                }
+            });
+
+    // The TUI takes over the terminal, so we don't stream results to
+    // stdout while it's running.
+    let tui = if config.tui.unwrap_or(false) && !quiet {
+        Some(tui::Tui::start(site))
+    } else {
+        None
+    };
+    let stream_format = if tui.is_some() { None } else { stdout_format };
+
+    while let Some(url_state) = crawler.next() {
+        if let Some(ref tui) = tui {
+            tui.record(&url_state, report.severities.of(&url_state));
+        }
+
+        if let Some(ref dashboard) = dashboard {
+            dashboard.record(&url_state, report.severities.of(&url_state));
+        }
+
+        if let Some(ref database) = database {
+            let severity = report.severities.of(&url_state);
+            if let Err(e) = database.record(&url_state, severity) {
+                eprintln!("Could not record result in database: {}", e);
+            }
+        }
+
+        if let Some(ref version) = url_state.http_version {
+            *report.protocols.entry(version.clone()).or_default() += 1;
+        }
+        if !url_state.headers.is_empty() {
+            report
+                .headers
+                .insert(url_state.url.clone(), url_state.headers.clone());
+        }
+        match url_state.parsed_url() {
+            Some(url) if url_state.is_accessible() => {
+                report.success_count += 1;
+                if url_state.permanent_redirect {
+                    if let Some(target) = url_state.redirects.last() {
+                        report.moved.insert(url_state.url.clone(), target.clone());
+                    }
+                }
+                if warn_redirects.map_or(false, |max| url_state.redirects.len() > max) {
+                    report
+                        .long_redirects
+                        .insert(url_state.url.clone(), url_state.redirects.clone());
+                }
+                let internal = scope.as_ref().map_or(false, |scope| scope.contains(&url));
+                let fixed = match url_state.redirects.last() {
+                    Some(target) if internal => fixing::slash_or_case_fix(&url, target),
+                    _ => None,
+                };
+                if let Some(fixed) = fixed {
+                    report.suggested_fixes.insert(url_state.url.clone(), fixed);
+                }
+                if baseline.contains(url.as_str()) {
+                    report.recovered.push(url.to_string());
+                }
+                // Links that already redirect to HTTPS are in `moved`.
+                let ends_insecure = url_state
+                    .redirects
+                    .last()
+                    .map_or(true, |last| last.starts_with("http://"));
+                if url.scheme() == "http" && ends_insecure {
+                    insecure_links.push(url.clone());
+                }
+                if internal {
+                    pages.push((url, url_state.last_modified));
+                }
+            }
+            _ => {
+                let severity = report.severities.of(&url_state);
+                if counts(severity) {
+                    broken_count += 1;
+                }
+                match stream_format {
+                    Some(Format::Text) => println!("{} {}", severity.label(), url_state),
+                    Some(Format::GitHub) => {
+                        let location = source_location(&url_state, &crawler, files);
+                        let location = location.as_ref().map(|(file, line)| (file.as_str(), *line));
+                        println!(
+                            "{}",
+                            reporting::github_annotation(&url_state, severity, location)
+                        )
+                    }
+                    _ => {}
+                }
+                report.failures.push(url_state);
+            }
+        }
+
+        if stream_format == Some(Format::Text) {
+            print!(
+                "{}: {} {}: {}\r",
+                "Succeeded".green(),
+                report.success_count,
+                "Failed".red(),
+                report.failures.len()
+            );
+            stdout().flush().unwrap();
+        }
+    }
+    report.referrers = crawler.referrers();
+    report.depths = crawler.graph().click_depths();
+    if let (Some(prefixes), Some(start_url)) = (config.locales.as_deref(), start_url) {
+        report.locale_drift = locales::compare(&crawler.graph(), start_url, prefixes);
+    }
+    if let (true, Some(scope)) = (config.warn_tracking.unwrap_or(false), scope.as_ref()) {
+        report.tracked_links = normalizing::tracked_links(&crawler.graph(), scope);
+    }
+    if let Some(ref scope) = scope {
+        report.confusable_links = confusables::confusable_links(&crawler.graph(), scope);
+        report.ip_links = ip_addresses::ip_links(&crawler.graph(), scope);
+    }
+    report.mixed_content = std::mem::take(&mut *insecure.lock().unwrap());
+    // Pages are fetched in parallel, so sort them for repeatable reports.
+    report
+        .mixed_content
+        .sort_by(|a, b| (&a.page, &a.url).cmp(&(&b.page, &b.url)));
+    report.duplicate_pages = duplicates.clusters();
+
+    // We can only tell whether a failure is in the baseline once we
+    // know every page linking to it.
+    let failures = std::mem::take(&mut report.failures);
+    let (baselined, failures): (Vec<_>, Vec<_>) = failures
+        .into_iter()
+        .partition(|state| baseline.covers(state.url_str(), &report.referrers_of(state)));
+    report.failures = failures;
+    broken_count -= baselined
+        .iter()
+        .filter(|state| counts(report.severities.of(state)))
+        .count();
+    report.baselined = baselined;
+    for state in &report.failures {
+        if let Some(location) = source_location(state, &crawler, files) {
+            report
+                .locations
+                .insert(state.url_str().to_owned(), location);
+        }
+    }
+    for url in &report.recovered {
+        eprintln!(
+            "{} {} is in the baseline but is no longer broken",
+            "Warning:".yellow(),
+            url
+        );
+    }
+
+    if !report.failures.is_empty() {
+        let sitemap = match crawled {
+            Some(crawled) => crawled.sitemap(config).iter().cloned().collect(),
+            None => HashSet::new(),
+        };
+        ranking::rank(&mut report, &sitemap);
+    }
+    if config.wayback.unwrap_or(false) {
+        report.archived = archived_copies(config, &report, start_url);
+    }
+    if config.suggest_https.unwrap_or(false) {
+        report.upgradable = https_upgrades(config, &insecure_links);
+    }
+    if check_integrity {
+        let mut subresources = std::mem::take(&mut *subresources.lock().unwrap());
+        subresources.sort_by(|a, b| (&a.page, &a.url).cmp(&(&b.page, &b.url)));
+        let client = match fetching::client(config.user_agent.as_deref()) {
+            Ok(client) => client,
+            Err(e) => fail(&e.to_string()),
+        };
+        let timeout = config
+            .timeout
+            .map(Duration::from_secs)
+            .unwrap_or(fetching::DEFAULT_TIMEOUT);
+        report.integrity_mismatches = integrity::verify(&client, timeout, &subresources);
+    }
+    if let Some(tui) = tui {
+        if let Err(e) = tui.finish(report.referrers.clone()) {
+            eprintln!("Could not draw the terminal UI: {}", e);
+        }
+    }
+    if let Some(ref dashboard) = dashboard {
+        dashboard.finish();
+    }
+
+    if let Some(database) = database {
+        if let Err(e) = database.finish(&report) {
+            eprintln!("Could not save results to database: {}", e);
+        }
+    }
+
+    match stdout_format {
+        Some(Format::Text) => println!(),
+        Some(Format::GitHub) | None => {}
+        Some(format) => println!("{}", reporting::render(format, &report)),
+    }
+
+    for (format, path) in outputs {
+        if let Err(e) = fs::write(&path, reporting::render(format, &report)) {
+            eprintln!("Could not write report to {}: {}", path.display(), e);
+            process::exit(2);
+        }
+    }
+
+    if let Some(template_path) = config.template.as_deref() {
+        let rendered = fs::read_to_string(template_path)
+            .map_err(|e| e.to_string())
+            .and_then(|template| reporting::render_template(&template, &report));
+        match rendered {
+            Ok(rendered) => match config.template_output.as_deref() {
+                Some(path) => {
+                    if let Err(e) = fs::write(path, rendered) {
+                        eprintln!("Could not write template output to {}: {}", path, e);
+                        process::exit(2);
+                    }
+                }
+                None => print!("{}", rendered),
+            },
+            Err(e) => {
+                eprintln!("Could not render template {}: {}", template_path, e);
+                process::exit(2);
+            }
+        }
+    }
+
+    if let Some(path) = graph_path {
+        let format = graphing::GraphFormat::from_path(path).unwrap();
+        let graph = crawler.graph();
+        let broken: HashSet<&str> = report.failures.iter().map(|s| s.url_str()).collect();
+        if let Err(e) = fs::write(path, graphing::render(format, &graph, &broken)) {
+            eprintln!("Could not write link graph to {}: {}", path.display(), e);
+            process::exit(2);
+        }
+    }
+
+    if let Some(path) = config.emit_sitemap.as_deref() {
+        if let Err(e) = fs::write(path, sitemap::render(&pages)) {
+            eprintln!("Could not write sitemap to {}: {}", path, e);
+            process::exit(2);
+        }
+    }
+
+    if let Some(path) = config.metrics_file.as_deref() {
+        // Write to a temporary file and rename, so the collector never
+        // sees a partially written file.
+        let tmp_path = format!("{}.tmp", path);
+        let result = fs::write(&tmp_path, crawler.metrics().to_prometheus())
+            .and_then(|_| fs::rename(&tmp_path, path));
+        if let Err(e) = result {
+            eprintln!("Could not write metrics to {}: {}", path, e);
+            process::exit(2);
+        }
+    }
+
+    if let Some(webhook) = webhook {
+        if webhook.should_notify(broken_count) {
+            if let Err(e) = webhook.notify(site, &report, broken_count) {
+                eprintln!("Could not notify webhook {}: {}", webhook.url, e);
+            }
+        }
+    }
+
+    for notifier in chat_notifiers {
+        if let Err(e) = notifier.notify(site, &report) {
+            eprintln!("Could not notify {:?}: {}", notifier.service, e);
+        }
+    }
+
+    if config.triage.unwrap_or(false) && !quiet && !report.failures.is_empty() {
+        if stdin().is_terminal() {
+            let baseline_path = config
+                .baseline
+                .as_deref()
+                .unwrap_or(triaging::DEFAULT_BASELINE_PATH);
+            let fix_list_path = config
+                .fix_list
+                .as_deref()
+                .unwrap_or(triaging::DEFAULT_FIX_LIST_PATH);
+            if let Err(e) =
+                triaging::triage(&report, Path::new(baseline_path), Path::new(fix_list_path))
+            {
+                eprintln!("Could not save triage decisions: {}", e);
+            }
+        } else {
+            eprintln!("Not triaging broken links, stdin isn't a terminal");
+        }
+    }
+
+    (report, broken_count > max_broken)
+}
+
+fn main() {
+    let matches = cli::build(&[]).get_matches();
+    match matches.subcommand() {
+        ("crawl", Some(matches)) => run_crawl(matches),
+        ("list", Some(matches)) => run_list(matches),
+        ("urls", Some(matches)) => run_urls(matches),
+        ("openapi", Some(matches)) => run_openapi(matches),
+        ("check", Some(matches)) => run_check(matches),
+        ("docs", Some(matches)) => run_docs(matches),
+        ("comments", Some(matches)) => run_comments(matches),
+        ("mdbook", Some(matches)) => run_mdbook(matches),
+        ("rustdoc", Some(matches)) => run_rustdoc(matches),
+        ("recheck", Some(matches)) => run_recheck(matches),
+        ("serve", Some(matches)) => run_serve(matches),
+        ("merge", Some(matches)) => run_merge(matches),
+        ("diff", Some(matches)) => run_diff(matches),
+        ("completions", Some(matches)) => run_completions(matches),
+        _ => unreachable!("clap requires a subcommand"),
     }
 }
@@ -0,0 +1,145 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use url::Url;
+
+use crate::directory::{self, DirectoryFetcher};
+use crate::fetching::{CheckResult, FetchError, Fetcher, Page};
+use crate::parsing::{LinkExtractor, MarkdownExtractor};
+
+/// An mdBook, either its source or the HTML that `mdbook build`
+/// wrote.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Book {
+    /// The `src` directory of a book, as set in its book.toml.
+    Source(PathBuf),
+    /// The output directory, usually `book/`.
+    Output(PathBuf),
+}
+
+impl Book {
+    /// Find the book at `path`: either a directory with a book.toml,
+    /// or the output of `mdbook build`.
+    pub fn find(path: &Path) -> Result<Book, String> {
+        let book_toml = path.join("book.toml");
+        if book_toml.is_file() {
+            let src = fs::read_to_string(&book_toml)
+                .map_err(|e| format!("Could not read {}: {}", book_toml.display(), e))?;
+            let config: toml::Value = toml::from_str(&src)
+                .map_err(|e| format!("Could not parse {}: {}", book_toml.display(), e))?;
+            let src_dir = config
+                .get("book")
+                .and_then(|book| book.get("src"))
+                .and_then(|src| src.as_str())
+                .unwrap_or("src");
+            return Ok(Book::Source(path.join(src_dir)));
+        }
+        if path.join("print.html").is_file() {
+            return Ok(Book::Output(path.to_owned()));
+        }
+        // With several renderers, the HTML goes in its own directory.
+        if path.join("html").join("print.html").is_file() {
+            return Ok(Book::Output(path.join("html")));
+        }
+        Err(format!(
+            "{} is not an mdBook: expected a book.toml, or the output of mdbook build",
+            path.display()
+        ))
+    }
+
+    /// The directory we check files in.
+    pub fn root(&self) -> &Path {
+        match self {
+            Book::Source(dir) | Book::Output(dir) => dir,
+        }
+    }
+
+    /// The files to start checking from. SUMMARY.md links to every
+    /// chapter in the source, but the output's sidebar may be drawn
+    /// by JavaScript, so there we start from every page except
+    /// print.html, which repeats all the others, and 404.html, whose
+    /// links are relative to wherever the site is served.
+    pub fn start_files(&self) -> io::Result<Vec<PathBuf>> {
+        match self {
+            Book::Source(dir) => {
+                let summary = dir.join("SUMMARY.md");
+                if !summary.is_file() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("no SUMMARY.md in {}", dir.display()),
+                    ));
+                }
+                Ok(vec![summary])
+            }
+            Book::Output(dir) => Ok(directory::html_files(dir)?
+                .into_iter()
+                .filter(|path| {
+                    let name = path.file_name().and_then(|name| name.to_str());
+                    name != Some("print.html") && name != Some("404.html")
+                })
+                .collect()),
+        }
+    }
+}
+
+/// Links in a chapter's Markdown, plus the files it pulls in with
+/// `{{#include ...}}` and similar preprocessor directives.
+pub struct ChapterExtractor;
+
+/// The directives whose argument is a path relative to the chapter.
+const FILE_DIRECTIVES: &[&str] = &["include", "rustdoc_include", "playground", "playpen"];
+
+impl LinkExtractor for ChapterExtractor {
+    fn extract(&self, source: &str) -> Vec<String> {
+        let mut urls = MarkdownExtractor.extract(source);
+        let mut rest = source;
+        while let Some(start) = rest.find("{{#") {
+            rest = &rest[start + "{{#".len()..];
+            let end = match rest.find("}}") {
+                Some(end) => end,
+                None => break,
+            };
+            let mut words = rest[..end].split_whitespace();
+            if let (Some(directive), Some(path)) = (words.next(), words.next()) {
+                if FILE_DIRECTIVES.contains(&directive) {
+                    // Drop line ranges and anchors, e.g. file.rs:2:10.
+                    let path = path.split(':').next().unwrap_or(path);
+                    urls.push(path.to_owned());
+                }
+            }
+            rest = &rest[end..];
+        }
+        urls
+    }
+}
+
+/// Checks a book's files. The same as a `DirectoryFetcher`, except
+/// that we don't look for links in print.html.
+pub struct BookFetcher {
+    inner: DirectoryFetcher,
+}
+
+impl BookFetcher {
+    pub fn new(inner: DirectoryFetcher) -> Self {
+        BookFetcher { inner }
+    }
+}
+
+impl Fetcher for BookFetcher {
+    fn check(&self, referrer: &str, url: &Url, timeout: Duration) -> CheckResult {
+        self.inner.check(referrer, url, timeout)
+    }
+
+    fn fetch(&self, url: &Url, timeout: Duration) -> Result<Page, FetchError> {
+        let print_path = format!("{}print.html", self.inner.base_url().path());
+        if url.path() == print_path {
+            return Ok(Page::default());
+        }
+        self.inner.fetch(url, timeout)
+    }
+
+    fn checks_fragments(&self) -> bool {
+        self.inner.checks_fragments()
+    }
+}
@@ -0,0 +1,68 @@
+use serde_json::json;
+use std::fs;
+
+/// Read the JSON reports at `paths`, returning one report per site. A
+/// merged report can itself be merged again.
+fn read_sites(paths: &[&str]) -> Result<Vec<serde_json::Value>, String> {
+    let mut sites = vec![];
+    for path in paths {
+        let src = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let report: serde_json::Value =
+            serde_json::from_str(&src).map_err(|e| format!("{}: {}", path, e))?;
+
+        match report.get("sites").and_then(|sites| sites.as_array()) {
+            Some(inner) => sites.extend(inner.iter().cloned()),
+            None => sites.push(report),
+        }
+    }
+    Ok(sites)
+}
+
+fn count(site: &serde_json::Value, key: &str) -> u64 {
+    site.get(key).and_then(|v| v.as_u64()).unwrap_or(0)
+}
+
+/// Combine JSON reports from several runs into one, with a section
+/// per site.
+pub fn merge(paths: &[&str]) -> Result<serde_json::Value, String> {
+    let sites = read_sites(paths)?;
+    let succeeded: u64 = sites.iter().map(|site| count(site, "succeeded")).sum();
+    let failed: u64 = sites.iter().map(|site| count(site, "failed")).sum();
+
+    Ok(json!({
+        "succeeded": succeeded,
+        "failed": failed,
+        "sites": sites,
+    }))
+}
+
+/// A plain text rendering of a merged report.
+pub fn to_text(merged: &serde_json::Value) -> String {
+    let mut s = String::new();
+    let no_sites = vec![];
+    let sites = merged["sites"].as_array().unwrap_or(&no_sites);
+    for site in sites {
+        s.push_str(&format!(
+            "{} (succeeded: {} failed: {})\n",
+            site["start_url"].as_str().unwrap_or("unknown site"),
+            count(site, "succeeded"),
+            count(site, "failed")
+        ));
+        if let Some(failures) = site["failures"].as_array() {
+            for failure in failures {
+                s.push_str(&format!(
+                    "  ✘ {} ({})\n",
+                    failure["url"].as_str().unwrap_or(""),
+                    failure["reason"].as_str().unwrap_or("")
+                ));
+            }
+        }
+        s.push('\n');
+    }
+    s.push_str(&format!(
+        "Total: Succeeded: {} Failed: {}\n",
+        count(merged, "succeeded"),
+        count(merged, "failed")
+    ));
+    s
+}
@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::fetching::CheckResult;
+
+/// Upper bounds, in seconds, of the request latency histogram buckets.
+const LATENCY_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+#[derive(Debug, Default)]
+struct Counters {
+    urls_checked: u64,
+    /// Number of results, keyed by `CheckResult::category`.
+    results: BTreeMap<&'static str, u64>,
+    /// Cumulative counts for each bucket in `LATENCY_BUCKETS`.
+    latency_buckets: Vec<u64>,
+    latency_sum: f64,
+    max_queue_depth: usize,
+}
+
+/// Crawl metrics, shared between the worker threads.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    counters: Mutex<Counters>,
+}
+
+impl Metrics {
+    /// Record the result of checking a single URL, which took
+    /// `elapsed`, with `queue_depth` URLs still waiting to be checked.
+    pub fn record(&self, state: &CheckResult, elapsed: Duration, queue_depth: usize) {
+        let mut counters = self.counters.lock().unwrap();
+        counters.urls_checked += 1;
+        *counters.results.entry(state.category()).or_insert(0) += 1;
+
+        if counters.latency_buckets.is_empty() {
+            counters.latency_buckets = vec![0; LATENCY_BUCKETS.len()];
+        }
+        let secs = elapsed.as_secs_f64();
+        for (i, bound) in LATENCY_BUCKETS.iter().enumerate() {
+            if secs <= *bound {
+                counters.latency_buckets[i] += 1;
+            }
+        }
+        counters.latency_sum += secs;
+
+        if queue_depth > counters.max_queue_depth {
+            counters.max_queue_depth = queue_depth;
+        }
+    }
+
+    /// Render the metrics in the Prometheus text exposition format,
+    /// suitable for the node_exporter textfile collector.
+    pub fn to_prometheus(&self) -> String {
+        let counters = self.counters.lock().unwrap();
+        let mut s = String::new();
+
+        writeln!(s, "# HELP linkdoc_urls_checked_total URLs checked.").unwrap();
+        writeln!(s, "# TYPE linkdoc_urls_checked_total counter").unwrap();
+        writeln!(s, "linkdoc_urls_checked_total {}", counters.urls_checked).unwrap();
+
+        writeln!(s, "# HELP linkdoc_results_total URLs checked, by result.").unwrap();
+        writeln!(s, "# TYPE linkdoc_results_total counter").unwrap();
+        for (category, count) in &counters.results {
+            writeln!(
+                s,
+                "linkdoc_results_total{{result=\"{}\"}} {}",
+                category, count
+            )
+            .unwrap();
+        }
+
+        writeln!(
+            s,
+            "# HELP linkdoc_request_duration_seconds Time taken to check a URL."
+        )
+        .unwrap();
+        writeln!(s, "# TYPE linkdoc_request_duration_seconds histogram").unwrap();
+        for (i, bound) in LATENCY_BUCKETS.iter().enumerate() {
+            let count = counters.latency_buckets.get(i).cloned().unwrap_or(0);
+            writeln!(
+                s,
+                "linkdoc_request_duration_seconds_bucket{{le=\"{}\"}} {}",
+                bound, count
+            )
+            .unwrap();
+        }
+        writeln!(
+            s,
+            "linkdoc_request_duration_seconds_bucket{{le=\"+Inf\"}} {}",
+            counters.urls_checked
+        )
+        .unwrap();
+        writeln!(
+            s,
+            "linkdoc_request_duration_seconds_sum {}",
+            counters.latency_sum
+        )
+        .unwrap();
+        writeln!(
+            s,
+            "linkdoc_request_duration_seconds_count {}",
+            counters.urls_checked
+        )
+        .unwrap();
+
+        writeln!(
+            s,
+            "# HELP linkdoc_queue_depth_max Most URLs waiting to be checked at once."
+        )
+        .unwrap();
+        writeln!(s, "# TYPE linkdoc_queue_depth_max gauge").unwrap();
+        writeln!(s, "linkdoc_queue_depth_max {}", counters.max_queue_depth).unwrap();
+
+        s
+    }
+}
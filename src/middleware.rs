@@ -0,0 +1,88 @@
+#[cfg(feature = "native")]
+use reqwest::{RequestBuilder, Response};
+use std::sync::Arc;
+use url::Url;
+
+use crate::fetching::CheckResult;
+use crate::ignoring::IgnoreList;
+use crate::iri;
+
+/// A stage in the crawl pipeline. Every method has a default that
+/// leaves things as they are, so a middleware only implements the
+/// stages it cares about.
+///
+/// Middleware is called from the worker threads, so must be `Send`
+/// and `Sync`.
+pub trait Middleware: Send + Sync {
+    /// Should we check `url`, linked from `referrer`, at all? A URL is
+    /// only checked if every middleware agrees.
+    fn filter(&self, _referrer: &str, _url: &Url) -> bool {
+        true
+    }
+
+    /// Change a request before it's sent, e.g. to add headers.
+    #[cfg(feature = "native")]
+    fn request(&self, request: RequestBuilder) -> RequestBuilder {
+        request
+    }
+
+    /// Look at the response to a check, and decide what state it's
+    /// in. `state` is what we decided from the status code.
+    #[cfg(feature = "native")]
+    fn response(&self, _response: &Response, state: CheckResult) -> CheckResult {
+        state
+    }
+
+    /// Change the result of a check before it's reported.
+    fn result(&self, state: CheckResult) -> CheckResult {
+        state
+    }
+}
+
+/// Skip every URL in the ignore list. Patterns can be written with
+/// internationalized hosts and paths in Unicode or in ASCII.
+impl Middleware for IgnoreList {
+    fn filter(&self, _referrer: &str, url: &Url) -> bool {
+        !self.is_ignored(url.as_str()) && !self.is_ignored(&iri::display(url.as_str()))
+    }
+}
+
+/// A chain of middleware, run in the order it was added.
+#[derive(Clone, Default)]
+pub struct Pipeline {
+    stages: Vec<Arc<dyn Middleware>>,
+}
+
+impl Pipeline {
+    pub fn push(&mut self, middleware: Arc<dyn Middleware>) {
+        self.stages.push(middleware);
+    }
+
+    pub fn stages(&self) -> &[Arc<dyn Middleware>] {
+        &self.stages
+    }
+
+    pub fn filter(&self, referrer: &str, url: &Url) -> bool {
+        self.stages.iter().all(|stage| stage.filter(referrer, url))
+    }
+
+    #[cfg(feature = "native")]
+    pub fn request(&self, request: RequestBuilder) -> RequestBuilder {
+        self.stages
+            .iter()
+            .fold(request, |request, stage| stage.request(request))
+    }
+
+    #[cfg(feature = "native")]
+    pub fn response(&self, response: &Response, state: CheckResult) -> CheckResult {
+        self.stages
+            .iter()
+            .fold(state, |state, stage| stage.response(response, state))
+    }
+
+    pub fn result(&self, state: CheckResult) -> CheckResult {
+        self.stages
+            .iter()
+            .fold(state, |state, stage| stage.result(state))
+    }
+}
@@ -0,0 +1,59 @@
+use scraper::{Html, Selector};
+use url::Url;
+
+/// The elements that load a sub-resource, and the attribute naming it.
+/// Plain links aren't included, since following one to an `http://`
+/// page is allowed.
+const RESOURCES: &[(&str, &str)] = &[
+    ("img[src]", "src"),
+    ("script[src]", "src"),
+    ("link[rel~=stylesheet][href]", "href"),
+    ("link[rel~=icon][href]", "href"),
+    ("iframe[src]", "src"),
+    ("frame[src]", "src"),
+    ("video[src]", "src"),
+    ("audio[src]", "src"),
+    ("source[src]", "src"),
+    ("track[src]", "src"),
+    ("embed[src]", "src"),
+    ("object[data]", "data"),
+];
+
+/// An `http://` resource loaded by an `https://` page, which browsers
+/// block or show the page as insecure for.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct MixedContent {
+    pub page: String,
+    pub url: String,
+    /// The element loading the resource, e.g. `img`.
+    pub element: String,
+}
+
+/// Every `http://` resource that `body`, the page at `url`, loads. Pages
+/// that aren't served over HTTPS can't have mixed content.
+pub fn find(url: &Url, body: &str) -> Vec<MixedContent> {
+    if url.scheme() != "https" {
+        return vec![];
+    }
+    let document = Html::parse_document(body);
+    let mut found: Vec<MixedContent> = vec![];
+    for (selector, attr) in RESOURCES {
+        let selector = Selector::parse(selector).unwrap();
+        for element in document.select(&selector) {
+            let resource = match element.value().attr(attr).map(|src| url.join(src.trim())) {
+                Some(Ok(resource)) if resource.scheme() == "http" => resource.into_string(),
+                _ => continue,
+            };
+            let seen = found.iter().any(|mixed| mixed.url == resource);
+            if !seen {
+                found.push(MixedContent {
+                    page: url.to_string(),
+                    url: resource,
+                    element: element.value().name().to_owned(),
+                });
+            }
+        }
+    }
+    found
+}
@@ -0,0 +1,342 @@
+use percent_encoding::percent_decode_str;
+use url::Url;
+
+use crate::graphing::LinkGraph;
+use crate::ignoring;
+use crate::scoping::Scope;
+
+/// Query parameters that record where a visitor came from, or who they
+/// are, rather than choosing a page. Links that only differ in these
+/// are always checked once.
+pub const TRACKING_PARAMS: &[&str] = &[
+    "utm_*",
+    "fbclid",
+    "gclid",
+    "dclid",
+    "msclkid",
+    "yclid",
+    "mc_cid",
+    "mc_eid",
+    "_ga",
+    "_gl",
+    "igshid",
+    "PHPSESSID",
+    "JSESSIONID",
+    "ASPSESSIONID*",
+    "CFID",
+    "CFTOKEN",
+];
+
+/// Put `url` in a normal form, so equivalent ways of writing it are
+/// checked and visited once. Percent escapes of unreserved characters
+/// (letters, digits and `-._~`) are decoded, other escapes use
+/// uppercase hex, and a `%` that doesn't start an escape is escaped
+/// itself, since servers often reject it.
+///
+/// The `url` crate already encodes raw spaces and non-ASCII text when
+/// parsing, so `a b` and `a%20b` are equivalent too.
+pub fn normalize(mut url: Url) -> Url {
+    if url.cannot_be_a_base() {
+        return url;
+    }
+    let path = normalize_escapes(url.path());
+    url.set_path(&path);
+    if let Some(query) = url.query().map(normalize_escapes) {
+        url.set_query(Some(&query));
+    }
+    if let Some(fragment) = url.fragment().map(normalize_escapes) {
+        url.set_fragment(Some(&fragment));
+    }
+    url
+}
+
+fn normalize_escapes(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('%') {
+        out.push_str(&rest[..i]);
+        match rest.get(i + 1..i + 3).and_then(hex_byte) {
+            Some(byte) if is_unreserved(byte) => {
+                out.push(byte as char);
+                rest = &rest[i + 3..];
+            }
+            Some(byte) => {
+                out.push_str(&format!("%{:02X}", byte));
+                rest = &rest[i + 3..];
+            }
+            None => {
+                out.push_str("%25");
+                rest = &rest[i + 1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The byte `hex` spells out, if it's two hex digits.
+fn hex_byte(hex: &str) -> Option<u8> {
+    if hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        u8::from_str_radix(hex, 16).ok()
+    } else {
+        None
+    }
+}
+
+/// Characters that mean the same escaped or not, see RFC 3986 §2.3.
+fn is_unreserved(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"-._~".contains(&byte)
+}
+
+/// Rules for query parameters that don't change which page a link
+/// goes to, such as `ref` or `page`. Parameters are matched by name,
+/// where `*` matches any run of characters, so `*` matches them all.
+/// `TRACKING_PARAMS` are always ignored.
+#[derive(Debug, Clone)]
+pub struct QueryRules {
+    /// Removed from links before they're checked.
+    strip: Vec<Vec<char>>,
+    /// Ignored when deciding whether we've already visited a link, so
+    /// links differing only in these are checked once.
+    ignore: Vec<Vec<char>>,
+}
+
+impl QueryRules {
+    pub fn new(strip: &[String], ignore: &[String]) -> QueryRules {
+        let strip = strip.iter().map(|name| name.chars().collect()).collect();
+        let ignore = ignore
+            .iter()
+            .map(|name| name.as_str())
+            .chain(TRACKING_PARAMS.iter().copied())
+            .map(|name| name.chars().collect())
+            .collect();
+        QueryRules { strip, ignore }
+    }
+
+    /// `url` without the parameters we strip.
+    pub fn strip(&self, url: Url) -> Url {
+        remove_params(url, &self.strip)
+    }
+
+    /// The key we deduplicate `url` by: `url` without the parameters
+    /// we ignore.
+    pub fn visit_key(&self, url: &Url) -> String {
+        remove_params(url.clone(), &self.ignore).into_string()
+    }
+}
+
+impl Default for QueryRules {
+    fn default() -> Self {
+        QueryRules::new(&[], &[])
+    }
+}
+
+/// A link between pages on the site with tracking or session
+/// parameters. These skew analytics, and session IDs can leak one
+/// visitor's session to another.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrackedLink {
+    pub page: String,
+    pub url: String,
+    /// The names of the tracking parameters in `url`.
+    pub params: Vec<String>,
+}
+
+/// The names of the parameters in `TRACKING_PARAMS` that `url` has.
+pub fn tracking_params(url: &Url) -> Vec<String> {
+    let patterns: Vec<Vec<char>> = TRACKING_PARAMS
+        .iter()
+        .map(|name| name.chars().collect())
+        .collect();
+    url.query_pairs()
+        .map(|(name, _)| name.into_owned())
+        .filter(|name| {
+            let name: Vec<char> = name.chars().collect();
+            patterns
+                .iter()
+                .any(|pattern| ignoring::matches(pattern, &name))
+        })
+        .collect()
+}
+
+/// Every link in `graph` from a page in `scope` to another page in
+/// `scope` that has tracking parameters, in the order we found them.
+pub fn tracked_links(graph: &LinkGraph, scope: &Scope) -> Vec<TrackedLink> {
+    let nodes = graph.nodes();
+    let mut links: Vec<TrackedLink> = vec![];
+    for edge in graph.edges() {
+        let (page, url) = (&nodes[edge.source].url, &nodes[edge.target].url);
+        let parsed = match (Url::parse(page), Url::parse(url)) {
+            (Ok(page), Ok(url)) if scope.contains(&page) && scope.contains(&url) => url,
+            _ => continue,
+        };
+        let params = tracking_params(&parsed);
+        let seen = links
+            .iter()
+            .any(|link| link.page == *page && link.url == *url);
+        if !params.is_empty() && !seen {
+            links.push(TrackedLink {
+                page: page.clone(),
+                url: url.clone(),
+                params,
+            });
+        }
+    }
+    links
+}
+
+/// `url` without the query parameters whose names match `patterns`.
+/// The parameters we keep are left exactly as written.
+fn remove_params(mut url: Url, patterns: &[Vec<char>]) -> Url {
+    let query = match url.query() {
+        Some(query) if !patterns.is_empty() => query,
+        _ => return url,
+    };
+    let kept = query
+        .split('&')
+        .filter(|param| {
+            let name = param.split('=').next().unwrap_or_default();
+            let name: Vec<char> = percent_decode_str(name)
+                .decode_utf8_lossy()
+                .replace('+', " ")
+                .chars()
+                .collect();
+            !param.is_empty()
+                && !patterns
+                    .iter()
+                    .any(|pattern| ignoring::matches(pattern, &name))
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+    url.set_query(if kept.is_empty() { None } else { Some(&kept) });
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn decodes_unreserved_escapes() {
+        assert_eq!(normalize_escapes("%7Euser/a%2db%5F"), "~user/a-b_");
+    }
+
+    #[test]
+    fn uppercases_other_escapes() {
+        assert_eq!(normalize_escapes("a%2fb%3a"), "a%2Fb%3A");
+        assert_eq!(normalize_escapes("%C3%A9"), "%C3%A9");
+    }
+
+    #[test]
+    fn escapes_stray_percent_signs() {
+        assert_eq!(normalize_escapes("100%"), "100%25");
+        assert_eq!(normalize_escapes("%zz"), "%25zz");
+        assert_eq!(normalize_escapes("%4"), "%254");
+        assert_eq!(normalize_escapes("50%%41"), "50%25A");
+    }
+
+    #[test]
+    fn normalizes_every_part() {
+        assert_eq!(
+            normalize(url("http://example.com/a%2db?q=%7e#%41")).as_str(),
+            "http://example.com/a-b?q=~#A"
+        );
+        assert_eq!(
+            normalize(url("http://example.com/100%")).as_str(),
+            "http://example.com/100%25"
+        );
+    }
+
+    #[test]
+    fn leaves_urls_without_paths_alone() {
+        assert_eq!(
+            normalize(url("mailto:a%7eb@example.com")).as_str(),
+            "mailto:a%7eb@example.com"
+        );
+    }
+
+    #[test]
+    fn strips_matching_params() {
+        let rules = QueryRules::new(&["ref".to_owned(), "filter_*".to_owned()], &[]);
+        assert_eq!(
+            rules
+                .strip(url(
+                    "http://example.com/?ref=x&filter_a=1&filter_b=2&page=3"
+                ))
+                .as_str(),
+            "http://example.com/?page=3"
+        );
+        assert_eq!(
+            rules.strip(url("http://example.com/p?ref=x")).as_str(),
+            "http://example.com/p"
+        );
+    }
+
+    #[test]
+    fn strip_keeps_tracking_params() {
+        let rules = QueryRules::default();
+        assert_eq!(
+            rules
+                .strip(url("http://example.com/?utm_source=x"))
+                .as_str(),
+            "http://example.com/?utm_source=x"
+        );
+    }
+
+    #[test]
+    fn visit_key_ignores_tracking_params() {
+        let rules = QueryRules::default();
+        assert_eq!(
+            rules.visit_key(&url("http://example.com/?utm_source=x&id=1&gclid=2")),
+            "http://example.com/?id=1"
+        );
+        assert_eq!(
+            rules.visit_key(&url("http://example.com/?fbclid=abc")),
+            "http://example.com/"
+        );
+        assert_eq!(
+            rules.visit_key(&url("http://example.com/?ASPSESSIONIDQQ=1&a=2")),
+            "http://example.com/?a=2"
+        );
+    }
+
+    #[test]
+    fn visit_key_ignores_matching_params() {
+        let rules = QueryRules::new(&[], &["sort*".to_owned()]);
+        let listing = url("http://example.com/list?sort_by=name&page=2");
+        assert_eq!(rules.visit_key(&listing), "http://example.com/list?page=2");
+        assert_eq!(rules.strip(listing.clone()), listing);
+    }
+
+    #[test]
+    fn visit_key_matches_decoded_names() {
+        let rules = QueryRules::default();
+        assert_eq!(
+            rules.visit_key(&url("http://example.com/?utm%5Fsource=x&b=1")),
+            "http://example.com/?b=1"
+        );
+    }
+
+    #[test]
+    fn keeps_other_params_as_written() {
+        let rules = QueryRules::default();
+        assert_eq!(
+            rules.visit_key(&url("http://example.com/?a=%41&utm_medium=x&b")),
+            "http://example.com/?a=%41&b"
+        );
+    }
+
+    #[test]
+    fn finds_tracking_params() {
+        assert_eq!(
+            tracking_params(&url("http://example.com/?utm_source=a&id=1&gclid=2")),
+            vec!["utm_source", "gclid"]
+        );
+        assert!(tracking_params(&url("http://example.com/?id=1")).is_empty());
+    }
+}
@@ -0,0 +1,149 @@
+use reqwest::header::CONTENT_TYPE;
+use serde_json::json;
+use std::collections::HashMap;
+
+use crate::reporting::{self, Report};
+
+/// Where and when to send a notification about a finished crawl.
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    pub url: String,
+    /// Only notify if there are more than this many broken links.
+    pub threshold: Option<usize>,
+    /// Include every failure in the payload, not just a summary.
+    pub full_results: bool,
+}
+
+impl Webhook {
+    pub fn should_notify(&self, broken_count: usize) -> bool {
+        match self.threshold {
+            Some(threshold) => broken_count > threshold,
+            None => true,
+        }
+    }
+
+    /// The JSON body we POST to the webhook.
+    fn payload(&self, start_url: &str, report: &Report, broken_count: usize) -> serde_json::Value {
+        let mut payload = json!({
+            "event": "crawl_finished",
+            "start_url": start_url,
+            "succeeded": report.success_count,
+            "failed": report.failures.len(),
+            "broken": broken_count,
+        });
+        if self.full_results {
+            let failures: Vec<_> = report
+                .failures
+                .iter()
+                .map(|state| reporting::failure_json(report, state))
+                .collect();
+            payload["failures"] = json!(failures);
+        }
+        payload
+    }
+
+    /// POST a summary of the crawl to the webhook.
+    pub fn notify(
+        &self,
+        start_url: &str,
+        report: &Report,
+        broken_count: usize,
+    ) -> Result<(), String> {
+        post_json(&self.url, &self.payload(start_url, report, broken_count))
+    }
+}
+
+fn post_json(url: &str, value: &serde_json::Value) -> Result<(), String> {
+    let response = reqwest::Client::new()
+        .post(url)
+        .header(CONTENT_TYPE, "application/json")
+        .body(value.to_string())
+        .send()
+        .map_err(|e| e.to_string())?;
+
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("webhook returned {}", response.status()))
+    }
+}
+
+/// How many broken URLs we list in chat notifications.
+const TOP_OFFENDERS: usize = 5;
+
+/// The broken URLs linked from the most pages, most common first.
+fn top_offenders(report: &Report) -> Vec<(&str, usize)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for state in &report.failures {
+        *counts.entry(state.url_str()).or_insert(0) += report.referrers_of(state).len();
+    }
+
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|(url1, count1), (url2, count2)| count2.cmp(count1).then(url1.cmp(url2)));
+    counts.truncate(TOP_OFFENDERS);
+    counts
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChatService {
+    Slack,
+    Teams,
+}
+
+/// Posts a human-readable summary to a Slack or Microsoft Teams
+/// incoming webhook.
+#[derive(Debug, Clone)]
+pub struct ChatNotifier {
+    pub service: ChatService,
+    pub url: String,
+    /// Where the full report can be viewed, if it's published somewhere.
+    pub report_link: Option<String>,
+}
+
+impl ChatNotifier {
+    /// A markdown summary of the crawl. Slack and Teams disagree on
+    /// link syntax, so we take that from the caller.
+    fn summary(&self, site: &str, report: &Report, link: fn(&str, &str) -> String) -> String {
+        let mut lines = vec![format!(
+            "*Link Doctor*: {} broken link(s) found on {} ({} OK)",
+            report.failures.len(),
+            site,
+            report.success_count
+        )];
+
+        let offenders = top_offenders(report);
+        if !offenders.is_empty() {
+            lines.push("Top offenders:".to_owned());
+            for (url, count) in offenders {
+                lines.push(format!("• {} (linked from {} page(s))", url, count));
+            }
+        }
+
+        if let Some(ref report_link) = self.report_link {
+            lines.push(link(report_link, "Full report"));
+        }
+
+        lines.join("\n")
+    }
+
+    fn payload(&self, site: &str, report: &Report) -> serde_json::Value {
+        match self.service {
+            ChatService::Slack => json!({
+                "text": self.summary(site, report, |url, text| format!("<{}|{}>", url, text)),
+            }),
+            ChatService::Teams => json!({
+                "@type": "MessageCard",
+                "@context": "https://schema.org/extensions",
+                "summary": format!("Link Doctor: {} broken link(s) on {}", report.failures.len(), site),
+                // Teams needs blank lines between paragraphs.
+                "text": self
+                    .summary(site, report, |url, text| format!("[{}]({})", text, url))
+                    .replace('\n', "\n\n"),
+            }),
+        }
+    }
+
+    pub fn notify(&self, site: &str, report: &Report) -> Result<(), String> {
+        post_json(&self.url, &self.payload(site, report))
+    }
+}
@@ -0,0 +1,114 @@
+use serde_json::{Map, Value};
+use url::Url;
+
+/// Parse an OpenAPI or Swagger document, in YAML or JSON.
+pub fn parse(src: &str) -> Result<Value, String> {
+    // JSON is YAML too, near enough.
+    serde_yaml::from_str(src).map_err(|e| e.to_string())
+}
+
+/// Escape `key` for use in a JSON pointer.
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// A server URL with its `{variables}` replaced by their defaults.
+fn server_url(server: &Map<String, Value>) -> Option<String> {
+    let mut url = server.get("url")?.as_str()?.to_owned();
+    if let Some(variables) = server.get("variables").and_then(|v| v.as_object()) {
+        for (name, variable) in variables {
+            if let Some(default) = variable.get("default").and_then(|d| d.as_str()) {
+                url = url.replace(&format!("{{{}}}", name), default);
+            }
+        }
+    }
+    Some(url)
+}
+
+/// The URL of the API in a Swagger 2.0 document, from its `host`,
+/// `basePath` and `schemes`.
+fn swagger_url(spec: &Value) -> Option<String> {
+    let host = spec.get("host")?.as_str()?;
+    let scheme = spec
+        .get("schemes")
+        .and_then(|schemes| schemes.get(0))
+        .and_then(|scheme| scheme.as_str())
+        .unwrap_or("https");
+    let base_path = spec.get("basePath").and_then(|p| p.as_str()).unwrap_or("/");
+    Some(format!("{}://{}{}", scheme, host, base_path))
+}
+
+/// The URLs in a spec that should be reachable: `externalDocs`,
+/// `servers`, the license, contact and terms of service in `info`, and
+/// `$ref`s to other documents. Each comes with a JSON pointer to where
+/// it is in the spec, e.g. `/info/license/url`.
+///
+/// Relative URLs are resolved against `base`, where the spec came from,
+/// if it has a URL. Otherwise they're skipped, since they name files
+/// next to the spec.
+pub fn urls(spec: &Value, base: Option<&Url>) -> Vec<(String, String)> {
+    let mut found = vec![];
+    for pointer in &[
+        "/info/termsOfService",
+        "/info/license/url",
+        "/info/contact/url",
+    ] {
+        if let Some(url) = spec.pointer(pointer).and_then(|url| url.as_str()) {
+            found.push((pointer.to_string(), url.to_owned()));
+        }
+    }
+    if let Some(url) = swagger_url(spec) {
+        found.push(("/host".to_owned(), url));
+    }
+    walk(spec, "", &mut found);
+
+    found
+        .into_iter()
+        .filter_map(|(pointer, url)| {
+            if Url::parse(&url).is_ok() {
+                return Some((pointer, url));
+            }
+            let resolved = base?.join(&url).ok()?;
+            Some((pointer, resolved.into()))
+        })
+        .collect()
+}
+
+/// Collect the `externalDocs`, `servers` and `$ref` URLs anywhere
+/// under `value`, which is at `pointer`.
+fn walk(value: &Value, pointer: &str, found: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(object) => {
+            for (key, child) in object {
+                let child_pointer = format!("{}/{}", pointer, escape(key));
+                match (key.as_str(), child) {
+                    ("externalDocs", Value::Object(docs)) => {
+                        if let Some(url) = docs.get("url").and_then(|url| url.as_str()) {
+                            found.push((format!("{}/url", child_pointer), url.to_owned()));
+                        }
+                    }
+                    ("servers", Value::Array(servers)) => {
+                        for (i, server) in servers.iter().enumerate() {
+                            if let Some(url) = server.as_object().and_then(server_url) {
+                                found.push((format!("{}/{}/url", child_pointer, i), url));
+                            }
+                        }
+                    }
+                    // Refs within the document start with #.
+                    ("$ref", Value::String(target)) if !target.starts_with('#') => {
+                        let document = target.split('#').next().unwrap_or(target);
+                        found.push((child_pointer.clone(), document.to_owned()));
+                    }
+                    _ => {}
+                }
+                walk(child, &child_pointer, found);
+            }
+        }
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                walk(item, &format!("{}/{}", pointer, i), found);
+            }
+        }
+        _ => {}
+    }
+}
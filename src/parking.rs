@@ -0,0 +1,75 @@
+use url::Url;
+
+use crate::fetching::Page;
+
+/// Hosts of domain marketplaces and parking services. A link that
+/// redirects to one of these has lapsed.
+pub const PARKING_HOSTS: &[&str] = &[
+    "sedo.com",
+    "sedoparking.com",
+    "dan.com",
+    "afternic.com",
+    "hugedomains.com",
+    "bodis.com",
+    "parkingcrew.net",
+    "above.com",
+    "undeveloped.com",
+    "domainmarket.com",
+    "buydomains.com",
+    "parklogic.com",
+    "smartname.com",
+    "parkingpage.namecheap.com",
+];
+
+/// Text found on parking pages, matched ignoring case: the registrars'
+/// templates, and the scripts of ad landers.
+pub const PARKING_MARKERS: &[&str] = &[
+    "this domain is for sale",
+    "this domain may be for sale",
+    "the domain name is for sale",
+    "buy this domain",
+    "this domain is parked",
+    "this web page is parked",
+    "parked free, courtesy of",
+    "sedoparking.com",
+    "parkingcrew.net",
+    "bodis.com",
+    "above.com/marketplace",
+    "dan.com/buy-domain",
+    "hugedomains.com",
+    "/js/parking.",
+    "window.park",
+];
+
+/// Is `host` one of `PARKING_HOSTS`, or a subdomain of one?
+fn is_parking_host(host: &str) -> bool {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    PARKING_HOSTS.iter().any(|parking| {
+        host == *parking
+            || host
+                .strip_suffix(parking)
+                .map_or(false, |sub| sub.ends_with('.'))
+    })
+}
+
+/// Why a link that was redirected through `redirects` goes to a parked
+/// domain, if it does.
+pub fn parked_redirect(redirects: &[String]) -> Option<String> {
+    redirects.iter().find_map(|redirect| {
+        let host = Url::parse(redirect).ok()?.host_str()?.to_owned();
+        if is_parking_host(&host) {
+            Some(format!("redirects to {}, a domain marketplace", host))
+        } else {
+            None
+        }
+    })
+}
+
+/// Why `page` looks like a parking page, if it does.
+pub fn parked_page(page: &Page) -> Option<String> {
+    let body = page.body.to_lowercase();
+    PARKING_MARKERS
+        .iter()
+        .find(|marker| body.contains(*marker))
+        .map(|marker| format!("the page looks parked (\"{}\")", marker))
+}
@@ -1,4 +1,239 @@
 use scraper::{Html, Selector};
+use std::sync::Arc;
+use tracing::trace;
+
+/// Finds the links in a document of some content type. We deliberately
+/// return strings because we're also interested in malformed URLs.
+pub trait LinkExtractor: Send + Sync {
+    fn extract(&self, source: &str) -> Vec<String>;
+}
+
+/// Links, images and stylesheets in HTML.
+pub struct HtmlExtractor;
+
+impl LinkExtractor for HtmlExtractor {
+    fn extract(&self, source: &str) -> Vec<String> {
+        get_urls(source)
+    }
+}
+
+/// Inline links, autolinks and link definitions in Markdown.
+pub struct MarkdownExtractor;
+
+impl LinkExtractor for MarkdownExtractor {
+    fn extract(&self, source: &str) -> Vec<String> {
+        let mut urls = vec![];
+        for line in source.lines() {
+            // [text](url "title") and ![alt](url)
+            let mut rest = line;
+            while let Some(start) = rest.find("](") {
+                rest = &rest[start + "](".len()..];
+                let end = rest.find(')').unwrap_or(rest.len());
+                if let Some(url) = rest[..end].split_whitespace().next() {
+                    urls.push(url.trim_matches(|c| c == '<' || c == '>').to_owned());
+                }
+                rest = &rest[end..];
+            }
+
+            // <http://example.com>
+            let mut rest = line;
+            while let Some(start) = rest.find('<') {
+                rest = &rest[start + 1..];
+                let end = match rest.find('>') {
+                    Some(end) => end,
+                    None => break,
+                };
+                let url = &rest[..end];
+                if url.starts_with("http://") || url.starts_with("https://") {
+                    urls.push(url.to_owned());
+                }
+                rest = &rest[end..];
+            }
+
+            // [label]: url
+            let trimmed = line.trim_start();
+            if trimmed.starts_with('[') {
+                if let Some(end) = trimmed.find("]:") {
+                    if let Some(url) = trimmed[end + "]:".len()..].split_whitespace().next() {
+                        urls.push(url.to_owned());
+                    }
+                }
+            }
+        }
+        urls
+    }
+}
+
+/// `url(...)` references and `@import`s in CSS.
+pub struct CssExtractor;
+
+impl LinkExtractor for CssExtractor {
+    fn extract(&self, source: &str) -> Vec<String> {
+        let quotes = |c: char| c == '"' || c == '\'';
+        let mut urls = vec![];
+
+        let mut rest = source;
+        while let Some(start) = rest.find("url(") {
+            rest = &rest[start + "url(".len()..];
+            let end = match rest.find(')') {
+                Some(end) => end,
+                None => break,
+            };
+            let url = rest[..end].trim().trim_matches(quotes);
+            // Inline data isn't worth checking.
+            if !url.is_empty() && !url.starts_with("data:") {
+                urls.push(url.to_owned());
+            }
+            rest = &rest[end..];
+        }
+
+        // @import "foo.css"; (@import url(...) is handled above.)
+        let mut rest = source;
+        while let Some(start) = rest.find("@import") {
+            rest = rest[start + "@import".len()..].trim_start();
+            if rest.starts_with(quotes) {
+                if let Some(end) = rest[1..].find(quotes) {
+                    urls.push(rest[1..end + 1].to_owned());
+                }
+            }
+        }
+        urls
+    }
+}
+
+/// Link annotations in PDFs. We only find links stored as plain text,
+/// not those inside compressed object streams.
+pub struct PdfExtractor;
+
+impl LinkExtractor for PdfExtractor {
+    fn extract(&self, source: &str) -> Vec<String> {
+        let mut urls = vec![];
+        let mut rest = source;
+        while let Some(start) = rest.find("/URI") {
+            rest = rest[start + "/URI".len()..].trim_start();
+            if !rest.starts_with('(') {
+                continue;
+            }
+            if let Some(end) = rest.find(')') {
+                urls.push(rest[1..end].replace("\\(", "(").replace("\\)", ")"));
+                rest = &rest[end..];
+            }
+        }
+        urls
+    }
+}
+
+/// The link extractor to use for each content type.
+#[derive(Clone)]
+pub struct Extractors {
+    /// (content type, extractor) pairs, where later entries take
+    /// priority.
+    table: Vec<(String, Arc<dyn LinkExtractor>)>,
+}
+
+impl Default for Extractors {
+    /// The built-in extractors for HTML, Markdown, CSS and PDF.
+    fn default() -> Self {
+        let mut extractors = Extractors { table: vec![] };
+        extractors.register("text/html", Arc::new(HtmlExtractor));
+        extractors.register("application/xhtml+xml", Arc::new(HtmlExtractor));
+        extractors.register("text/markdown", Arc::new(MarkdownExtractor));
+        extractors.register("text/x-markdown", Arc::new(MarkdownExtractor));
+        extractors.register("text/css", Arc::new(CssExtractor));
+        extractors.register("application/pdf", Arc::new(PdfExtractor));
+        extractors
+    }
+}
+
+impl Extractors {
+    /// Use `extractor` for documents of `content_type`, replacing any
+    /// extractor already registered for it.
+    pub fn register(&mut self, content_type: &str, extractor: Arc<dyn LinkExtractor>) {
+        self.table
+            .push((content_type.to_ascii_lowercase(), extractor));
+    }
+
+    /// Find the links in `source`, cleaned up with `clean_link`.
+    /// Documents without a content type are treated as HTML, and
+    /// documents we have no extractor for have no links.
+    pub fn extract(&self, content_type: Option<&str>, source: &str) -> Vec<String> {
+        // Ignore parameters, e.g. "text/html; charset=utf-8".
+        let content_type = content_type
+            .and_then(|content_type| content_type.split(';').next())
+            .map(|content_type| content_type.trim().to_ascii_lowercase())
+            .unwrap_or_else(|| "text/html".to_owned());
+        match self.table.iter().rev().find(|(t, _)| *t == content_type) {
+            Some((_, extractor)) => extractor
+                .extract(source)
+                .iter()
+                .map(|link| clean_link(link))
+                .collect(),
+            None => {
+                trace!(content_type = %content_type, "no link extractor");
+                vec![]
+            }
+        }
+    }
+}
+
+/// Undo the mistakes templates make when writing links: surrounding
+/// whitespace, HTML entities that were escaped twice or never decoded,
+/// e.g. `&amp;`, and stray quotes around the URL.
+pub fn clean_link(link: &str) -> String {
+    let quotes = |c: char| {
+        matches!(
+            c,
+            '"' | '\'' | '\u{201C}' | '\u{201D}' | '\u{2018}' | '\u{2019}'
+        )
+    };
+    let link = decode_entities(link.trim());
+    link.trim().trim_matches(quotes).trim().to_owned()
+}
+
+/// Decode the HTML character references in `s`, such as `&amp;` and
+/// `&#39;`. Only the named references that turn up in URLs are known,
+/// and others are left as written.
+fn decode_entities(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest.find(';').and_then(|end| {
+            let name = &rest[1..end];
+            let c = match name {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some('\u{a0}'),
+                _ => match name.strip_prefix('#') {
+                    Some(hex) if hex.starts_with('x') || hex.starts_with('X') => {
+                        u32::from_str_radix(&hex[1..], 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                    }
+                    Some(decimal) => decimal.parse().ok().and_then(char::from_u32),
+                    None => None,
+                },
+            };
+            c.map(|c| (c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
 
 pub fn get_urls(source_str: &str) -> Vec<String> {
     let document = Html::parse_document(source_str);
@@ -29,5 +264,210 @@ pub fn get_urls(source_str: &str) -> Vec<String> {
         }
     }
 
+    trace!(count = urls.len(), "extracted URLs");
+    urls
+}
+
+/// Does an HTML document declare an icon with `<link rel="icon">`? If
+/// not, browsers request `/favicon.ico` instead.
+pub fn declares_icon(source_str: &str) -> bool {
+    let document = Html::parse_document(source_str);
+    let selector = Selector::parse("link[rel][href]").unwrap();
+    document.select(&selector).any(|node| {
+        node.value()
+            .attr("rel")
+            .unwrap_or_default()
+            .split_whitespace()
+            .any(|rel| rel.eq_ignore_ascii_case("icon"))
+    })
+}
+
+/// The anchors in an HTML document that a URL fragment can point at:
+/// every `id`, and the `name` of every `<a>`.
+pub fn get_ids(source_str: &str) -> Vec<String> {
+    let document = Html::parse_document(source_str);
+    let mut ids = vec![];
+
+    let selector = Selector::parse("[id]").unwrap();
+    for node in document.select(&selector) {
+        if let Some(id) = node.value().attr("id") {
+            ids.push(id.to_owned());
+        }
+    }
+    let selector = Selector::parse("a[name]").unwrap();
+    for node in document.select(&selector) {
+        if let Some(name) = node.value().attr("name") {
+            ids.push(name.to_owned());
+        }
+    }
+    ids
+}
+
+/// Bare http and https URLs in plain text, such as a code comment.
+/// Trailing punctuation isn't part of a URL, and nor is a closing
+/// bracket, unless the URL has the opening one.
+pub fn find_urls(text: &str) -> Vec<String> {
+    let mut urls = vec![];
+    let mut rest = text;
+    while let Some(start) = ["http://", "https://"]
+        .iter()
+        .filter_map(|scheme| rest.find(scheme))
+        .min()
+    {
+        rest = &rest[start..];
+        let end = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"' | '\'' | '`'))
+            .unwrap_or(rest.len());
+        let mut url = &rest[..end];
+        loop {
+            let trimmed =
+                url.trim_end_matches(|c| matches!(c, '.' | ',' | ';' | ':' | '!' | '?' | '*'));
+            let trimmed = match trimmed.chars().last() {
+                Some(close) if matches!(close, ')' | ']' | '}') => {
+                    let open = match close {
+                        ')' => '(',
+                        ']' => '[',
+                        _ => '{',
+                    };
+                    if trimmed.matches(open).count() < trimmed.matches(close).count() {
+                        &trimmed[..trimmed.len() - 1]
+                    } else {
+                        trimmed
+                    }
+                }
+                _ => trimmed,
+            };
+            if trimmed.len() == url.len() {
+                break;
+            }
+            url = trimmed;
+        }
+        if !url.ends_with("//") {
+            urls.push(url.to_owned());
+        }
+        rest = &rest[end..];
+    }
+    urls
+}
+
+/// The URL a page redirects to with `<meta http-equiv="refresh">`, if
+/// any.
+pub fn get_refresh_url(source_str: &str) -> Option<String> {
+    let document = Html::parse_document(source_str);
+    let selector = Selector::parse("meta[http-equiv]").unwrap();
+    let content = document
+        .select(&selector)
+        .find(|node| {
+            node.value()
+                .attr("http-equiv")
+                .map_or(false, |equiv| equiv.eq_ignore_ascii_case("refresh"))
+        })?
+        .value()
+        .attr("content")?;
+    // The content is e.g. "0;URL=target.html", or "0; url='target.html'".
+    let start = content.to_ascii_lowercase().find("url=")? + "url=".len();
+    let url = content[start..]
+        .trim()
+        .trim_matches(|c| c == '"' || c == '\'');
+    Some(url.to_owned())
+}
+
+/// Turn a heading into an anchor the way GitHub and mdBook do:
+/// lowercase, spaces become hyphens, and punctuation other than `-`
+/// and `_` is dropped.
+pub fn heading_slug(heading: &str) -> String {
+    heading
+        .chars()
+        .filter_map(|c| {
+            if c.is_alphanumeric() || c == '_' || c == '-' {
+                Some(c.to_ascii_lowercase())
+            } else if c.is_whitespace() {
+                Some('-')
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// The text of a Markdown heading as it's rendered, without inline
+/// code, emphasis, link targets or HTML tags.
+fn heading_text(heading: &str) -> String {
+    let mut text = String::new();
+    let mut rest = heading;
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        match c {
+            '`' | '*' => {}
+            '<' => match rest.find('>') {
+                Some(end) => rest = &rest[end + 1..],
+                None => text.push(c),
+            },
+            // Skip the target of [text](url).
+            ']' if rest.starts_with('(') => match rest.find(')') {
+                Some(end) => rest = &rest[end + 1..],
+                None => text.push(c),
+            },
+            '[' | ']' => {}
+            _ => text.push(c),
+        }
+    }
+    text
+}
+
+/// The anchors of the headings in a Markdown document. Repeated
+/// headings get `-1`, `-2` and so on, and `{#custom-id}` overrides the
+/// anchor.
+pub fn heading_ids(source: &str) -> Vec<String> {
+    let mut ids: Vec<String> = vec![];
+    let mut in_code = false;
+    for line in source.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code || !trimmed.starts_with('#') {
+            continue;
+        }
+        let heading = trimmed.trim_start_matches('#');
+        let level = trimmed.len() - heading.len();
+        if level > 6 || !(heading.is_empty() || heading.starts_with(' ')) {
+            continue;
+        }
+        let heading = heading.trim().trim_end_matches('#').trim();
+
+        if let Some(start) = heading.rfind("{#") {
+            if heading.ends_with('}') {
+                ids.push(heading[start + 2..heading.len() - 1].to_owned());
+                continue;
+            }
+        }
+        let slug = heading_slug(&heading_text(heading));
+        let mut id = slug.clone();
+        let mut n = 1;
+        while ids.contains(&id) {
+            id = format!("{}-{}", slug, n);
+            n += 1;
+        }
+        ids.push(id);
+    }
+    ids
+}
+
+/// Extract the page URLs listed in a sitemap.xml.
+pub fn get_sitemap_urls(source_str: &str) -> Vec<String> {
+    let mut urls = vec![];
+    let mut rest = source_str;
+    while let Some(start) = rest.find("<loc>") {
+        rest = &rest[start + "<loc>".len()..];
+        match rest.find("</loc>") {
+            Some(end) => {
+                urls.push(rest[..end].trim().replace("&amp;", "&"));
+                rest = &rest[end..];
+            }
+            None => break,
+        }
+    }
     urls
 }
@@ -0,0 +1,260 @@
+use reqwest::header::USER_AGENT;
+use reqwest::Client;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::debug;
+use url::{Position, Url};
+
+use crate::fetching::DEFAULT_USER_AGENT;
+use crate::ignoring;
+
+/// Whether we may request a URL yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permit {
+    Now,
+    /// Ask again after this long.
+    After(Duration),
+    /// Don't request the URL at all.
+    Never,
+}
+
+/// Decides how quickly we may request URLs from each host. Workers ask
+/// before every request, and a policy should assume that a `Now`
+/// answer means the request is made.
+pub trait Politeness: Send + Sync {
+    fn permit(&self, url: &Url, now: Instant) -> Permit;
+}
+
+/// The host we throttle `url` by.
+fn host_of(url: &Url) -> String {
+    url.host_str().unwrap_or_default().to_owned()
+}
+
+/// Wait at least `delay` between requests to the same host.
+pub struct FixedDelay {
+    delay: Duration,
+    /// When each host may next be requested.
+    next: Mutex<HashMap<String, Instant>>,
+}
+
+impl FixedDelay {
+    pub fn new(delay: Duration) -> FixedDelay {
+        FixedDelay {
+            delay,
+            next: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl Politeness for FixedDelay {
+    fn permit(&self, url: &Url, now: Instant) -> Permit {
+        let mut next = self.next.lock().unwrap();
+        let next = next.entry(host_of(url)).or_insert(now);
+        if now >= *next {
+            *next = now + self.delay;
+            Permit::Now
+        } else {
+            Permit::After(*next - now)
+        }
+    }
+}
+
+/// Allow `rate` requests a second to each host, with bursts of up to
+/// `burst` requests.
+pub struct TokenBucket {
+    rate: f64,
+    burst: f64,
+    /// (tokens, when we last added tokens) for each host.
+    buckets: Mutex<HashMap<String, (f64, Instant)>>,
+}
+
+impl TokenBucket {
+    pub fn new(rate: f64, burst: usize) -> TokenBucket {
+        TokenBucket {
+            rate,
+            burst: burst.max(1) as f64,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl Politeness for TokenBucket {
+    fn permit(&self, url: &Url, now: Instant) -> Permit {
+        let mut buckets = self.buckets.lock().unwrap();
+        let (tokens, last) = buckets.entry(host_of(url)).or_insert((self.burst, now));
+        let elapsed = now.saturating_duration_since(*last).as_secs_f64();
+        *tokens = (*tokens + elapsed * self.rate).min(self.burst);
+        *last = now;
+
+        if *tokens >= 1.0 {
+            *tokens -= 1.0;
+            Permit::Now
+        } else if self.rate > 0.0 {
+            Permit::After(Duration::from_secs_f64((1.0 - *tokens) / self.rate))
+        } else {
+            Permit::Never
+        }
+    }
+}
+
+/// The rules in a robots.txt that apply to us.
+#[derive(Debug, Default)]
+struct Rules {
+    /// (allow, pattern) pairs.
+    rules: Vec<(bool, String)>,
+    crawl_delay: Option<Duration>,
+}
+
+impl Rules {
+    /// Parse `src`, keeping the group for `agent`, or the `*` group if
+    /// there isn't one.
+    fn parse(src: &str, agent: &str) -> Rules {
+        let agent = agent.to_ascii_lowercase();
+        let mut ours = None;
+        let mut anyone = None;
+        // The user agents of the current group, and its rules.
+        let mut agents: Vec<String> = vec![];
+        let mut group = Rules::default();
+        let mut in_rules = false;
+
+        let mut finish_group = |agents: &[String], group: Rules| {
+            if agents
+                .iter()
+                .any(|a| a != "*" && agent.contains(a.as_str()))
+            {
+                ours.get_or_insert(group);
+            } else if agents.iter().any(|a| a == "*") {
+                anyone.get_or_insert(group);
+            }
+        };
+
+        for line in src.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) => (key.trim().to_ascii_lowercase(), value.trim()),
+                None => continue,
+            };
+            match key.as_str() {
+                "user-agent" => {
+                    // A user-agent after some rules starts a new group.
+                    if in_rules {
+                        finish_group(&agents, std::mem::take(&mut group));
+                        agents.clear();
+                        in_rules = false;
+                    }
+                    agents.push(value.to_ascii_lowercase());
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+                    // An empty Disallow allows everything.
+                    if !value.is_empty() {
+                        group.rules.push((key == "allow", value.to_owned()));
+                    }
+                }
+                "crawl-delay" => {
+                    in_rules = true;
+                    group.crawl_delay = value.parse().ok().map(Duration::from_secs_f64);
+                }
+                _ => {}
+            }
+        }
+        finish_group(&agents, group);
+        ours.or(anyone).unwrap_or_default()
+    }
+
+    /// May we request `path`? The longest matching rule wins, and
+    /// Allow wins a tie.
+    fn allows(&self, path: &str) -> bool {
+        let path: Vec<char> = path.chars().collect();
+        self.rules
+            .iter()
+            .filter(|(_, pattern)| {
+                // Rules match path prefixes, unless they end with `$`.
+                let glob: Vec<char> = match pattern.strip_suffix('$') {
+                    Some(pattern) => pattern.chars().collect(),
+                    None => pattern.chars().chain(Some('*')).collect(),
+                };
+                ignoring::matches(&glob, &path)
+            })
+            .max_by_key(|(allow, pattern)| (pattern.len(), *allow))
+            .map_or(true, |(allow, _)| *allow)
+    }
+}
+
+/// Follow each site's robots.txt: skip the URLs it disallows, and wait
+/// for its Crawl-delay, if any, between requests.
+pub struct Robots {
+    client: Client,
+    user_agent: String,
+    /// The rules for each origin, and when its host may next be
+    /// requested.
+    sites: Mutex<HashMap<String, (Rules, Instant)>>,
+}
+
+impl Robots {
+    /// Follow the rules for `user_agent`, or `DEFAULT_USER_AGENT`.
+    pub fn new(user_agent: Option<&str>) -> Robots {
+        Robots {
+            client: Client::new(),
+            user_agent: user_agent.unwrap_or(DEFAULT_USER_AGENT).to_owned(),
+            sites: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Fetch the robots.txt for the origin of `url`. Sites without one
+    /// allow everything.
+    fn fetch_rules(&self, url: &Url) -> Rules {
+        let robots_url = match url.join("/robots.txt") {
+            Ok(robots_url) => robots_url,
+            Err(_) => return Rules::default(),
+        };
+        let response = self
+            .client
+            .get(robots_url.as_str())
+            .header(USER_AGENT, self.user_agent.as_str())
+            .send();
+        match response {
+            Ok(mut response) if response.status().is_success() => match response.text() {
+                Ok(src) => Rules::parse(&src, &self.user_agent),
+                Err(_) => Rules::default(),
+            },
+            Ok(_) => Rules::default(),
+            Err(e) => {
+                debug!(url = %robots_url, error = %e, "could not fetch robots.txt");
+                Rules::default()
+            }
+        }
+    }
+}
+
+impl Politeness for Robots {
+    fn permit(&self, url: &Url, now: Instant) -> Permit {
+        let origin = url.origin().ascii_serialization();
+        // Fetch the rules without holding the lock, so other hosts
+        // don't have to wait.
+        let known = self.sites.lock().unwrap().contains_key(&origin);
+        if !known {
+            let rules = self.fetch_rules(url);
+            self.sites
+                .lock()
+                .unwrap()
+                .entry(origin.clone())
+                .or_insert((rules, now));
+        }
+
+        let mut sites = self.sites.lock().unwrap();
+        let (rules, next) = sites.get_mut(&origin).unwrap();
+        if !rules.allows(&url[Position::BeforePath..]) {
+            return Permit::Never;
+        }
+        match rules.crawl_delay {
+            Some(_) if now < *next => Permit::After(*next - now),
+            Some(delay) => {
+                *next = now + delay;
+                Permit::Now
+            }
+            None => Permit::Now,
+        }
+    }
+}
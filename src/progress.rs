@@ -0,0 +1,116 @@
+use crossbeam_channel::{Receiver, Sender};
+use std::sync::Mutex;
+
+/// What happened to a URL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    /// Added to the queue of URLs to check.
+    Queued,
+    /// Taken off the queue, and checked from now on.
+    Started,
+    /// Checked again, after it wasn't accessible.
+    Retried,
+    /// Taken off the queue, but filtered out rather than checked.
+    Skipped,
+    /// Checked, and its result sent to the crawler.
+    Finished,
+}
+
+/// How many of each kind of event there have been so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Counts {
+    pub queued: usize,
+    pub started: usize,
+    pub retried: usize,
+    pub skipped: usize,
+    pub finished: usize,
+}
+
+impl Counts {
+    /// URLs still waiting in the queue.
+    pub fn waiting(&self) -> usize {
+        self.queued.saturating_sub(self.started + self.skipped)
+    }
+
+    /// URLs being checked right now.
+    pub fn in_flight(&self) -> usize {
+        self.started.saturating_sub(self.finished)
+    }
+}
+
+/// A step in the crawl, with the totals so far, so progress displays
+/// don't have to keep count themselves.
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub kind: EventKind,
+    /// The URL as it was queued, i.e. as written on the page linking
+    /// to it.
+    pub url: String,
+    pub counts: Counts,
+}
+
+/// Keeps count of progress events, and sends them on if anyone asked
+/// for them.
+pub(crate) struct Tracker {
+    counts: Mutex<Counts>,
+    sender: Option<Sender<Event>>,
+}
+
+impl Tracker {
+    pub(crate) fn new(sender: Option<Sender<Event>>) -> Tracker {
+        Tracker {
+            counts: Mutex::new(Counts::default()),
+            sender,
+        }
+    }
+
+    pub(crate) fn record(&self, kind: EventKind, url: &str) {
+        let sender = match self.sender {
+            Some(ref sender) => sender,
+            None => return,
+        };
+        let counts = {
+            let mut counts = self.counts.lock().unwrap();
+            match kind {
+                EventKind::Queued => counts.queued += 1,
+                EventKind::Started => counts.started += 1,
+                EventKind::Retried => counts.retried += 1,
+                EventKind::Skipped => counts.skipped += 1,
+                EventKind::Finished => counts.finished += 1,
+            }
+            *counts
+        };
+        // Nobody listening any more is fine.
+        let _ = sender.send(Event {
+            kind,
+            url: url.to_owned(),
+            counts,
+        });
+    }
+}
+
+/// The progress events of a crawl, separate from its results.
+/// Iterating blocks until the next event, and ends once the crawl has
+/// finished and the crawler has been dropped.
+pub struct ProgressEvents {
+    receiver: Receiver<Event>,
+}
+
+impl ProgressEvents {
+    pub(crate) fn new(receiver: Receiver<Event>) -> ProgressEvents {
+        ProgressEvents { receiver }
+    }
+
+    /// The next event, if there is one yet.
+    pub fn try_next(&self) -> Option<Event> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+impl Iterator for ProgressEvents {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        self.receiver.recv().ok()
+    }
+}
@@ -0,0 +1,42 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::graphing::LinkGraph;
+use crate::reporting::Report;
+
+/// Score how visible each broken URL is: every page linking to it
+/// adds to the score, more so if the page is shallow or listed in the
+/// site's sitemap.
+pub fn impact_scores(report: &Report, sitemap: &HashSet<String>) -> HashMap<String, f64> {
+    let depths = LinkGraph::from_referrers(&report.referrers).depths(&report.start_url);
+
+    let mut scores = HashMap::new();
+    for state in &report.failures {
+        let score: f64 = report
+            .referrers_of(state)
+            .into_iter()
+            .map(|referrer| {
+                // Pages we can't find a path to are probably deep.
+                let depth = depths.get(referrer).cloned().unwrap_or(10);
+                let in_sitemap = if sitemap.contains(referrer) { 2.0 } else { 1.0 };
+                in_sitemap / (1.0 + depth as f64)
+            })
+            .sum();
+        scores.insert(state.url_str().to_owned(), score);
+    }
+    scores
+}
+
+/// Sort the failures in `report` so the most visible breakage comes
+/// first, and record their scores.
+pub fn rank(report: &mut Report, sitemap: &HashSet<String>) {
+    let scores = impact_scores(report, sitemap);
+    report.failures.sort_by(|a, b| {
+        let score_a = scores.get(a.url_str()).cloned().unwrap_or(0.0);
+        let score_b = scores.get(b.url_str()).cloned().unwrap_or(0.0);
+        score_b
+            .partial_cmp(&score_a)
+            .unwrap()
+            .then_with(|| a.url_str().cmp(b.url_str()))
+    });
+    report.impact = scores;
+}
@@ -0,0 +1,934 @@
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::confusables::ConfusableLink;
+use crate::fetching::CheckResult;
+use crate::integrity::IntegrityMismatch;
+use crate::ip_addresses::IpLink;
+use crate::locales::LocaleDrift;
+use crate::mixed_content::MixedContent;
+use crate::normalizing::TrackedLink;
+use crate::severity::{Severities, Severity};
+
+/// The results of a crawl, for formats that report everything at the
+/// end.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Report {
+    pub start_url: String,
+    pub success_count: usize,
+    /// How many URLs were served over each HTTP version, e.g.
+    /// `HTTP/1.1`.
+    #[cfg_attr(feature = "serde1", serde(default))]
+    pub protocols: BTreeMap<String, usize>,
+    /// The response headers we kept for each URL, if we were asked to
+    /// keep any.
+    #[cfg_attr(feature = "serde1", serde(default))]
+    pub headers: HashMap<String, BTreeMap<String, String>>,
+    pub failures: Vec<CheckResult>,
+    /// All the pages linking to each URL, see `Crawler::referrers`.
+    pub referrers: HashMap<String, Vec<String>>,
+    pub severities: Severities,
+    /// The fewest clicks from a seed to each URL, see
+    /// `LinkGraph::click_depths`.
+    #[cfg_attr(feature = "serde1", serde(default))]
+    pub depths: HashMap<String, usize>,
+    /// How visible each broken URL is, see `ranking::impact_scores`.
+    pub impact: HashMap<String, f64>,
+    /// Failures that are in the baseline, so don't count as broken.
+    pub baselined: Vec<CheckResult>,
+    /// URLs in the baseline that are no longer broken.
+    pub recovered: Vec<String>,
+    /// Pages that aren't fine in every locale, if we were comparing
+    /// locales.
+    #[cfg_attr(feature = "serde1", serde(default))]
+    pub locale_drift: Vec<LocaleDrift>,
+    /// Links between pages on the site with tracking parameters, if we
+    /// were looking for them.
+    #[cfg_attr(feature = "serde1", serde(default))]
+    pub tracked_links: Vec<TrackedLink>,
+    /// Links to hosts that look like the site's domain or a popular
+    /// one, but aren't.
+    #[cfg_attr(feature = "serde1", serde(default))]
+    pub confusable_links: Vec<ConfusableLink>,
+    /// Links to bare IP addresses rather than host names.
+    #[cfg_attr(feature = "serde1", serde(default))]
+    pub ip_links: Vec<IpLink>,
+    /// `http://` resources loaded by `https://` pages on the site.
+    #[cfg_attr(feature = "serde1", serde(default))]
+    pub mixed_content: Vec<MixedContent>,
+    /// Groups of pages on the site serving identical content.
+    #[cfg_attr(feature = "serde1", serde(default))]
+    pub duplicate_pages: Vec<Vec<String>>,
+    /// The working `https://` version of each `http://` link, if we
+    /// looked for them.
+    #[cfg_attr(feature = "serde1", serde(default))]
+    pub upgradable: HashMap<String, String>,
+    /// Scripts and stylesheets that don't match their `integrity`
+    /// attribute, if we checked them.
+    #[cfg_attr(feature = "serde1", serde(default))]
+    pub integrity_mismatches: Vec<IntegrityMismatch>,
+    /// Where each URL that permanently redirects ends up.
+    #[cfg_attr(feature = "serde1", serde(default))]
+    pub moved: HashMap<String, String>,
+    /// Every URL we were redirected to from each URL that redirects
+    /// more times than we warn about, if we were warning about any.
+    #[cfg_attr(feature = "serde1", serde(default))]
+    pub long_redirects: HashMap<String, Vec<String>>,
+    /// Where to link instead of each URL on the site that only
+    /// redirects to add or remove a trailing slash, or fix the case of
+    /// its path.
+    #[cfg_attr(feature = "serde1", serde(default))]
+    pub suggested_fixes: HashMap<String, String>,
+    /// The file and line each broken URL is linked from, when checking
+    /// local files.
+    #[cfg_attr(feature = "serde1", serde(default))]
+    pub locations: HashMap<String, (String, usize)>,
+    /// The latest copy in the Wayback Machine of each broken URL, if
+    /// we looked them up.
+    #[cfg_attr(feature = "serde1", serde(default))]
+    pub archived: HashMap<String, String>,
+}
+
+impl Report {
+    /// Every page that linked to the URL in `state`.
+    pub fn referrers_of<'a>(&'a self, state: &'a CheckResult) -> Vec<&'a str> {
+        match self.referrers.get(state.url_str()) {
+            Some(pages) if !pages.is_empty() => pages.iter().map(|p| p.as_str()).collect(),
+            // The start URL isn't linked from anywhere.
+            _ => state.referrers.iter().map(|p| p.as_str()).collect(),
+        }
+    }
+
+    /// The fewest clicks from a seed to the URL in `state`, falling
+    /// back to the depth the crawl found it at.
+    pub fn depth_of(&self, state: &CheckResult) -> Option<usize> {
+        self.depths.get(state.url_str()).copied().or(state.depth)
+    }
+
+    /// The failures with `severity`.
+    pub fn failures_with(&self, severity: Severity) -> Vec<&CheckResult> {
+        self.failures
+            .iter()
+            .filter(|state| self.severities.of(state) == severity)
+            .collect()
+    }
+}
+
+/// The different ways we can report results.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// Human-readable output with a progress counter.
+    Text,
+    /// GitHub Actions workflow commands, so broken links show up as
+    /// annotations.
+    GitHub,
+    /// A GitLab Code Quality report, so broken links show up in the
+    /// merge request widget.
+    GitLab,
+    /// A JSON summary of the whole crawl.
+    Json,
+    /// A standalone HTML page.
+    Html,
+}
+
+impl Format {
+    pub const NAMES: &'static [&'static str] = &["text", "github", "gitlab", "json", "html"];
+
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "text" => Some(Format::Text),
+            "github" => Some(Format::GitHub),
+            "gitlab" => Some(Format::GitLab),
+            "json" => Some(Format::Json),
+            "html" => Some(Format::Html),
+            _ => None,
+        }
+    }
+
+    /// Guess the format from a file extension.
+    fn from_path(path: &Path) -> Option<Format> {
+        match path.extension()?.to_str()? {
+            "txt" => Some(Format::Text),
+            "json" => Some(Format::Json),
+            "html" | "htm" => Some(Format::Html),
+            _ => None,
+        }
+    }
+}
+
+/// Parse an output destination, either `FORMAT:PATH` or a path whose
+/// extension tells us the format.
+pub fn parse_output(spec: &str) -> Result<(Format, PathBuf), String> {
+    if let Some(i) = spec.find(':') {
+        if let Some(format) = Format::from_name(&spec[..i]) {
+            return Ok((format, PathBuf::from(&spec[i + 1..])));
+        }
+    }
+
+    let path = PathBuf::from(spec);
+    match Format::from_path(&path) {
+        Some(format) => Ok((format, path)),
+        None => Err(format!(
+            "Can't tell the format of {}, use FORMAT:PATH (e.g. json:{})",
+            spec, spec
+        )),
+    }
+}
+
+/// Render the whole report in `format`.
+pub fn render(format: Format, report: &Report) -> String {
+    match format {
+        Format::Text => text_report(report),
+        Format::GitHub => {
+            let mut lines: Vec<String> = report
+                .failures
+                .iter()
+                .map(|state| {
+                    let location = report
+                        .locations
+                        .get(state.url_str())
+                        .map(|(file, line)| (file.as_str(), *line));
+                    github_annotation(state, report.severities.of(state), location)
+                })
+                .collect();
+            lines.push(String::new());
+            lines.join("\n")
+        }
+        Format::GitLab => gitlab_code_quality(report),
+        Format::Json => json_report(report),
+        Format::Html => html_report(report),
+    }
+}
+
+/// Reports are split into a section for each severity, most severe
+/// first.
+const SECTIONS: &[(Severity, &str)] = &[
+    (Severity::Error, "Errors"),
+    (Severity::Warning, "Warnings"),
+    (Severity::Info, "Info"),
+];
+
+/// A plain text report, without color codes.
+fn text_report(report: &Report) -> String {
+    let mut s = String::new();
+    for (severity, heading) in SECTIONS {
+        let failures = report.failures_with(*severity);
+        if failures.is_empty() {
+            continue;
+        }
+
+        s.push_str(&format!("{}:\n", heading));
+        for state in failures {
+            s.push_str(&format!("✘ {} ({})\n", state.display_url(), state.reason()));
+            for referrer in report.referrers_of(state) {
+                s.push_str(&format!("    linked from {}\n", referrer));
+            }
+            if let Some(depth) = report.depth_of(state) {
+                s.push_str(&format!("    {} from the start\n", clicks(depth)));
+            }
+            if let Some(snapshot) = report.archived.get(state.url_str()) {
+                s.push_str(&format!("    archived at {}\n", snapshot));
+            }
+        }
+        s.push('\n');
+    }
+    if !report.baselined.is_empty() {
+        s.push_str("Known broken (in the baseline):\n");
+        for state in &report.baselined {
+            s.push_str(&format!("✘ {} ({})\n", state.display_url(), state.reason()));
+        }
+        s.push('\n');
+    }
+    if !report.recovered.is_empty() {
+        s.push_str("No longer broken (remove from the baseline):\n");
+        for url in &report.recovered {
+            s.push_str(&format!("✔ {}\n", url));
+        }
+        s.push('\n');
+    }
+    if !report.locale_drift.is_empty() {
+        s.push_str("Differences between locales:\n");
+        for page in &report.locale_drift {
+            s.push_str(&format!("✘ {}\n", page.path));
+            if !page.broken.is_empty() {
+                s.push_str(&format!("    broken in {}\n", page.broken.join(", ")));
+            }
+            if !page.missing.is_empty() {
+                s.push_str(&format!("    missing from {}\n", page.missing.join(", ")));
+            }
+        }
+        s.push('\n');
+    }
+    if !report.tracked_links.is_empty() {
+        s.push_str("Links with tracking parameters:\n");
+        for link in &report.tracked_links {
+            s.push_str(&format!("⚠ {} ({})\n", link.url, link.params.join(", ")));
+            s.push_str(&format!("    linked from {}\n", link.page));
+        }
+        s.push('\n');
+    }
+    if !report.confusable_links.is_empty() {
+        s.push_str("Links to look-alike domains:\n");
+        for link in &report.confusable_links {
+            s.push_str(&format!(
+                "⚠ {} ({} looks like {})\n",
+                link.url, link.host, link.looks_like
+            ));
+            s.push_str(&format!("    linked from {}\n", link.page));
+        }
+        s.push('\n');
+    }
+    if !report.ip_links.is_empty() {
+        s.push_str("Links to IP addresses:\n");
+        for link in &report.ip_links {
+            s.push_str(&format!("⚠ {} ({} address)\n", link.url, link.kind));
+            s.push_str(&format!("    linked from {}\n", link.page));
+        }
+        s.push('\n');
+    }
+    if !report.mixed_content.is_empty() {
+        s.push_str("Insecure resources on secure pages (mixed content):\n");
+        for mixed in &report.mixed_content {
+            s.push_str(&format!("⚠ {} ({})\n", mixed.url, mixed.element));
+            s.push_str(&format!("    loaded by {}\n", mixed.page));
+        }
+        s.push('\n');
+    }
+    if !report.duplicate_pages.is_empty() {
+        s.push_str("Pages with identical content:\n");
+        for urls in &report.duplicate_pages {
+            s.push_str(&format!("⚠ {}\n", urls[0]));
+            for url in &urls[1..] {
+                s.push_str(&format!("    same as {}\n", url));
+            }
+        }
+        s.push('\n');
+    }
+    if !report.integrity_mismatches.is_empty() {
+        s.push_str("Scripts and stylesheets that don't match their integrity hash:\n");
+        for mismatch in &report.integrity_mismatches {
+            s.push_str(&format!("✘ {}\n", mismatch.url));
+            s.push_str(&format!("    expected {}\n", mismatch.expected));
+            s.push_str(&format!("    actually {}\n", mismatch.actual));
+            s.push_str(&format!("    loaded by {}\n", mismatch.page));
+        }
+        s.push('\n');
+    }
+    if !report.upgradable.is_empty() {
+        s.push_str("Links that also work over HTTPS:\n");
+        let mut upgradable: Vec<_> = report.upgradable.iter().collect();
+        upgradable.sort();
+        for (url, https) in upgradable {
+            s.push_str(&format!("⚠ {} → {}\n", url, https));
+            if let Some(pages) = report.referrers.get(url) {
+                for page in pages {
+                    s.push_str(&format!("    linked from {}\n", page));
+                }
+            }
+        }
+        s.push('\n');
+    }
+    if !report.suggested_fixes.is_empty() {
+        s.push_str("Links that only redirect to fix a slash or case:\n");
+        let mut suggested_fixes: Vec<_> = report.suggested_fixes.iter().collect();
+        suggested_fixes.sort();
+        for (url, fixed) in suggested_fixes {
+            s.push_str(&format!("⚠ {} → {}\n", url, fixed));
+            if let Some(pages) = report.referrers.get(url) {
+                for page in pages {
+                    s.push_str(&format!("    linked from {}\n", page));
+                }
+            }
+        }
+        s.push('\n');
+    }
+    if !report.long_redirects.is_empty() {
+        s.push_str("Long redirect chains:\n");
+        let mut long_redirects: Vec<_> = report.long_redirects.iter().collect();
+        long_redirects.sort();
+        for (url, redirects) in long_redirects {
+            s.push_str(&format!("⚠ {} ({} redirects)\n", url, redirects.len()));
+            for redirect in redirects {
+                s.push_str(&format!("    → {}\n", redirect));
+            }
+            if let Some(pages) = report.referrers.get(url) {
+                for page in pages {
+                    s.push_str(&format!("    linked from {}\n", page));
+                }
+            }
+        }
+        s.push('\n');
+    }
+    s.push_str(&format!(
+        "Succeeded: {} Failed: {}\n",
+        report.success_count,
+        report.failures.len()
+    ));
+    if !report.protocols.is_empty() {
+        s.push_str(&format!("Protocols: {}\n", protocol_summary(report)));
+    }
+    if let Some(depths) = depth_summary(report) {
+        s.push_str(&format!("Broken by depth: {}\n", depths));
+    }
+    s
+}
+
+/// `1 click`, `2 clicks`, etc.
+fn clicks(depth: usize) -> String {
+    if depth == 1 {
+        "1 click".to_owned()
+    } else {
+        format!("{} clicks", depth)
+    }
+}
+
+/// How many failures are each number of clicks from the start, e.g.
+/// `0: 1, 1: 3, 2: 5, unknown: 2`, if we know any of their depths.
+fn depth_summary(report: &Report) -> Option<String> {
+    let mut counts = BTreeMap::new();
+    let mut unknown = 0;
+    for state in &report.failures {
+        match report.depth_of(state) {
+            Some(depth) => *counts.entry(depth).or_insert(0) += 1,
+            None => unknown += 1,
+        }
+    }
+    let mut counts: Vec<_> = counts
+        .iter()
+        .map(|(depth, count)| format!("{}: {}", depth, count))
+        .collect();
+    if counts.is_empty() {
+        return None;
+    }
+    if unknown > 0 {
+        counts.push(format!("unknown: {}", unknown));
+    }
+    Some(counts.join(", "))
+}
+
+/// How many URLs were served over each HTTP version, e.g.
+/// `HTTP/1.1: 120, HTTP/2: 30`.
+fn protocol_summary(report: &Report) -> String {
+    let counts: Vec<_> = report
+        .protocols
+        .iter()
+        .map(|(version, count)| format!("{}: {}", version, count))
+        .collect();
+    counts.join(", ")
+}
+
+fn json_report(report: &Report) -> String {
+    serde_json::to_string_pretty(&report_json(report)).unwrap()
+}
+
+/// A single failed URL as JSON.
+pub fn failure_json(report: &Report, state: &CheckResult) -> serde_json::Value {
+    json!({
+        "url": state.url_str(),
+        "referrer": state.referrer(),
+        "referrers": report.referrers_of(state),
+        "reason": state.reason(),
+        "category": state.category(),
+        "status": state.status.map(|status| status.as_u16()),
+        "redirects": state.redirects,
+        "http_version": state.http_version,
+        "headers": state.headers,
+        "error": state.error,
+        "duration_ms": state.duration.map(|d| d.as_millis() as u64),
+        "depth": report.depth_of(state),
+        "severity": report.severities.of(state).name(),
+        "impact": report.impact.get(state.url_str()),
+        "archived": report.archived.get(state.url_str()),
+    })
+}
+
+/// The whole report as JSON.
+pub fn report_json(report: &Report) -> serde_json::Value {
+    let failures: Vec<_> = report
+        .failures
+        .iter()
+        .map(|state| failure_json(report, state))
+        .collect();
+    let baselined: Vec<_> = report
+        .baselined
+        .iter()
+        .map(|state| failure_json(report, state))
+        .collect();
+    let locale_drift: Vec<_> = report
+        .locale_drift
+        .iter()
+        .map(|page| {
+            json!({
+                "path": page.path,
+                "present": page.present,
+                "broken": page.broken,
+                "missing": page.missing,
+            })
+        })
+        .collect();
+    let tracked_links: Vec<_> = report
+        .tracked_links
+        .iter()
+        .map(|link| {
+            json!({
+                "page": link.page,
+                "url": link.url,
+                "params": link.params,
+            })
+        })
+        .collect();
+    let confusable_links: Vec<_> = report
+        .confusable_links
+        .iter()
+        .map(|link| {
+            json!({
+                "page": link.page,
+                "url": link.url,
+                "host": link.host,
+                "looks_like": link.looks_like,
+            })
+        })
+        .collect();
+    let ip_links: Vec<_> = report
+        .ip_links
+        .iter()
+        .map(|link| {
+            json!({
+                "page": link.page,
+                "url": link.url,
+                "kind": link.kind,
+            })
+        })
+        .collect();
+    let mixed_content: Vec<_> = report
+        .mixed_content
+        .iter()
+        .map(|mixed| {
+            json!({
+                "page": mixed.page,
+                "url": mixed.url,
+                "element": mixed.element,
+            })
+        })
+        .collect();
+    let integrity_mismatches: Vec<_> = report
+        .integrity_mismatches
+        .iter()
+        .map(|mismatch| {
+            json!({
+                "page": mismatch.page,
+                "url": mismatch.url,
+                "expected": mismatch.expected,
+                "actual": mismatch.actual,
+            })
+        })
+        .collect();
+    json!({
+        "start_url": report.start_url,
+        "succeeded": report.success_count,
+        "failed": report.failures.len(),
+        "protocols": report.protocols,
+        "headers": report.headers,
+        "failures": failures,
+        "baselined": baselined,
+        "recovered": report.recovered,
+        "locale_drift": locale_drift,
+        "tracked_links": tracked_links,
+        "confusable_links": confusable_links,
+        "ip_links": ip_links,
+        "mixed_content": mixed_content,
+        "duplicate_pages": report.duplicate_pages,
+        "upgradable": report.upgradable,
+        "integrity_mismatches": integrity_mismatches,
+        "moved": report.moved,
+        "long_redirects": report.long_redirects,
+        "suggested_fixes": report.suggested_fixes,
+    })
+}
+
+/// Render a user-supplied Tera template. The template gets the same
+/// data as the JSON report.
+#[cfg(feature = "native")]
+pub fn render_template(template: &str, report: &Report) -> Result<String, String> {
+    let context = tera::Context::from_value(report_json(report)).map_err(|e| e.to_string())?;
+    tera::Tera::one_off(template, &context, false).map_err(|e| e.to_string())
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn html_report(report: &Report) -> String {
+    let mut sections = String::new();
+    for (severity, heading) in SECTIONS {
+        let failures = report.failures_with(*severity);
+        if failures.is_empty() {
+            continue;
+        }
+
+        sections.push_str(&format!(
+            "<h2>{}</h2>\n<table>\n<tr><th>URL</th><th>Linked from</th><th>Depth</th><th>Problem</th></tr>\n{}</table>\n",
+            heading,
+            html_rows(report, &failures)
+        ));
+    }
+    if !report.baselined.is_empty() {
+        let baselined: Vec<_> = report.baselined.iter().collect();
+        sections.push_str(&format!(
+            "<h2>Known broken (in the baseline)</h2>\n<table>\n<tr><th>URL</th><th>Linked from</th><th>Depth</th><th>Problem</th></tr>\n{}</table>\n",
+            html_rows(report, &baselined)
+        ));
+    }
+    if !report.recovered.is_empty() {
+        let items: Vec<_> = report
+            .recovered
+            .iter()
+            .map(|url| format!("<li>{}</li>\n", escape_html(url)))
+            .collect();
+        sections.push_str(&format!(
+            "<h2>No longer broken (remove from the baseline)</h2>\n<ul>\n{}</ul>\n",
+            items.concat()
+        ));
+    }
+    if !report.locale_drift.is_empty() {
+        let rows: Vec<_> = report
+            .locale_drift
+            .iter()
+            .map(|page| {
+                format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                    escape_html(&page.path),
+                    escape_html(&page.broken.join(", ")),
+                    escape_html(&page.missing.join(", "))
+                )
+            })
+            .collect();
+        sections.push_str(&format!(
+            "<h2>Differences between locales</h2>\n<table>\n<tr><th>Path</th><th>Broken in</th><th>Missing from</th></tr>\n{}</table>\n",
+            rows.concat()
+        ));
+    }
+    if !report.tracked_links.is_empty() {
+        let rows: Vec<_> = report
+            .tracked_links
+            .iter()
+            .map(|link| {
+                format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                    escape_html(&link.url),
+                    escape_html(&link.page),
+                    escape_html(&link.params.join(", "))
+                )
+            })
+            .collect();
+        sections.push_str(&format!(
+            "<h2>Links with tracking parameters</h2>\n<table>\n<tr><th>URL</th><th>Linked from</th><th>Parameters</th></tr>\n{}</table>\n",
+            rows.concat()
+        ));
+    }
+    if !report.confusable_links.is_empty() {
+        let rows: Vec<_> = report
+            .confusable_links
+            .iter()
+            .map(|link| {
+                format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                    escape_html(&link.url),
+                    escape_html(&link.host),
+                    escape_html(&link.looks_like),
+                    escape_html(&link.page)
+                )
+            })
+            .collect();
+        sections.push_str(&format!(
+            "<h2>Links to look-alike domains</h2>\n<table>\n<tr><th>URL</th><th>Host</th><th>Looks like</th><th>Linked from</th></tr>\n{}</table>\n",
+            rows.concat()
+        ));
+    }
+    if !report.ip_links.is_empty() {
+        let rows: Vec<_> = report
+            .ip_links
+            .iter()
+            .map(|link| {
+                format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                    escape_html(&link.url),
+                    escape_html(&link.kind),
+                    escape_html(&link.page)
+                )
+            })
+            .collect();
+        sections.push_str(&format!(
+            "<h2>Links to IP addresses</h2>\n<table>\n<tr><th>URL</th><th>Address</th><th>Linked from</th></tr>\n{}</table>\n",
+            rows.concat()
+        ));
+    }
+    if !report.mixed_content.is_empty() {
+        let rows: Vec<_> = report
+            .mixed_content
+            .iter()
+            .map(|mixed| {
+                format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                    escape_html(&mixed.url),
+                    escape_html(&mixed.element),
+                    escape_html(&mixed.page)
+                )
+            })
+            .collect();
+        sections.push_str(&format!(
+            "<h2>Insecure resources on secure pages</h2>\n<table>\n<tr><th>URL</th><th>Element</th><th>Loaded by</th></tr>\n{}</table>\n",
+            rows.concat()
+        ));
+    }
+    if !report.duplicate_pages.is_empty() {
+        let rows: Vec<_> = report
+            .duplicate_pages
+            .iter()
+            .map(|urls| {
+                let urls: Vec<_> = urls.iter().map(|url| escape_html(url)).collect();
+                format!("<tr><td>{}</td></tr>\n", urls.join("<br>"))
+            })
+            .collect();
+        sections.push_str(&format!(
+            "<h2>Pages with identical content</h2>\n<table>\n<tr><th>URLs</th></tr>\n{}</table>\n",
+            rows.concat()
+        ));
+    }
+    if !report.integrity_mismatches.is_empty() {
+        let rows: Vec<_> = report
+            .integrity_mismatches
+            .iter()
+            .map(|mismatch| {
+                format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                    escape_html(&mismatch.url),
+                    escape_html(&mismatch.page),
+                    escape_html(&mismatch.expected),
+                    escape_html(&mismatch.actual)
+                )
+            })
+            .collect();
+        sections.push_str(&format!(
+            "<h2>Scripts and stylesheets that don't match their integrity hash</h2>\n<table>\n<tr><th>URL</th><th>Loaded by</th><th>Expected</th><th>Actual</th></tr>\n{}</table>\n",
+            rows.concat()
+        ));
+    }
+    if !report.upgradable.is_empty() {
+        let mut upgradable: Vec<_> = report.upgradable.iter().collect();
+        upgradable.sort();
+        let rows: Vec<_> = upgradable
+            .into_iter()
+            .map(|(url, https)| {
+                format!(
+                    "<tr><td>{}</td><td>{}</td></tr>\n",
+                    escape_html(url),
+                    escape_html(https)
+                )
+            })
+            .collect();
+        sections.push_str(&format!(
+            "<h2>Links that also work over HTTPS</h2>\n<table>\n<tr><th>URL</th><th>Use instead</th></tr>\n{}</table>\n",
+            rows.concat()
+        ));
+    }
+    if !report.suggested_fixes.is_empty() {
+        let mut suggested_fixes: Vec<_> = report.suggested_fixes.iter().collect();
+        suggested_fixes.sort();
+        let rows: Vec<_> = suggested_fixes
+            .into_iter()
+            .map(|(url, fixed)| {
+                format!(
+                    "<tr><td>{}</td><td>{}</td></tr>\n",
+                    escape_html(url),
+                    escape_html(fixed)
+                )
+            })
+            .collect();
+        sections.push_str(&format!(
+            "<h2>Links that only redirect to fix a slash or case</h2>\n<table>\n<tr><th>URL</th><th>Link to</th></tr>\n{}</table>\n",
+            rows.concat()
+        ));
+    }
+    if !report.long_redirects.is_empty() {
+        let mut long_redirects: Vec<_> = report.long_redirects.iter().collect();
+        long_redirects.sort();
+        let rows: Vec<_> = long_redirects
+            .into_iter()
+            .map(|(url, redirects)| {
+                let redirects: Vec<_> = redirects.iter().map(|url| escape_html(url)).collect();
+                format!(
+                    "<tr><td>{}</td><td>{}</td></tr>\n",
+                    escape_html(url),
+                    redirects.join("<br>")
+                )
+            })
+            .collect();
+        sections.push_str(&format!(
+            "<h2>Long redirect chains</h2>\n<table>\n<tr><th>URL</th><th>Redirects</th></tr>\n{}</table>\n",
+            rows.concat()
+        ));
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Link Doctor report</title>
+</head>
+<body>
+<h1>Link Doctor report</h1>
+<p>Succeeded: {} Failed: {}</p>
+{}{}{}</body>
+</html>
+"#,
+        report.success_count,
+        report.failures.len(),
+        if report.protocols.is_empty() {
+            String::new()
+        } else {
+            format!(
+                "<p>Protocols: {}</p>\n",
+                escape_html(&protocol_summary(report))
+            )
+        },
+        depth_summary(report).map_or(String::new(), |depths| format!(
+            "<p>Broken by depth: {}</p>\n",
+            escape_html(&depths)
+        )),
+        sections
+    )
+}
+
+fn html_rows(report: &Report, failures: &[&CheckResult]) -> String {
+    let mut rows = String::new();
+    for state in failures {
+        let url = escape_html(state.url_str());
+        let display_url = escape_html(&state.display_url());
+        let referrers: Vec<_> = report
+            .referrers_of(state)
+            .into_iter()
+            .map(|referrer| {
+                let referrer = escape_html(referrer);
+                format!("<a href=\"{}\">{}</a>", referrer, referrer)
+            })
+            .collect();
+        let mut problem = escape_html(&state.reason());
+        if let Some(snapshot) = report.archived.get(state.url_str()) {
+            problem.push_str(&format!(
+                " (<a href=\"{}\">archived copy</a>)",
+                escape_html(snapshot)
+            ));
+        }
+        rows.push_str(&format!(
+            "<tr><td><a href=\"{}\">{}</a></td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            url,
+            display_url,
+            referrers.join("<br>"),
+            report
+                .depth_of(state)
+                .map_or(String::new(), |depth| depth.to_string()),
+            problem
+        ));
+    }
+    rows
+}
+
+/// Escape data for use in a workflow command message.
+fn escape_data(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape data for use as a workflow command property value.
+fn escape_property(s: &str) -> String {
+    escape_data(s).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// Format a failed URL state as a GitHub Actions annotation.
+///
+/// `location` is the file and line the link came from, if we know it.
+pub fn github_annotation(
+    state: &CheckResult,
+    severity: Severity,
+    location: Option<(&str, usize)>,
+) -> String {
+    let level = match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Info => "notice",
+    };
+
+    let mut properties = vec![];
+    if let Some((file, line)) = location {
+        properties.push(format!("file={}", escape_property(file)));
+        properties.push(format!("line={}", line));
+    }
+    properties.push(format!("title={}", escape_property("Broken link")));
+
+    let message = format!(
+        "broken link {} on {} ({})",
+        state.display_url(),
+        state.referrer(),
+        state.reason()
+    );
+
+    format!(
+        "::{} {}::{}",
+        level,
+        properties.join(","),
+        escape_data(&message)
+    )
+}
+
+/// A 64-bit FNV-1a hash. Unlike `DefaultHasher`, this is guaranteed
+/// to be stable across Rust versions, which matters for fingerprints
+/// compared between CI runs.
+fn fnv1a(s: &str) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in s.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// Format failed URL states as a GitLab Code Quality report.
+///
+/// GitLab requires a file path for every issue, so we use the
+/// referring page.
+pub fn gitlab_code_quality(report: &Report) -> String {
+    let issues: Vec<_> = report
+        .failures
+        .iter()
+        .map(|state| {
+            let severity = match report.severities.of(state) {
+                Severity::Error => "major",
+                Severity::Warning => "minor",
+                Severity::Info => "info",
+            };
+            let fingerprint = fnv1a(&format!("{} {}", state.referrer(), state.url_str()));
+            json!({
+                "type": "issue",
+                "check_name": "broken-link",
+                "description": format!("Broken link {} ({})", state.url_str(), state.reason()),
+                "categories": ["Bug Risk"],
+                "severity": severity,
+                "fingerprint": format!("{:016x}", fingerprint),
+                "location": {
+                    "path": state.referrer(),
+                    "lines": { "begin": 1 },
+                },
+            })
+        })
+        .collect();
+
+    serde_json::to_string_pretty(&issues).unwrap()
+}
@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+use std::net::ToSocketAddrs;
+use std::sync::Mutex;
+use std::thread;
+use url::{Host, Url};
+
+/// Whether each host name we've looked up resolves, so links to hosts
+/// that don't exist can be reported without an HTTP request, or waiting
+/// for one to time out.
+#[derive(Debug, Default)]
+pub struct HostCache {
+    /// Why each host we couldn't resolve doesn't, or None if it does.
+    hosts: Mutex<HashMap<String, Option<String>>>,
+}
+
+/// Why `host` doesn't resolve, if it doesn't.
+fn lookup(host: &str) -> Option<String> {
+    match (host, 80).to_socket_addrs() {
+        Ok(mut addrs) if addrs.next().is_some() => None,
+        Ok(_) => Some(format!("{} has no addresses", host)),
+        Err(e) => Some(format!("could not resolve {}: {}", host, e)),
+    }
+}
+
+/// The host name of `url`, unless it's an IP address.
+fn domain(url: &Url) -> Option<&str> {
+    match url.host()? {
+        Host::Domain(domain) => Some(domain),
+        Host::Ipv4(_) | Host::Ipv6(_) => None,
+    }
+}
+
+impl HostCache {
+    /// Look up every host in `urls` we haven't already, `concurrency`
+    /// at a time.
+    pub fn resolve_all(&self, urls: &[Url], concurrency: usize) {
+        let mut hosts: Vec<&str> = urls.iter().filter_map(domain).collect();
+        hosts.sort_unstable();
+        hosts.dedup();
+        hosts.retain(|host| !self.hosts.lock().unwrap().contains_key(*host));
+        let next = Mutex::new(hosts.iter());
+        thread::scope(|scope| {
+            for _ in 0..concurrency.clamp(1, hosts.len().max(1)) {
+                scope.spawn(|| loop {
+                    let host = match next.lock().unwrap().next() {
+                        Some(host) => *host,
+                        None => break,
+                    };
+                    let error = lookup(host);
+                    self.hosts.lock().unwrap().insert(host.to_owned(), error);
+                });
+            }
+        });
+    }
+
+    /// Why the host of `url` doesn't resolve, if it doesn't, looking it
+    /// up the first time we see it.
+    pub fn unresolvable(&self, url: &Url) -> Option<String> {
+        let host = domain(url)?;
+        if let Some(error) = self.hosts.lock().unwrap().get(host) {
+            return error.clone();
+        }
+        // Don't hold the lock while we wait for DNS. Two workers may
+        // look up the same host, which is harmless.
+        let error = lookup(host);
+        self.hosts
+            .lock()
+            .unwrap()
+            .insert(host.to_owned(), error.clone());
+        error
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use url::Url;
+
+use crate::directory::{self, DirectoryFetcher};
+use crate::fetching::{CheckResult, FetchError, Fetcher, Page};
+use crate::parsing::{self, LinkExtractor};
+
+/// The directories in `cargo doc` output that aren't crates.
+const NOT_CRATES: &[&str] = &[
+    "src",
+    "static.files",
+    "implementors",
+    "trait.impl",
+    "type.impl",
+    "search.desc",
+];
+
+/// The crates documented in `doc_dir`, usually target/doc: the
+/// directories with an index.html.
+pub fn crates(doc_dir: &Path) -> io::Result<HashSet<String>> {
+    let mut crates = HashSet::new();
+    for entry in fs::read_dir(doc_dir)? {
+        let path = entry?.path();
+        let name = match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) => name.to_owned(),
+            None => continue,
+        };
+        if path.join("index.html").is_file() && !NOT_CRATES.contains(&name.as_str()) {
+            crates.insert(name);
+        }
+    }
+    Ok(crates)
+}
+
+/// The pages to start checking from: every page, except the
+/// highlighted source under src/, whose only links are to its own
+/// lines.
+pub fn start_files(doc_dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let src = doc_dir.join("src");
+    Ok(directory::html_files(doc_dir)?
+        .into_iter()
+        .filter(|path| !path.starts_with(&src))
+        .collect())
+}
+
+/// Links in rustdoc's HTML, plus where redirect stubs point. rustdoc
+/// leaves stubs at the old paths of items that were moved or
+/// re-exported, so they should keep working.
+pub struct RustdocExtractor;
+
+impl LinkExtractor for RustdocExtractor {
+    fn extract(&self, source: &str) -> Vec<String> {
+        let mut urls = parsing::get_urls(source);
+        urls.extend(parsing::get_refresh_url(source));
+        urls
+    }
+}
+
+/// Is `fragment` a line, or range of lines, in a source page? These
+/// are highlighted by JavaScript, so there's no anchor to look for.
+fn is_line_fragment(fragment: &str) -> bool {
+    let is_line = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    match fragment.split_once('-') {
+        Some((start, end)) => is_line(start) && is_line(end),
+        None => is_line(fragment),
+    }
+}
+
+/// Checks `cargo doc` output. The same as a `DirectoryFetcher` with
+/// anchors checked, except that:
+///
+/// * Links to the latest docs on docs.rs for crates in the output are
+///   checked against the output, so links to your own crate are
+///   checked before it's published.
+/// * Anchors in redirect stubs are looked up in the page they
+///   redirect to.
+/// * Line numbers in source pages are always fine.
+pub struct RustdocFetcher {
+    inner: DirectoryFetcher,
+    crates: HashSet<String>,
+}
+
+impl RustdocFetcher {
+    /// Check the output in `inner`, which documents `crates`.
+    pub fn new(inner: DirectoryFetcher, crates: HashSet<String>) -> Self {
+        RustdocFetcher {
+            inner: inner.check_anchors(true),
+            crates,
+        }
+    }
+
+    /// Where `url` is in the output, if it's a link to the latest
+    /// docs on docs.rs for one of our crates, e.g.
+    /// https://docs.rs/foo-bar/latest/foo_bar/struct.Baz.html. Links to
+    /// a specific version are left alone, since they may well differ.
+    fn local_url(&self, url: &Url) -> Option<Url> {
+        if url.host_str() != Some("docs.rs") {
+            return None;
+        }
+        let mut segments = url.path_segments()?;
+        let _name = segments.next()?;
+        let version = segments.next()?;
+        if version != "latest" && version != "*" {
+            return None;
+        }
+        let rest: Vec<&str> = segments.collect();
+        let krate = rest.first()?;
+        if !self.crates.contains(*krate) {
+            return None;
+        }
+        let mut local = self.inner.base_url().join(&rest.join("/")).ok()?;
+        local.set_fragment(url.fragment());
+        Some(local)
+    }
+
+    /// If `url` is a local redirect stub, where it redirects to, with
+    /// the same fragment.
+    fn follow_stub(&self, url: &Url, timeout: Duration) -> Option<Url> {
+        if !url.as_str().starts_with(self.inner.base_url().as_str()) {
+            return None;
+        }
+        let mut page_url = url.clone();
+        page_url.set_fragment(None);
+        let page = self.inner.fetch(&page_url, timeout).ok()?;
+        // Save parsing every page we link to with an anchor.
+        if !page.body.contains("http-equiv") {
+            return None;
+        }
+        let target = parsing::get_refresh_url(&page.body)?;
+        let mut target = page_url.join(&target).ok()?;
+        target.set_fragment(url.fragment());
+        Some(target)
+    }
+}
+
+impl Fetcher for RustdocFetcher {
+    fn check(&self, referrer: &str, url: &Url, timeout: Duration) -> CheckResult {
+        let mut target = self.local_url(url).unwrap_or_else(|| url.clone());
+        let fragment = target.fragment().unwrap_or_default().to_owned();
+        if is_line_fragment(&fragment) && target.path().contains("/src/") {
+            target.set_fragment(None);
+        } else if !fragment.is_empty() {
+            if let Some(stub_target) = self.follow_stub(&target, timeout) {
+                target = stub_target;
+            }
+        }
+
+        let mut result = self.inner.check(referrer, &target, timeout);
+        // Report the link as it was written.
+        result.url = url.to_string();
+        result
+    }
+
+    fn fetch(&self, url: &Url, timeout: Duration) -> Result<Page, FetchError> {
+        self.inner.fetch(url, timeout)
+    }
+
+    fn checks_fragments(&self) -> bool {
+        self.inner.checks_fragments()
+    }
+}
@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use url::Url;
+
+/// What to do with links using a scheme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemeRule {
+    /// Check them as usual.
+    Check,
+    /// Leave them out of the crawl, as if they were ignored.
+    Skip,
+    /// Report them as broken without requesting them, e.g. `http:`
+    /// links on a site that should only link over HTTPS.
+    Forbid,
+}
+
+impl SchemeRule {
+    pub const NAMES: &'static [&'static str] = &["check", "skip", "forbid"];
+
+    pub fn from_name(name: &str) -> Option<SchemeRule> {
+        match name {
+            "check" => Some(SchemeRule::Check),
+            "skip" => Some(SchemeRule::Skip),
+            "forbid" => Some(SchemeRule::Forbid),
+            _ => None,
+        }
+    }
+}
+
+/// The rule for each URL scheme. Schemes without a rule follow the
+/// rule for `*`, and are checked if there isn't one.
+#[derive(Debug, Clone, Default)]
+pub struct Schemes {
+    /// Keyed by lowercase scheme, without the colon, or `*`.
+    rules: HashMap<String, SchemeRule>,
+}
+
+impl Schemes {
+    /// Parse a rule like `http=forbid`, `mailto=skip` or `*=skip`.
+    pub fn parse_rule(rule: &str) -> Result<(String, SchemeRule), String> {
+        let (scheme, name) = rule
+            .split_once('=')
+            .ok_or_else(|| format!("Expected SCHEME=RULE (e.g. http=forbid), got {}", rule))?;
+        let scheme = scheme.trim().trim_end_matches(':').to_ascii_lowercase();
+        let starts_with_letter = scheme
+            .chars()
+            .next()
+            .map_or(false, |c| c.is_ascii_alphabetic());
+        let valid = scheme == "*"
+            || (starts_with_letter
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c)));
+        if !valid {
+            return Err(format!("Invalid scheme {} in {}", scheme, rule));
+        }
+        match SchemeRule::from_name(name.trim()) {
+            Some(rule) => Ok((scheme, rule)),
+            None => Err(format!(
+                "Unknown rule {}, expected one of: {}",
+                name.trim(),
+                SchemeRule::NAMES.join(", ")
+            )),
+        }
+    }
+
+    pub fn set(&mut self, scheme: &str, rule: SchemeRule) {
+        self.rules.insert(scheme.to_ascii_lowercase(), rule);
+    }
+
+    /// What to do with links to `url`.
+    pub fn rule_for(&self, url: &Url) -> SchemeRule {
+        self.rules
+            .get(url.scheme())
+            .or_else(|| self.rules.get("*"))
+            .copied()
+            .unwrap_or(SchemeRule::Check)
+    }
+}
@@ -0,0 +1,126 @@
+use url::Url;
+
+/// Which URLs are on the site we're crawling, so we fetch them to find
+/// more links.
+///
+/// Hosts are compared case-insensitively and without a trailing dot,
+/// and can be IP addresses. If the start URL gives a port, URLs must be
+/// on that port. Otherwise they must be on the default port for their
+/// scheme, so http:// and https:// links to the site both count.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scope {
+    host: String,
+    port: Option<u16>,
+    ignore_www: bool,
+}
+
+/// `host` in the form we compare it in.
+fn normalize(host: &str) -> String {
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
+
+impl Scope {
+    /// The site of `url`, or None if it doesn't have a host, e.g. a
+    /// `mailto:` URL.
+    pub fn new(url: &Url) -> Option<Scope> {
+        Some(Scope {
+            host: normalize(url.host_str()?),
+            port: url.port(),
+            ignore_www: false,
+        })
+    }
+
+    /// Treat `www.example.com` and `example.com` as the same site.
+    pub fn ignore_www(mut self, ignore_www: bool) -> Self {
+        self.ignore_www = ignore_www;
+        self
+    }
+
+    /// The site's host, e.g. for logging.
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    pub fn contains(&self, url: &Url) -> bool {
+        let host = match url.host_str() {
+            Some(host) => normalize(host),
+            None => return false,
+        };
+        let port_matches = match self.port {
+            Some(port) => url.port_or_known_default() == Some(port),
+            None => url.port().is_none(),
+        };
+        let without_www = |host: &str| host.strip_prefix("www.").unwrap_or(host).to_owned();
+        port_matches
+            && (host == self.host
+                || (self.ignore_www && without_www(&host) == without_www(&self.host)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scope(s: &str) -> Scope {
+        Scope::new(&Url::parse(s).unwrap()).unwrap()
+    }
+
+    fn contains(scope: &Scope, s: &str) -> bool {
+        scope.contains(&Url::parse(s).unwrap())
+    }
+
+    #[test]
+    fn contains_the_same_host() {
+        let site = scope("http://example.com/start");
+        assert!(contains(&site, "http://example.com/other"));
+        assert!(contains(&site, "https://example.com/"));
+        assert!(!contains(&site, "http://example.org/"));
+        assert!(!contains(&site, "http://docs.example.com/"));
+        assert!(!contains(&site, "mailto:someone@example.com"));
+    }
+
+    #[test]
+    fn ignores_case_and_trailing_dots() {
+        let site = scope("http://Example.COM./");
+        assert_eq!(site.host(), "example.com");
+        assert!(contains(&site, "http://EXAMPLE.com/a"));
+        assert!(contains(&site, "http://example.com./a"));
+    }
+
+    #[test]
+    fn needs_the_start_urls_port() {
+        let site = scope("http://example.com:8080/");
+        assert!(contains(&site, "http://example.com:8080/a"));
+        assert!(contains(&site, "https://example.com:8080/a"));
+        assert!(!contains(&site, "http://example.com/a"));
+    }
+
+    #[test]
+    fn needs_the_default_port_otherwise() {
+        let site = scope("http://example.com/");
+        assert!(contains(&site, "http://example.com:80/a"));
+        assert!(contains(&site, "https://example.com:443/a"));
+        assert!(!contains(&site, "http://example.com:8080/a"));
+    }
+
+    #[test]
+    fn only_ignores_www_when_asked() {
+        let site = scope("http://example.com/");
+        assert!(!contains(&site, "http://www.example.com/"));
+
+        let site = site.ignore_www(true);
+        assert!(contains(&site, "http://www.example.com/"));
+        assert!(!contains(&site, "http://www2.example.com/"));
+
+        let site = scope("http://www.example.com/").ignore_www(true);
+        assert!(contains(&site, "http://example.com/"));
+    }
+
+    #[test]
+    fn urls_without_hosts_have_no_scope() {
+        assert_eq!(
+            Scope::new(&Url::parse("mailto:someone@example.com").unwrap()),
+            None
+        );
+    }
+}
@@ -0,0 +1,285 @@
+use serde_json::json;
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tracing::{info, warn};
+use url::Url;
+
+use linkdoc::crawling::Crawler;
+use linkdoc::fetching::{self, CheckResult};
+use linkdoc::ranking;
+use linkdoc::reporting::{self, Report};
+use linkdoc::severity::Severities;
+
+use crate::cli;
+use crate::dashboard::Dashboard;
+
+/// Request bodies larger than this are rejected.
+const MAX_BODY: usize = 64 * 1024;
+
+/// A crawl started through the API.
+#[derive(Debug, Default)]
+struct Job {
+    start_url: String,
+    succeeded: usize,
+    failed: usize,
+    /// The full JSON report, once the crawl has finished.
+    report: Option<serde_json::Value>,
+}
+
+impl Job {
+    fn to_json(&self, id: usize) -> serde_json::Value {
+        json!({
+            "id": id,
+            "start_url": self.start_url,
+            "succeeded": self.succeeded,
+            "failed": self.failed,
+            "finished": self.report.is_some(),
+        })
+    }
+}
+
+/// Settings used for crawls unless the request overrides them.
+#[derive(Debug, Clone)]
+pub struct Defaults {
+    pub concurrency: usize,
+    pub timeout: Duration,
+    pub severities: Severities,
+}
+
+/// An HTTP API for starting crawls and fetching their results.
+///
+/// * `POST /crawls` with `{"url": ..., "concurrency": N, "timeout": SECS}`
+///   starts a crawl and returns its status, including its `id`.
+/// * `GET /crawls` lists every crawl.
+/// * `GET /crawls/ID` returns the progress of a crawl.
+/// * `GET /crawls/ID/report` returns the JSON report of a finished
+///   crawl.
+#[derive(Clone)]
+pub struct Server {
+    jobs: Arc<Mutex<Vec<Job>>>,
+    defaults: Defaults,
+    /// Shows the progress of every crawl, if enabled.
+    dashboard: Option<Dashboard>,
+}
+
+/// An HTTP response: status line and JSON body.
+type Response = (&'static str, serde_json::Value);
+
+fn error(status: &'static str, message: &str) -> Response {
+    (status, json!({ "error": message }))
+}
+
+impl Server {
+    pub fn new(defaults: Defaults, dashboard: Option<Dashboard>) -> Server {
+        Server {
+            jobs: Arc::new(Mutex::new(vec![])),
+            defaults,
+            dashboard,
+        }
+    }
+
+    /// Serve the API on `addr`, handling each connection on its own
+    /// thread. Only returns if we can't listen on `addr`.
+    pub fn run(&self, addr: &str) -> std::io::Result<()> {
+        let listener = TcpListener::bind(addr)?;
+        info!(addr, "serving API");
+        for stream in listener.incoming().flatten() {
+            let server = self.clone();
+            thread::spawn(move || server.handle(stream));
+        }
+        Ok(())
+    }
+
+    fn handle(&self, stream: TcpStream) {
+        let mut reader = BufReader::new(&stream);
+        let (status, body) = match read_request(&mut reader) {
+            Ok((method, path, body)) => self.route(&method, &path, &body),
+            Err(e) => error("400 Bad Request", &e),
+        };
+        let body = body.to_string();
+        let _ = write!(
+            &stream,
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+    }
+
+    fn route(&self, method: &str, path: &str, body: &[u8]) -> Response {
+        let segments: Vec<_> = path.trim_matches('/').split('/').collect();
+        match (method, segments.as_slice()) {
+            ("POST", ["crawls"]) => self.start_crawl(body),
+            ("GET", ["crawls"]) => {
+                let jobs = self.jobs.lock().unwrap();
+                let jobs: Vec<_> = jobs
+                    .iter()
+                    .enumerate()
+                    .map(|(id, job)| job.to_json(id))
+                    .collect();
+                ("200 OK", json!(jobs))
+            }
+            ("GET", ["crawls", id]) => match id.parse::<usize>() {
+                Ok(id) => match self.jobs.lock().unwrap().get(id) {
+                    Some(job) => ("200 OK", job.to_json(id)),
+                    None => error("404 Not Found", "no such crawl"),
+                },
+                Err(_) => error("404 Not Found", "no such crawl"),
+            },
+            ("GET", ["crawls", id, "report"]) => match id.parse::<usize>() {
+                Ok(id) => match self.jobs.lock().unwrap().get(id) {
+                    Some(Job {
+                        report: Some(report),
+                        ..
+                    }) => ("200 OK", report.clone()),
+                    Some(_) => error("409 Conflict", "the crawl hasn't finished yet"),
+                    None => error("404 Not Found", "no such crawl"),
+                },
+                Err(_) => error("404 Not Found", "no such crawl"),
+            },
+            (_, ["crawls"]) | (_, ["crawls", _]) | (_, ["crawls", _, "report"]) => {
+                error("405 Method Not Allowed", "method not allowed")
+            }
+            _ => error("404 Not Found", "not found"),
+        }
+    }
+
+    fn start_crawl(&self, body: &[u8]) -> Response {
+        let request: serde_json::Value = match serde_json::from_slice(body) {
+            Ok(request) => request,
+            Err(e) => return error("400 Bad Request", &format!("invalid JSON: {}", e)),
+        };
+        let start_url = match request["url"].as_str() {
+            Some(url) => url,
+            None => return error("400 Bad Request", "url is required"),
+        };
+        if let Err(e) = cli::is_start_url(start_url.to_owned()) {
+            return error("400 Bad Request", &e);
+        }
+        let start_url = Url::parse(start_url).unwrap();
+
+        let crawler = Crawler::builder()
+            .seed(&start_url)
+            .concurrency(
+                request["concurrency"]
+                    .as_u64()
+                    .map_or(self.defaults.concurrency, |n| n as usize),
+            )
+            .timeout(
+                request["timeout"]
+                    .as_u64()
+                    .map_or(self.defaults.timeout, Duration::from_secs),
+            )
+            .build();
+        let crawler = match crawler {
+            Ok(crawler) => crawler,
+            Err(e) => return error("400 Bad Request", &e.to_string()),
+        };
+
+        let id = {
+            let mut jobs = self.jobs.lock().unwrap();
+            jobs.push(Job {
+                start_url: start_url.to_string(),
+                ..Job::default()
+            });
+            jobs.len() - 1
+        };
+        info!(id, url = %start_url, "starting crawl");
+        if let Some(ref dashboard) = self.dashboard {
+            dashboard.crawling(start_url.as_str());
+        }
+
+        let server = self.clone();
+        thread::spawn(move || server.crawl(id, &start_url, crawler));
+
+        let job = self.jobs.lock().unwrap()[id].to_json(id);
+        ("201 Created", job)
+    }
+
+    /// Run crawl `id`, updating its progress as we go.
+    fn crawl(&self, id: usize, start_url: &Url, mut crawler: Crawler) {
+        let mut report = Report {
+            start_url: start_url.to_string(),
+            severities: self.defaults.severities.clone(),
+            ..Report::default()
+        };
+
+        for url_state in crawler.by_ref() {
+            if let Some(ref dashboard) = self.dashboard {
+                dashboard.record(&url_state, report.severities.of(&url_state));
+            }
+            let mut jobs = self.jobs.lock().unwrap();
+            let job = &mut jobs[id];
+            if url_state.is_accessible() {
+                job.succeeded += 1;
+                report.success_count += 1;
+            } else {
+                job.failed += 1;
+                report.failures.push(url_state);
+            }
+        }
+
+        report.referrers = crawler.referrers();
+        report.depths = crawler.graph().click_depths();
+        if !report.failures.is_empty() {
+            let sitemap: HashSet<_> = match fetching::client(None) {
+                Ok(client) => {
+                    fetching::fetch_sitemap_urls(&client, start_url, self.defaults.timeout)
+                        .into_iter()
+                        .collect()
+                }
+                Err(_) => HashSet::new(),
+            };
+            ranking::rank(&mut report, &sitemap);
+        }
+
+        info!(id, failed = report.failures.len(), "crawl finished");
+        if let Some(ref dashboard) = self.dashboard {
+            dashboard.finish();
+        }
+        self.jobs.lock().unwrap()[id].report = Some(reporting::report_json(&report));
+    }
+}
+
+/// Read an HTTP request, returning its method, path and body.
+fn read_request(reader: &mut impl BufRead) -> Result<(String, String, Vec<u8>), String> {
+    let mut request_line = String::new();
+    reader
+        .read_line(&mut request_line)
+        .map_err(|e| e.to_string())?;
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = match (parts.next(), parts.next()) {
+        (Some(method), Some(path)) => (method.to_owned(), path.to_owned()),
+        _ => return Err("malformed request line".to_owned()),
+    };
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).map_err(|e| e.to_string())?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| "invalid Content-Length".to_owned())?;
+            }
+        }
+    }
+    if content_length > MAX_BODY {
+        warn!(content_length, "request body too large");
+        return Err("request body too large".to_owned());
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(|e| e.to_string())?;
+    Ok((method, path, body))
+}
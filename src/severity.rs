@@ -0,0 +1,124 @@
+use colored::*;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::fetching::{CheckResult, Outcome};
+
+/// How serious a result is. Errors fail the run, warnings fail it
+/// unless `--ignore-warnings` is given, and info is only reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde1",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub const NAMES: &'static [&'static str] = &["error", "warning", "info"];
+
+    pub fn from_name(name: &str) -> Option<Severity> {
+        match name {
+            "error" => Some(Severity::Error),
+            "warning" => Some(Severity::Warning),
+            "info" => Some(Severity::Info),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        }
+    }
+
+    /// The severity name, colored for the terminal.
+    pub fn label(self) -> ColoredString {
+        match self {
+            Severity::Error => self.name().red(),
+            Severity::Warning => self.name().yellow(),
+            Severity::Info => self.name().blue(),
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.name().fmt(f)
+    }
+}
+
+/// The severity of each kind of result, with user overrides.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Severities {
+    /// Keyed by `CheckResult::category` or an HTTP status code (e.g. "404").
+    overrides: HashMap<String, Severity>,
+}
+
+impl Severities {
+    /// Parse a rule like `timed_out=info` or `403=warning`.
+    pub fn parse_rule(rule: &str) -> Result<(String, Severity), String> {
+        let mut parts = rule.splitn(2, '=');
+        let key = parts.next().unwrap_or("");
+        let severity = parts.next().ok_or_else(|| {
+            format!(
+                "Expected CATEGORY=SEVERITY (e.g. timed_out=info), got {}",
+                rule
+            )
+        })?;
+
+        let is_status = key.len() == 3 && key.chars().all(|c| c.is_ascii_digit());
+        if !is_status && !CheckResult::CATEGORIES.contains(&key) {
+            return Err(format!(
+                "Unknown category {}, expected an HTTP status or one of: {}",
+                key,
+                CheckResult::CATEGORIES.join(", ")
+            ));
+        }
+        match Severity::from_name(severity) {
+            Some(severity) => Ok((key.to_owned(), severity)),
+            None => Err(format!(
+                "Unknown severity {}, expected one of: {}",
+                severity,
+                Severity::NAMES.join(", ")
+            )),
+        }
+    }
+
+    pub fn set(&mut self, key: &str, severity: Severity) {
+        self.overrides.insert(key.to_owned(), severity);
+    }
+
+    fn default_for(state: &CheckResult) -> Severity {
+        match state.outcome {
+            Outcome::Accessible => Severity::Info,
+            Outcome::TimedOut
+            | Outcome::Soft404
+            | Outcome::LoginRequired
+            | Outcome::HeaderAssertionFailed
+            | Outcome::ContentTypeMismatch => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+
+    /// The severity of `state`. Rules for a specific HTTP status take
+    /// priority over rules for the whole category.
+    pub fn of(&self, state: &CheckResult) -> Severity {
+        if let (Outcome::BadStatus, Some(status)) = (state.outcome, state.status) {
+            if let Some(severity) = self.overrides.get(status.as_str()) {
+                return *severity;
+            }
+        }
+        match self.overrides.get(state.category()) {
+            Some(severity) => *severity,
+            None => Severities::default_for(state),
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use chrono::DateTime;
+use url::Url;
+
+/// Convert an HTTP Last-Modified header to the W3C date format that
+/// sitemaps use.
+fn lastmod(last_modified: &str) -> Option<String> {
+    let date = DateTime::parse_from_rfc2822(last_modified).ok()?;
+    Some(date.format("%Y-%m-%d").to_string())
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Render a sitemap.xml listing `pages`, along with their
+/// Last-Modified headers.
+pub fn render(pages: &[(Url, Option<String>)]) -> String {
+    let mut pages: Vec<_> = pages.iter().collect();
+    pages.sort_by(|(url1, _), (url2, _)| url1.as_str().cmp(url2.as_str()));
+    pages.dedup_by(|(url1, _), (url2, _)| url1 == url2);
+
+    let mut s = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+    );
+    for (url, last_modified) in pages {
+        s.push_str("  <url>\n");
+        s.push_str(&format!("    <loc>{}</loc>\n", escape_xml(url.as_str())));
+        if let Some(date) = last_modified.as_ref().and_then(|lm| lastmod(lm)) {
+            s.push_str(&format!("    <lastmod>{}</lastmod>\n", date));
+        }
+        s.push_str("  </url>\n");
+    }
+    s.push_str("</urlset>\n");
+    s
+}
@@ -0,0 +1,154 @@
+use scraper::{Html, Selector};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
+use tracing::debug;
+use url::Url;
+
+use crate::fetching::{Fetcher, Page};
+
+/// Phrases in the title or main heading of an error page, matched
+/// ignoring case.
+pub const DEFAULT_PHRASES: &[&str] = &[
+    "page not found",
+    "404 not found",
+    "error 404",
+    "404 error",
+    "not found",
+    "page does not exist",
+    "page doesn't exist",
+    "no longer available",
+    "nothing was found",
+    "nothing found",
+];
+
+/// A page on the site we don't expect to exist, so we can see what the
+/// site's 404 page looks like.
+const PROBE_PATH: &str = "/linkdoc-soft-404-probe-7c1e9b2f";
+
+/// Pages with less than this much in them, ignoring surrounding
+/// whitespace, are almost certainly errors.
+const MIN_BODY_BYTES: usize = 100;
+
+/// Pages sharing at least this proportion of their text with the
+/// site's 404 page are taken to be the 404 page.
+const MIN_SIMILARITY: f64 = 0.9;
+
+/// Spots pages that return 200 but are really error pages: pages whose
+/// title or heading says the page wasn't found, pages that look like
+/// the site's 404 page, and pages with next to nothing in them.
+#[derive(Debug, Clone)]
+pub struct Soft404Detector {
+    /// Lowercase phrases that mark an error page.
+    phrases: Vec<String>,
+    /// The text of the site's 404 page, once we've seen it.
+    not_found_page: Option<HashSet<u64>>,
+}
+
+impl Default for Soft404Detector {
+    fn default() -> Self {
+        Soft404Detector::new(&[])
+    }
+}
+
+impl Soft404Detector {
+    /// Look for `DEFAULT_PHRASES` and `phrases`.
+    pub fn new(phrases: &[String]) -> Soft404Detector {
+        Soft404Detector {
+            phrases: DEFAULT_PHRASES
+                .iter()
+                .copied()
+                .chain(phrases.iter().map(|phrase| phrase.as_str()))
+                .map(|phrase| phrase.to_lowercase())
+                .collect(),
+            not_found_page: None,
+        }
+    }
+
+    /// Learn what the 404 page of the site at `site` looks like, by
+    /// fetching a page that shouldn't exist. Whatever the status, the
+    /// body is the site's error page. We wait up to `timeout` for it.
+    pub fn probe(&mut self, fetcher: &dyn Fetcher, site: &Url, timeout: Duration) {
+        let probe_url = match site.join(PROBE_PATH) {
+            Ok(probe_url) => probe_url,
+            Err(_) => return,
+        };
+        match fetcher.fetch(&probe_url, timeout) {
+            Ok(page) if is_html(&page) && !page.body.trim().is_empty() => {
+                self.not_found_page = Some(shingles(&Html::parse_document(&page.body)));
+            }
+            Ok(_) => {}
+            Err(e) => debug!(url = %probe_url, error = %e, "could not fetch the 404 page"),
+        }
+    }
+
+    /// Why `page`, which was served successfully, looks like an error
+    /// page, if it does.
+    pub fn check(&self, page: &Page) -> Option<String> {
+        if !is_html(page) {
+            return None;
+        }
+        let size = page.body.trim().len();
+        if size < MIN_BODY_BYTES {
+            return Some(format!("the page is almost empty ({} bytes)", size));
+        }
+
+        let document = Html::parse_document(&page.body);
+        let selector = Selector::parse("title, h1").unwrap();
+        let headings = document
+            .select(&selector)
+            .map(|node| node.text().collect::<String>().to_lowercase())
+            .collect::<Vec<_>>()
+            .join(" ");
+        if let Some(phrase) = self.phrases.iter().find(|p| headings.contains(p.as_str())) {
+            return Some(format!("the page says \"{}\"", phrase));
+        }
+
+        if let Some(ref not_found_page) = self.not_found_page {
+            let page = shingles(&document);
+            let shared = page.intersection(not_found_page).count();
+            let total = page.union(not_found_page).count();
+            let similarity = if total == 0 {
+                1.0
+            } else {
+                shared as f64 / total as f64
+            };
+            if similarity >= MIN_SIMILARITY {
+                return Some(format!(
+                    "the page is {:.0}% the same as the site's 404 page",
+                    similarity * 100.0
+                ));
+            }
+        }
+        None
+    }
+}
+
+/// Whether `page` is HTML. Pages without a Content-Type are skipped,
+/// since fetchers leave it out for files they don't read, e.g. images.
+fn is_html(page: &Page) -> bool {
+    page.content_type.as_deref().map_or(false, |content_type| {
+        let content_type = content_type.to_ascii_lowercase();
+        content_type.starts_with("text/html") || content_type.starts_with("application/xhtml")
+    })
+}
+
+/// Hashes of every run of three words in `document`'s text, for
+/// comparing documents while ignoring small differences.
+fn shingles(document: &Html) -> HashSet<u64> {
+    let words: Vec<String> = document
+        .root_element()
+        .text()
+        .flat_map(|text| text.split_whitespace())
+        .map(|word| word.to_lowercase())
+        .collect();
+    words
+        .windows(3)
+        .map(|window| {
+            let mut hasher = DefaultHasher::new();
+            window.hash(&mut hasher);
+            hasher.finish()
+        })
+        .collect()
+}
@@ -0,0 +1,102 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+
+use crate::fetching::CheckResult;
+
+#[derive(Debug, Default)]
+struct HostCounters {
+    checked: AtomicU64,
+    broken: AtomicU64,
+}
+
+/// Live counters for a crawl. Workers update them without waiting on
+/// each other, except the first time we see each host.
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    checked: AtomicU64,
+    broken: AtomicU64,
+    pages_fetched: AtomicU64,
+    bytes_downloaded: AtomicU64,
+    hosts: RwLock<HashMap<String, HostCounters>>,
+}
+
+impl Counters {
+    pub(crate) fn record_result(&self, result: &CheckResult) {
+        let broken = !result.is_accessible();
+        self.checked.fetch_add(1, Ordering::Relaxed);
+        if broken {
+            self.broken.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let host = match result.parsed_url() {
+            Some(url) => url.host_str().unwrap_or_default().to_owned(),
+            None => "(malformed)".to_owned(),
+        };
+        let record = |counters: &HostCounters| {
+            counters.checked.fetch_add(1, Ordering::Relaxed);
+            if broken {
+                counters.broken.fetch_add(1, Ordering::Relaxed);
+            }
+        };
+        if let Some(counters) = self.hosts.read().unwrap().get(&host) {
+            record(counters);
+            return;
+        }
+        record(self.hosts.write().unwrap().entry(host).or_default());
+    }
+
+    pub(crate) fn record_page(&self, bytes: usize) {
+        self.pages_fetched.fetch_add(1, Ordering::Relaxed);
+        self.bytes_downloaded
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self, queue_length: usize) -> Stats {
+        let hosts = self
+            .hosts
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(host, counters)| {
+                let stats = HostStats {
+                    checked: counters.checked.load(Ordering::Relaxed),
+                    broken: counters.broken.load(Ordering::Relaxed),
+                };
+                (host.clone(), stats)
+            })
+            .collect();
+        Stats {
+            checked: self.checked.load(Ordering::Relaxed),
+            broken: self.broken.load(Ordering::Relaxed),
+            pages_fetched: self.pages_fetched.load(Ordering::Relaxed),
+            bytes_downloaded: self.bytes_downloaded.load(Ordering::Relaxed),
+            queue_length,
+            hosts,
+        }
+    }
+}
+
+/// Results for a single host.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HostStats {
+    pub checked: u64,
+    pub broken: u64,
+}
+
+/// A snapshot of a crawl's statistics, see `Crawler::stats`.
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    /// URLs checked so far.
+    pub checked: u64,
+    /// URLs checked so far that weren't accessible.
+    pub broken: u64,
+    /// Pages fetched to find their links.
+    pub pages_fetched: u64,
+    /// The total size of the pages fetched.
+    pub bytes_downloaded: u64,
+    /// URLs waiting to be checked.
+    pub queue_length: usize,
+    /// Results for each host, with malformed URLs under "(malformed)".
+    pub hosts: BTreeMap<String, HostStats>,
+}
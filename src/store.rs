@@ -0,0 +1,553 @@
+use rusqlite::{params, Connection, OptionalExtension, NO_PARAMS};
+use std::cmp::Reverse;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BinaryHeap, HashSet, VecDeque};
+use std::f64::consts::LN_2;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tracing::warn;
+
+use crate::fetching::CheckResult;
+use crate::prioritizing::Priority;
+
+/// A URL waiting to be checked: (referrer, url, depth).
+pub type Queued = (String, String, usize);
+
+/// Where a crawl keeps its state: the URLs it has seen, the URLs
+/// still to check, and the results so far. Workers share one store,
+/// so every method must be safe to call from several threads at once.
+///
+/// Store errors can't stop the crawl, so implementations should log
+/// them and carry on as best they can.
+pub trait CrawlStore: Send + Sync {
+    /// Mark `url` as seen. Returns true if we hadn't seen it before,
+    /// i.e. it should be queued.
+    fn visit(&self, url: &str) -> bool;
+
+    /// Add a URL to the back of the queue.
+    fn push(&self, item: Queued);
+
+    /// Take the URL at the front of the queue, if any.
+    fn pop(&self) -> Option<Queued>;
+
+    /// How many URLs are waiting in the queue.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// We've finished checking `url`, as it was queued. Stores that
+    /// persist their queue use this to know it's no longer in flight.
+    fn finish(&self, _url: &str, _result: &CheckResult) {}
+
+    /// We've decided not to check `url`, as it was queued, e.g.
+    /// because it's ignored. There's no result to record, but stores
+    /// that persist their queue should stop treating it as in flight,
+    /// so it isn't taken again when the crawl resumes.
+    fn skip(&self, _url: &str) {}
+
+    /// Whether the store keeps the crawl's memory use bounded, e.g. by
+    /// keeping its state on disk, or the URLs it has seen in a Bloom
+    /// filter. Crawls with such a store don't keep a link graph either,
+    /// unless asked to with `CrawlerBuilder::record_links`.
+    fn bounds_memory(&self) -> bool {
+        false
+    }
+}
+
+/// A set of strings in a fixed amount of memory, however many are
+/// added. It can wrongly say it already has a string, but never
+/// forgets one.
+#[derive(Debug, Clone)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    hashes: u64,
+}
+
+impl BloomFilter {
+    /// A filter that wrongly claims to have a string with about
+    /// `false_positive_rate` probability once it has `expected`
+    /// strings. It uses about `-1.44 * log2(false_positive_rate)` bits
+    /// per string, e.g. 1.8MB for a million strings at 0.1%.
+    pub fn new(expected: usize, false_positive_rate: f64) -> BloomFilter {
+        let expected = expected.max(1) as f64;
+        let rate = false_positive_rate.clamp(1e-9, 0.5);
+        let bits = (-expected * rate.ln() / (LN_2 * LN_2)).ceil().max(64.0);
+        let hashes = (bits / expected * LN_2).round().max(1.0);
+        BloomFilter {
+            bits: vec![0; (bits as usize).div_ceil(64)],
+            hashes: hashes as u64,
+        }
+    }
+
+    /// Add `s`, returning true if we didn't have it already.
+    pub fn insert(&mut self, s: &str) -> bool {
+        // Derive every bit position from two hashes, see Kirsch and
+        // Mitzenmacher, "Less Hashing, Same Performance".
+        let (h1, h2) = (hash(0, s), hash(1, s) | 1);
+        let len = self.bits.len() as u64 * 64;
+        let mut added = false;
+        for i in 0..self.hashes {
+            let bit = h1.wrapping_add(i.wrapping_mul(h2)) % len;
+            let (word, mask) = ((bit / 64) as usize, 1u64 << (bit % 64));
+            if self.bits[word] & mask == 0 {
+                self.bits[word] |= mask;
+                added = true;
+            }
+        }
+        added
+    }
+}
+
+/// Hash `s`, with `seed` picking one of several unrelated hashes.
+fn hash(seed: u8, s: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (seed, s).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The URLs a `MemoryStore` shard has seen.
+#[derive(Debug)]
+enum Visited {
+    Exact(HashSet<String>),
+    Bloom(BloomFilter),
+}
+
+impl Default for Visited {
+    fn default() -> Self {
+        Visited::Exact(HashSet::new())
+    }
+}
+
+impl Visited {
+    /// Add `url`, returning true if we hadn't seen it.
+    fn insert(&mut self, url: &str) -> bool {
+        match self {
+            Visited::Exact(visited) => visited.insert(url.to_owned()),
+            Visited::Bloom(visited) => visited.insert(url),
+        }
+    }
+
+    fn is_bloom(&self) -> bool {
+        matches!(self, Visited::Bloom(_))
+    }
+}
+
+/// The URLs seen and queued with the same hash, behind their own locks.
+#[derive(Debug, Default)]
+struct Shard {
+    visited: Mutex<Visited>,
+    queue: Mutex<VecDeque<Queued>>,
+}
+
+/// How many shards a `MemoryStore` splits its state into by default.
+pub const DEFAULT_SHARDS: usize = 16;
+
+/// Keep everything in memory. This is the default, and what you want
+/// unless the crawl should outlive the process.
+///
+/// The state is split into shards by URL hash, each with its own locks,
+/// so workers rarely wait for each other. Shards take turns giving out
+/// URLs, so the queue is only roughly first in, first out: with more
+/// than one shard, the crawl isn't strictly breadth first, and a URL
+/// can be checked before others found fewer clicks from the seeds. Use
+/// `MemoryStore::sharded(1)` if the order matters.
+#[derive(Debug)]
+pub struct MemoryStore {
+    shards: Vec<Shard>,
+    /// Where the next `pop` starts looking.
+    next: AtomicUsize,
+    /// How many URLs are queued across all the shards. It's counted
+    /// before a URL is queued, so it's never too low.
+    queued: AtomicUsize,
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        MemoryStore::sharded(DEFAULT_SHARDS)
+    }
+}
+
+impl MemoryStore {
+    /// Split the state into `shards` shards. One shard keeps the queue
+    /// in strict first in, first out order.
+    pub fn sharded(shards: usize) -> MemoryStore {
+        MemoryStore {
+            shards: (0..shards.max(1)).map(|_| Shard::default()).collect(),
+            next: AtomicUsize::new(0),
+            queued: AtomicUsize::new(0),
+        }
+    }
+
+    /// Remember the URLs we've seen in a `BloomFilter` rather than
+    /// keeping every one, so the visited set of a crawl of millions of
+    /// URLs fits in a few megabytes. The catch is that about
+    /// `false_positive_rate` of the URLs are wrongly taken as seen, and
+    /// never checked. Crawls using it don't keep a link graph, unless
+    /// asked to with `CrawlerBuilder::record_links`.
+    pub fn bloom(expected: usize, false_positive_rate: f64) -> MemoryStore {
+        let store = MemoryStore::default();
+        let per_shard = expected / store.shards.len() + 1;
+        for shard in &store.shards {
+            *shard.visited.lock().unwrap() =
+                Visited::Bloom(BloomFilter::new(per_shard, false_positive_rate));
+        }
+        store
+    }
+
+    fn shard(&self, url: &str) -> &Shard {
+        &self.shards[(hash(2, url) % self.shards.len() as u64) as usize]
+    }
+}
+
+impl CrawlStore for MemoryStore {
+    fn visit(&self, url: &str) -> bool {
+        self.shard(url).visited.lock().unwrap().insert(url)
+    }
+
+    fn push(&self, item: Queued) {
+        self.queued.fetch_add(1, Ordering::SeqCst);
+        self.shard(&item.1).queue.lock().unwrap().push_back(item);
+    }
+
+    fn pop(&self) -> Option<Queued> {
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        for i in 0..self.shards.len() {
+            let shard = &self.shards[start.wrapping_add(i) % self.shards.len()];
+            if let Some(item) = shard.queue.lock().unwrap().pop_front() {
+                self.queued.fetch_sub(1, Ordering::SeqCst);
+                return Some(item);
+            }
+        }
+        None
+    }
+
+    fn len(&self) -> usize {
+        self.queued.load(Ordering::SeqCst)
+    }
+
+    fn bounds_memory(&self) -> bool {
+        self.shards[0].visited.lock().unwrap().is_bloom()
+    }
+}
+
+/// A URL in a `PriorityStore`'s queue, with its score and when it was
+/// queued. Higher scores come out first, then earlier URLs.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Ranked(i64, Reverse<u64>, Queued);
+
+/// Keep everything in memory, like `MemoryStore`, but check the queued
+/// URLs with the highest `Priority` score first rather than in the
+/// order they were found, e.g. with `prioritizing::Heuristics`.
+///
+/// Unlike `MemoryStore`, it isn't split into shards: the queue and the
+/// visited set are each behind one lock, since the highest scoring URL
+/// has to be found across the whole queue. Workers rarely wait for it,
+/// since checking a URL takes far longer than queueing it, but with
+/// many workers and fast checks it can become the bottleneck.
+pub struct PriorityStore {
+    priority: Box<dyn Priority>,
+    visited: Mutex<Visited>,
+    queue: Mutex<BinaryHeap<Ranked>>,
+    /// How many URLs have been queued, to keep ties in order.
+    pushed: AtomicU64,
+}
+
+impl PriorityStore {
+    pub fn new(priority: impl Priority + 'static) -> PriorityStore {
+        PriorityStore {
+            priority: Box::new(priority),
+            visited: Mutex::new(Visited::default()),
+            queue: Mutex::new(BinaryHeap::new()),
+            pushed: AtomicU64::new(0),
+        }
+    }
+
+    /// Remember the URLs we've seen in a `BloomFilter`, see
+    /// `MemoryStore::bloom`.
+    pub fn bloom(self, expected: usize, false_positive_rate: f64) -> PriorityStore {
+        *self.visited.lock().unwrap() =
+            Visited::Bloom(BloomFilter::new(expected, false_positive_rate));
+        self
+    }
+}
+
+impl CrawlStore for PriorityStore {
+    fn visit(&self, url: &str) -> bool {
+        self.visited.lock().unwrap().insert(url)
+    }
+
+    fn push(&self, item: Queued) {
+        let score = self.priority.score(&item.1, item.2);
+        let order = self.pushed.fetch_add(1, Ordering::SeqCst);
+        self.queue
+            .lock()
+            .unwrap()
+            .push(Ranked(score, Reverse(order), item));
+    }
+
+    fn pop(&self) -> Option<Queued> {
+        let Ranked(_, _, item) = self.queue.lock().unwrap().pop()?;
+        Some(item)
+    }
+
+    fn len(&self) -> usize {
+        self.queue.lock().unwrap().len()
+    }
+
+    fn bounds_memory(&self) -> bool {
+        self.visited.lock().unwrap().is_bloom()
+    }
+}
+
+const SCHEMA: &str = "
+PRAGMA journal_mode = WAL;
+PRAGMA synchronous = NORMAL;
+CREATE TABLE IF NOT EXISTS visited (
+    url TEXT PRIMARY KEY
+);
+CREATE TABLE IF NOT EXISTS queue (
+    id INTEGER PRIMARY KEY,
+    referrer TEXT NOT NULL,
+    url TEXT NOT NULL,
+    depth INTEGER NOT NULL,
+    taken INTEGER NOT NULL DEFAULT 0
+);
+CREATE TABLE IF NOT EXISTS results (
+    url TEXT NOT NULL,
+    referrer TEXT NOT NULL,
+    outcome TEXT NOT NULL,
+    status INTEGER,
+    error TEXT
+);
+";
+
+/// Keep the crawl state in a SQLite database, so a crawl can be
+/// stopped and resumed later, and needn't fit in memory.
+///
+/// Reopening the database carries on where the last crawl left off:
+/// URLs that were being checked when it stopped are queued again, and
+/// seeds that were already visited aren't.
+pub struct SqliteStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    /// Open (or create) the database at `path`.
+    pub fn open(path: &Path) -> rusqlite::Result<SqliteStore> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+        conn.execute("UPDATE queue SET taken = 0", NO_PARAMS)?;
+        Ok(SqliteStore {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn try_pop(&self) -> rusqlite::Result<Option<Queued>> {
+        let conn = self.conn.lock().unwrap();
+        let row = conn
+            .query_row(
+                "SELECT id, referrer, url, depth FROM queue WHERE taken = 0 ORDER BY id LIMIT 1",
+                NO_PARAMS,
+                |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, i64>(3)?,
+                    ))
+                },
+            )
+            .optional()?;
+        match row {
+            Some((id, referrer, url, depth)) => {
+                conn.execute("UPDATE queue SET taken = 1 WHERE id = ?1", params![id])?;
+                Ok(Some((referrer, url, depth as usize)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn try_finish(&self, url: &str, result: &CheckResult) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO results (url, referrer, outcome, status, error) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                result.url_str(),
+                result.referrer(),
+                result.outcome.name(),
+                result.status.map(|status| i64::from(status.as_u16())),
+                result.error
+            ],
+        )?;
+        conn.execute(
+            "DELETE FROM queue WHERE url = ?1 AND taken = 1",
+            params![url],
+        )?;
+        Ok(())
+    }
+}
+
+impl CrawlStore for SqliteStore {
+    fn visit(&self, url: &str) -> bool {
+        let conn = self.conn.lock().unwrap();
+        match conn.execute(
+            "INSERT OR IGNORE INTO visited (url) VALUES (?1)",
+            params![url],
+        ) {
+            Ok(inserted) => inserted > 0,
+            Err(e) => {
+                warn!(error = %e, "could not record visited URL");
+                true
+            }
+        }
+    }
+
+    fn push(&self, (referrer, url, depth): Queued) {
+        let conn = self.conn.lock().unwrap();
+        if let Err(e) = conn.execute(
+            "INSERT INTO queue (referrer, url, depth) VALUES (?1, ?2, ?3)",
+            params![referrer, url, depth as i64],
+        ) {
+            warn!(error = %e, %url, "could not queue URL");
+        }
+    }
+
+    fn pop(&self) -> Option<Queued> {
+        self.try_pop().unwrap_or_else(|e| {
+            warn!(error = %e, "could not read the queue");
+            None
+        })
+    }
+
+    fn len(&self) -> usize {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT COUNT(*) FROM queue WHERE taken = 0",
+            NO_PARAMS,
+            |row| row.get::<_, i64>(0),
+        )
+        .map(|count| count as usize)
+        .unwrap_or(0)
+    }
+
+    fn finish(&self, url: &str, result: &CheckResult) {
+        if let Err(e) = self.try_finish(url, result) {
+            warn!(error = %e, %url, "could not record result");
+        }
+    }
+
+    fn skip(&self, url: &str) {
+        let conn = self.conn.lock().unwrap();
+        if let Err(e) = conn.execute(
+            "DELETE FROM queue WHERE url = ?1 AND taken = 1",
+            params![url],
+        ) {
+            warn!(error = %e, %url, "could not remove skipped URL");
+        }
+    }
+
+    fn bounds_memory(&self) -> bool {
+        true
+    }
+}
+
+/// Keep the crawl state in Redis, so several processes can share one
+/// crawl. Each process stops once the shared queue is empty and it has
+/// nothing in flight itself.
+#[cfg(feature = "redis")]
+pub struct RedisStore {
+    conn: Mutex<redis::Connection>,
+    /// Prepended to every key, so several crawls can share a server.
+    prefix: String,
+}
+
+#[cfg(feature = "redis")]
+impl RedisStore {
+    /// Connect to the server at `url`, e.g. `redis://127.0.0.1/`, and
+    /// use the keys starting with `prefix`.
+    pub fn open(url: &str, prefix: &str) -> redis::RedisResult<RedisStore> {
+        let conn = redis::Client::open(url)?.get_connection()?;
+        Ok(RedisStore {
+            conn: Mutex::new(conn),
+            prefix: prefix.to_owned(),
+        })
+    }
+
+    fn key(&self, name: &str) -> String {
+        format!("{}:{}", self.prefix, name)
+    }
+}
+
+#[cfg(feature = "redis")]
+impl CrawlStore for RedisStore {
+    fn visit(&self, url: &str) -> bool {
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd("SADD")
+            .arg(self.key("visited"))
+            .arg(url)
+            .query::<i64>(&mut *conn)
+            .map(|added| added > 0)
+            .unwrap_or_else(|e| {
+                warn!(error = %e, "could not record visited URL");
+                true
+            })
+    }
+
+    fn push(&self, item: Queued) {
+        let mut conn = self.conn.lock().unwrap();
+        let item = serde_json::to_string(&item).unwrap();
+        if let Err(e) = redis::cmd("RPUSH")
+            .arg(self.key("queue"))
+            .arg(item)
+            .query::<i64>(&mut *conn)
+        {
+            warn!(error = %e, "could not queue URL");
+        }
+    }
+
+    fn pop(&self) -> Option<Queued> {
+        let mut conn = self.conn.lock().unwrap();
+        let item = redis::cmd("LPOP")
+            .arg(self.key("queue"))
+            .query::<Option<String>>(&mut *conn)
+            .unwrap_or_else(|e| {
+                warn!(error = %e, "could not read the queue");
+                None
+            })?;
+        serde_json::from_str(&item).ok()
+    }
+
+    fn len(&self) -> usize {
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd("LLEN")
+            .arg(self.key("queue"))
+            .query::<usize>(&mut *conn)
+            .unwrap_or(0)
+    }
+
+    fn finish(&self, url: &str, result: &CheckResult) {
+        let mut conn = self.conn.lock().unwrap();
+        let result = serde_json::json!({
+            "url": result.url_str(),
+            "referrer": result.referrer(),
+            "outcome": result.outcome.name(),
+            "status": result.status.map(|status| status.as_u16()),
+            "error": result.error,
+        });
+        if let Err(e) = redis::cmd("RPUSH")
+            .arg(self.key("results"))
+            .arg(result.to_string())
+            .query::<i64>(&mut *conn)
+        {
+            warn!(error = %e, %url, "could not record result");
+        }
+    }
+
+    fn bounds_memory(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,94 @@
+use colored::*;
+use std::fs::OpenOptions;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::process::Command;
+
+use linkdoc::reporting::Report;
+
+/// Where we record links accepted as broken, unless a baseline file
+/// is configured.
+pub const DEFAULT_BASELINE_PATH: &str = "linkdoc-baseline.txt";
+
+/// Where we record links to fix, unless configured otherwise.
+pub const DEFAULT_FIX_LIST_PATH: &str = "linkdoc-fixes.txt";
+
+/// Open `url` in the user's browser.
+fn open_in_browser(url: &str) -> io::Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(&["/C", "start", ""]);
+        command
+    } else {
+        Command::new("xdg-open")
+    };
+    command.arg(url).spawn().map(|_| ())
+}
+
+fn append_line(path: &Path, line: &str) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)
+}
+
+/// Step through every broken link in `report`, asking the user what
+/// to do with each one. Accepted links are added to the baseline file
+/// at `baseline_path`, and links to fix are added to the fix list at
+/// `fix_list_path`, along with the pages linking to them.
+pub fn triage(report: &Report, baseline_path: &Path, fix_list_path: &Path) -> io::Result<()> {
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let total = report.failures.len();
+
+    for (i, state) in report.failures.iter().enumerate() {
+        let referrers = report.referrers_of(state);
+        eprintln!();
+        eprintln!(
+            "[{}/{}] {} ({})",
+            i + 1,
+            total,
+            state.url_str().bold(),
+            state.reason()
+        );
+        for referrer in &referrers {
+            eprintln!("    linked from {}", referrer);
+        }
+
+        loop {
+            eprint!("[o]pen, [b]aseline, [f]ix later, [s]kip, [q]uit? ");
+            io::stderr().flush()?;
+            let answer = match lines.next() {
+                Some(line) => line?,
+                // Treat end of input like quitting.
+                None => return Ok(()),
+            };
+
+            match answer.trim() {
+                "o" => {
+                    if let Err(e) = open_in_browser(state.url_str()) {
+                        eprintln!("Could not open a browser: {}", e);
+                    }
+                    // Ask again, now they've seen it.
+                    continue;
+                }
+                "b" => {
+                    append_line(baseline_path, state.url_str())?;
+                    eprintln!("Added to {}", baseline_path.display());
+                }
+                "f" => {
+                    append_line(
+                        fix_list_path,
+                        &format!("{}\t{}", state.url_str(), referrers.join(" ")),
+                    )?;
+                    eprintln!("Added to {}", fix_list_path.display());
+                }
+                "s" | "" => {}
+                "q" => return Ok(()),
+                _ => continue,
+            }
+            break;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,312 @@
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Table, TableState, Wrap};
+use ratatui::{Frame, Terminal};
+use std::collections::HashMap;
+use std::io::{self, stdout};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use linkdoc::fetching::CheckResult;
+use linkdoc::severity::Severity;
+
+/// How long we wait for a key press before redrawing.
+const TICK: Duration = Duration::from_millis(200);
+
+/// One checked URL.
+struct Entry {
+    url: String,
+    referrer: String,
+    category: &'static str,
+    severity: Severity,
+    reason: String,
+    duration: Option<Duration>,
+}
+
+#[derive(Default)]
+struct Progress {
+    site: String,
+    entries: Vec<Entry>,
+    /// Every page linking to each URL, once the crawl has finished.
+    referrers: HashMap<String, Vec<String>>,
+    finished: bool,
+}
+
+/// What the user is looking at.
+#[derive(Default)]
+struct View {
+    table: TableState,
+    filter: String,
+    editing_filter: bool,
+    failures_only: bool,
+    show_details: bool,
+}
+
+impl View {
+    fn shows(&self, entry: &Entry) -> bool {
+        (!self.failures_only || entry.category != "accessible")
+            && (self.filter.is_empty() || entry.url.contains(&self.filter))
+    }
+}
+
+/// A terminal UI showing crawl results as they arrive, with a
+/// filterable table and a details pane for the selected URL.
+pub struct Tui {
+    progress: Arc<Mutex<Progress>>,
+    ui_thread: thread::JoinHandle<io::Result<()>>,
+}
+
+impl Tui {
+    /// Take over the terminal and start drawing in the background.
+    pub fn start(site: &str) -> Tui {
+        let progress = Arc::new(Mutex::new(Progress {
+            site: site.to_owned(),
+            ..Progress::default()
+        }));
+        let ui_progress = progress.clone();
+        let ui_thread = thread::spawn(move || run(&ui_progress));
+        Tui {
+            progress,
+            ui_thread,
+        }
+    }
+
+    pub fn record(&self, state: &CheckResult, severity: Severity) {
+        self.progress.lock().unwrap().entries.push(Entry {
+            url: state.url_str().to_owned(),
+            referrer: state.referrer().to_owned(),
+            category: state.category(),
+            severity,
+            reason: state.reason(),
+            duration: state.duration,
+        });
+    }
+
+    /// Mark the crawl as finished, and wait for the user to quit.
+    pub fn finish(self, referrers: HashMap<String, Vec<String>>) -> io::Result<()> {
+        {
+            let mut progress = self.progress.lock().unwrap();
+            progress.referrers = referrers;
+            progress.finished = true;
+        }
+        self.ui_thread.join().unwrap()
+    }
+}
+
+fn run(progress: &Mutex<Progress>) -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(stdout(), EnterAlternateScreen)?;
+    let result = Terminal::new(CrosstermBackend::new(stdout()))
+        .and_then(|mut terminal| event_loop(&mut terminal, progress));
+    // Always give the terminal back, even if drawing failed.
+    disable_raw_mode()?;
+    execute!(stdout(), LeaveAlternateScreen)?;
+    result
+}
+
+fn event_loop(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    progress: &Mutex<Progress>,
+) -> io::Result<()> {
+    let mut view = View::default();
+    loop {
+        {
+            let progress = progress.lock().unwrap();
+            terminal.draw(|frame| draw(frame, &progress, &mut view))?;
+        }
+
+        if !event::poll(TICK)? {
+            continue;
+        }
+        let key = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            _ => continue,
+        };
+
+        if view.editing_filter {
+            match key.code {
+                KeyCode::Enter => view.editing_filter = false,
+                KeyCode::Esc => {
+                    view.editing_filter = false;
+                    view.filter.clear();
+                }
+                KeyCode::Backspace => {
+                    view.filter.pop();
+                }
+                KeyCode::Char(c) => view.filter.push(c),
+                _ => {}
+            }
+            view.table.select(Some(0));
+            continue;
+        }
+
+        let selected = view.table.selected().unwrap_or(0);
+        match key.code {
+            KeyCode::Char('q') => return Ok(()),
+            KeyCode::Down | KeyCode::Char('j') => view.table.select(Some(selected + 1)),
+            KeyCode::Up | KeyCode::Char('k') => view.table.select(Some(selected.saturating_sub(1))),
+            KeyCode::PageDown => view.table.select(Some(selected + 20)),
+            KeyCode::PageUp => view.table.select(Some(selected.saturating_sub(20))),
+            KeyCode::Enter => view.show_details = !view.show_details,
+            KeyCode::Char('/') => view.editing_filter = true,
+            KeyCode::Char('f') => {
+                view.failures_only = !view.failures_only;
+                view.table.select(Some(0));
+            }
+            _ => {}
+        }
+    }
+}
+
+fn severity_style(entry: &Entry) -> Style {
+    match (entry.category, entry.severity) {
+        ("accessible", _) => Style::default().fg(Color::Green),
+        (_, Severity::Error) => Style::default().fg(Color::Red),
+        (_, Severity::Warning) => Style::default().fg(Color::Yellow),
+        (_, Severity::Info) => Style::default().fg(Color::Blue),
+    }
+}
+
+fn draw(frame: &mut Frame, progress: &Progress, view: &mut View) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .split(frame.size());
+
+    let failed = progress
+        .entries
+        .iter()
+        .filter(|entry| entry.category != "accessible")
+        .count();
+    let header = format!(
+        "{}  Succeeded: {}  Failed: {}  ({})",
+        progress.site,
+        progress.entries.len() - failed,
+        failed,
+        if progress.finished {
+            "finished"
+        } else {
+            "crawling..."
+        }
+    );
+    frame.render_widget(
+        Paragraph::new(header).style(Style::default().add_modifier(Modifier::BOLD)),
+        rows[0],
+    );
+
+    let entries: Vec<&Entry> = progress
+        .entries
+        .iter()
+        .filter(|entry| view.shows(entry))
+        .collect();
+    // Keep the selection on the table, even as it's filtered.
+    let selected = view
+        .table
+        .selected()
+        .unwrap_or(0)
+        .min(entries.len().saturating_sub(1));
+    view.table.select(Some(selected));
+
+    let columns = if view.show_details {
+        Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(rows[1])
+    } else {
+        Layout::default()
+            .constraints([Constraint::Percentage(100)])
+            .split(rows[1])
+    };
+
+    let table_rows = entries.iter().map(|entry| {
+        let severity = if entry.category == "accessible" {
+            "ok"
+        } else {
+            entry.severity.name()
+        };
+        Row::new(vec![
+            severity.to_owned(),
+            entry.category.to_owned(),
+            entry.url.clone(),
+        ])
+        .style(severity_style(entry))
+    });
+    let table = Table::new(
+        table_rows,
+        [
+            Constraint::Length(8),
+            Constraint::Length(18),
+            Constraint::Min(10),
+        ],
+    )
+    .header(
+        Row::new(vec!["Result", "Category", "URL"])
+            .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .block(Block::default().borders(Borders::ALL).title("Results"))
+    .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(table, columns[0], &mut view.table);
+
+    if view.show_details {
+        let lines = match entries.get(selected) {
+            Some(entry) => details(entry, progress),
+            None => vec![],
+        };
+        let details = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title("Details"))
+            .wrap(Wrap { trim: false });
+        frame.render_widget(details, columns[1]);
+    }
+
+    let footer = if view.editing_filter {
+        format!("Filter: {}_", view.filter)
+    } else {
+        format!(
+            "q quit  ↑/↓ move  enter details  / filter{}  f {}",
+            if view.filter.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", view.filter)
+            },
+            if view.failures_only {
+                "show all"
+            } else {
+                "failures only"
+            }
+        )
+    };
+    frame.render_widget(Paragraph::new(footer), rows[2]);
+}
+
+fn details<'a>(entry: &'a Entry, progress: &'a Progress) -> Vec<Line<'a>> {
+    let mut lines = vec![
+        Line::from(entry.url.as_str()),
+        Line::from(""),
+        Line::from(format!("Result: {}", entry.reason)),
+        Line::from(format!("Severity: {}", entry.severity)),
+        Line::from(format!("Category: {}", entry.category)),
+    ];
+    if let Some(duration) = entry.duration {
+        lines.push(Line::from(format!("Time: {}ms", duration.as_millis())));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from("Linked from:"));
+    match progress.referrers.get(&entry.url) {
+        Some(pages) => lines.extend(pages.iter().map(|page| Line::from(format!("  {}", page)))),
+        None => lines.push(Line::from(format!("  {}", entry.referrer))),
+    }
+    lines
+}
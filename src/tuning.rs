@@ -0,0 +1,84 @@
+use std::time::Duration;
+use url::Url;
+
+use crate::ignoring;
+
+/// A setting for the URLs matching a glob, matched against the whole
+/// URL or, if it starts with `/`, the path.
+#[derive(Debug, Clone)]
+struct Rule<T> {
+    pattern: Vec<char>,
+    value: T,
+}
+
+impl<T: Copy> Rule<T> {
+    fn applies_to(&self, url: &Url) -> bool {
+        let target: Vec<char> = if self.pattern.first() == Some(&'/') {
+            url.path().chars().collect()
+        } else {
+            url.as_str().chars().collect()
+        };
+        ignoring::matches(&self.pattern, &target)
+    }
+}
+
+/// The value of the most specific rule for `url`, i.e. the one with the
+/// longest pattern.
+fn most_specific<T: Copy>(rules: &[Rule<T>], url: &Url) -> Option<T> {
+    rules
+        .iter()
+        .filter(|rule| rule.applies_to(url))
+        .max_by_key(|rule| rule.pattern.len())
+        .map(|rule| rule.value)
+}
+
+/// Timeouts and retries for URLs matching patterns, e.g. a slow
+/// `/export/*` endpoint, overriding the crawl's.
+#[derive(Debug, Clone, Default)]
+pub struct Tuning {
+    timeouts: Vec<Rule<Duration>>,
+    retries: Vec<Rule<usize>>,
+}
+
+impl Tuning {
+    /// Wait `timeout` for URLs matching `pattern`.
+    pub fn timeout(mut self, pattern: &str, timeout: Duration) -> Self {
+        self.timeouts.push(Rule {
+            pattern: pattern.chars().collect(),
+            value: timeout,
+        });
+        self
+    }
+
+    /// Check URLs matching `pattern` up to `retries` more times if they
+    /// aren't accessible.
+    pub fn retries(mut self, pattern: &str, retries: usize) -> Self {
+        self.retries.push(Rule {
+            pattern: pattern.chars().collect(),
+            value: retries,
+        });
+        self
+    }
+
+    /// How long to wait for `url`, if a rule says. When several rules
+    /// match, the one with the longest pattern wins.
+    pub fn timeout_for(&self, url: &Url) -> Option<Duration> {
+        most_specific(&self.timeouts, url)
+    }
+
+    /// How many times to retry `url`, if a rule says.
+    pub fn retries_for(&self, url: &Url) -> Option<usize> {
+        most_specific(&self.retries, url)
+    }
+}
+
+/// Parse a `PATTERN=NUMBER` rule from the command line.
+pub fn parse_rule(rule: &str) -> Result<(String, u64), String> {
+    let invalid = || format!("Invalid rule {}, expected e.g. \"/export/*=60\"", rule);
+    let (pattern, number) = rule.rsplit_once('=').ok_or_else(invalid)?;
+    if pattern.trim().is_empty() {
+        return Err(invalid());
+    }
+    let number = number.trim().parse().map_err(|_| invalid())?;
+    Ok((pattern.trim().to_owned(), number))
+}
@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tracing::debug;
+use url::Url;
+
+use crate::fetching::Fetcher;
+
+/// `url` over HTTPS, if it's an `http://` URL.
+pub fn https_version(url: &Url) -> Option<Url> {
+    if url.scheme() != "http" {
+        return None;
+    }
+    let mut https = url.clone();
+    // The default port goes with the scheme, but an explicit port is
+    // only served over plain HTTP.
+    if https.port().is_some() {
+        return None;
+    }
+    https.set_scheme("https").ok()?;
+    Some(https)
+}
+
+/// Check the HTTPS version of each of `urls`, `concurrency` at a time,
+/// and return the ones that work, keyed by the original URL. A version
+/// that redirects back to `http://` doesn't count.
+pub fn https_upgrades(
+    fetcher: &dyn Fetcher,
+    urls: &[Url],
+    timeout: Duration,
+    concurrency: usize,
+) -> HashMap<String, String> {
+    let upgrades = Mutex::new(HashMap::new());
+    let next = Mutex::new(urls.iter());
+    thread::scope(|scope| {
+        for _ in 0..concurrency.clamp(1, urls.len().max(1)) {
+            scope.spawn(|| loop {
+                let url = match next.lock().unwrap().next() {
+                    Some(url) => url,
+                    None => break,
+                };
+                let https = match https_version(url) {
+                    Some(https) => https,
+                    None => continue,
+                };
+                let result = fetcher.check("", &https, timeout);
+                let secure = result
+                    .redirects
+                    .last()
+                    .map_or(true, |last| last.starts_with("https://"));
+                if result.is_accessible() && secure {
+                    upgrades
+                        .lock()
+                        .unwrap()
+                        .insert(url.to_string(), https.to_string());
+                } else {
+                    debug!(url = %https, reason = %result.reason(), "can't upgrade to HTTPS");
+                }
+            });
+        }
+    });
+    upgrades.into_inner().unwrap()
+}
@@ -0,0 +1,43 @@
+use reqwest::Client;
+use std::collections::HashMap;
+use tracing::{debug, warn};
+use url::Url;
+
+/// The Internet Archive's availability API, see
+/// https://archive.org/help/wayback_api.php.
+const AVAILABILITY_API: &str = "https://archive.org/wayback/available";
+
+/// The most recent snapshot of `url` in the Wayback Machine, if there
+/// is one and the page worked when it was archived.
+pub fn closest_snapshot(client: &Client, url: &str) -> Result<Option<String>, reqwest::Error> {
+    let api = Url::parse_with_params(AVAILABILITY_API, &[("url", url)]).unwrap();
+    let response: serde_json::Value = client.get(api.as_str()).send()?.json()?;
+    let closest = &response["archived_snapshots"]["closest"];
+    let worked = closest["status"]
+        .as_str()
+        .map_or(false, |status| status.starts_with('2'));
+    if closest["available"].as_bool() != Some(true) || !worked {
+        return Ok(None);
+    }
+    // The API gives http:// URLs, but the archive is served over HTTPS.
+    Ok(closest["url"]
+        .as_str()
+        .map(|snapshot| snapshot.replacen("http://", "https://", 1)))
+}
+
+/// The closest snapshot of each of `urls` that the Wayback Machine
+/// has. We ask about one URL at a time, to go easy on the API, and
+/// skip URLs we can't look up.
+pub fn snapshots(client: &Client, urls: &[&str]) -> HashMap<String, String> {
+    let mut snapshots = HashMap::new();
+    for url in urls {
+        match closest_snapshot(client, url) {
+            Ok(Some(snapshot)) => {
+                snapshots.insert((*url).to_owned(), snapshot);
+            }
+            Ok(None) => debug!(%url, "no snapshot in the Wayback Machine"),
+            Err(e) => warn!(%url, error = %e, "could not query the Wayback Machine"),
+        }
+    }
+    snapshots
+}
@@ -0,0 +1,222 @@
+//! The crawl must only finish once every queued URL has been checked,
+//! however the workers' checks interleave.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use linkdoc::fetching::Page;
+use linkdoc::store::MemoryStore;
+use linkdoc::{CheckResult, Crawler, FetchError, Fetcher, Outcome};
+use url::Url;
+
+/// Pages from here on aren't linked from anywhere, so are only found
+/// as seeds.
+const LINKED_PAGES: usize = 100;
+
+fn page_url(i: usize) -> String {
+    format!("http://example.com/p{}", i)
+}
+
+/// The links on page `i`: a few other pages, some of them already
+/// seen, and a link to another site.
+fn links(i: usize) -> Vec<String> {
+    if i >= LINKED_PAGES {
+        return vec![page_url(0)];
+    }
+    vec![
+        page_url((i + 1) % LINKED_PAGES),
+        page_url((i * 7 + 3) % LINKED_PAGES),
+        page_url((i * 13) % LINKED_PAGES),
+        format!("http://other.example/{}", i % 10),
+    ]
+}
+
+/// Serves the fake site, taking a pseudo-random time over each request
+/// so the workers finish in a different order on every run.
+struct SlowSite {
+    seed: u64,
+    /// Checks and fetches that have started but not finished.
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl SlowSite {
+    fn pause(&self, url: &Url) {
+        let mut hasher = DefaultHasher::new();
+        (self.seed, url.as_str()).hash(&mut hasher);
+        thread::sleep(Duration::from_micros(hasher.finish() % 2000));
+    }
+}
+
+impl Fetcher for SlowSite {
+    fn check(&self, referrer: &str, url: &Url, _timeout: Duration) -> CheckResult {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        self.pause(url);
+        let outcome = if url.host_str() == Some("example.com") {
+            Outcome::Accessible
+        } else {
+            Outcome::BadStatus
+        };
+        let state = CheckResult::new(referrer, url.as_str(), outcome);
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        state
+    }
+
+    fn fetch(&self, url: &Url, _timeout: Duration) -> Result<Page, FetchError> {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        self.pause(url);
+        let i: usize = url.path().trim_start_matches("/p").parse().unwrap();
+        let anchors: Vec<_> = links(i)
+            .iter()
+            .map(|link| format!("<a href=\"{}\">link</a>", link))
+            .collect();
+        let page = Page {
+            content_type: Some("text/html".to_owned()),
+            body: format!("<html><body>{}</body></html>", anchors.concat()),
+        };
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        Ok(page)
+    }
+}
+
+/// Every URL the crawl should check, starting from `seeds`.
+fn reachable(seeds: &[usize]) -> HashSet<String> {
+    let mut seen: HashSet<String> = seeds.iter().map(|&i| page_url(i)).collect();
+    let mut queue: VecDeque<usize> = seeds.iter().cloned().collect();
+    while let Some(i) = queue.pop_front() {
+        for link in links(i) {
+            if seen.insert(link.clone()) {
+                if let Some(j) = link.strip_prefix("http://example.com/p") {
+                    queue.push_back(j.parse().unwrap());
+                }
+            }
+        }
+    }
+    seen
+}
+
+#[test]
+fn crawl_checks_every_url_exactly_once() {
+    let seeds = [0, 100, 105, 119];
+    let expected = reachable(&seeds);
+
+    for run in 0..25 {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let mut builder = Crawler::builder().concurrency(16).fetcher(SlowSite {
+            seed: run,
+            in_flight: in_flight.clone(),
+        });
+        for &i in &seeds {
+            builder = builder.seed(&Url::parse(&page_url(i)).unwrap());
+        }
+        let crawler = builder.build().unwrap();
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for state in crawler {
+            *counts.entry(state.url_str().to_owned()).or_default() += 1;
+        }
+
+        assert_eq!(
+            in_flight.load(Ordering::SeqCst),
+            0,
+            "run {}: the crawl finished while URLs were still being checked",
+            run
+        );
+        let checked: HashSet<String> = counts.keys().cloned().collect();
+        let missing: Vec<_> = expected.difference(&checked).collect();
+        assert!(
+            missing.is_empty(),
+            "run {}: never checked {:?}",
+            run,
+            missing
+        );
+        let unexpected: Vec<_> = checked.difference(&expected).collect();
+        assert!(
+            unexpected.is_empty(),
+            "run {}: checked {:?}",
+            run,
+            unexpected
+        );
+        let repeated: Vec<_> = counts.iter().filter(|(_, &count)| count > 1).collect();
+        assert!(
+            repeated.is_empty(),
+            "run {}: checked twice {:?}",
+            run,
+            repeated
+        );
+    }
+}
+
+#[test]
+fn crawl_survives_panicking_hooks() {
+    let seeds = [0];
+    let expected = reachable(&seeds);
+    let panicky = page_url(7);
+
+    for run in 0..5 {
+        let panicky_hook = panicky.clone();
+        let crawler = Crawler::builder()
+            .concurrency(16)
+            .fetcher(SlowSite {
+                seed: run,
+                in_flight: Arc::new(AtomicUsize::new(0)),
+            })
+            .seed(&Url::parse(&page_url(0)).unwrap())
+            .on_result(move |state| {
+                if state.url_str() == panicky_hook {
+                    panic!("result hook failed");
+                }
+            })
+            .on_error(|_| panic!("error hook failed"))
+            .build()
+            .unwrap();
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for state in crawler {
+            let crashed = state
+                .error
+                .as_deref()
+                .map_or(false, |error| error.contains("hook failed"));
+            let should_crash = state.url_str() == panicky || !state.is_accessible();
+            assert_eq!(crashed, should_crash, "run {}: {:?}", run, state);
+            *counts.entry(state.url_str().to_owned()).or_default() += 1;
+        }
+
+        let checked: HashSet<String> = counts.keys().cloned().collect();
+        assert_eq!(checked, expected, "run {}", run);
+        assert!(
+            counts.values().all(|&count| count == 1),
+            "run {}: checked twice",
+            run
+        );
+    }
+}
+
+#[test]
+fn bounded_stores_skip_the_link_graph() {
+    let crawl = |record_links: Option<bool>| {
+        let mut builder = Crawler::builder()
+            .fetcher(SlowSite {
+                seed: 0,
+                in_flight: Arc::new(AtomicUsize::new(0)),
+            })
+            .seed(&Url::parse(&page_url(0)).unwrap())
+            .store(MemoryStore::bloom(1000, 0.001));
+        if let Some(record_links) = record_links {
+            builder = builder.record_links(record_links);
+        }
+        let mut crawler = builder.build().unwrap();
+        let checked = crawler.by_ref().count();
+        (checked, crawler.links().len())
+    };
+
+    let (checked, links) = crawl(None);
+    assert_eq!(checked, reachable(&[0]).len());
+    assert_eq!(links, 0);
+    let (_, links) = crawl(Some(true));
+    assert_eq!(links, 4 * LINKED_PAGES);
+}